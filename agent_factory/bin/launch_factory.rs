@@ -26,13 +26,15 @@ use agent_models::factory::config::FactoryMcpRuntimeConfig;
 // Invokers
 use resource_invoker::McpRuntimeToolInvoker;
 use resource_invoker::GreetTask;
-use resource_invoker::A2AAgentInvoker;
+use resource_invoker::{A2AAgentInvoker, DEFAULT_HEALTH_PROBE_INTERVAL};
 use resource_invoker::McpRuntimeToolInvoker as McpRuntimeTools;
 
 use workflow_management::agent_communication::agent_invoker::AgentInvoker;
 use workflow_management::tasks::task_invoker::TaskInvoker;
 use workflow_management::tools::tool_invoker::ToolInvoker;
 use executor_agent::business_logic::executor_agent::WorkFlowInvokers;
+use workflow_management::graph::Capabilities;
+use workflow_management::graph::workflow_registry::WorkflowRegistry;
 
 use agent_service_adapters::{AgentEvaluationServiceAdapter, AgentMemoryServiceAdapter,AgentDiscoveryServiceAdapter};
 
@@ -51,12 +53,19 @@ struct Args {
     /// MCP Config
     #[clap(long, default_value = "./configuration/mcp_runtime_config.toml")]
     mcp_config_path: String,
+    /// Optional list of MCP servers (TOML format), used instead of the server of the MCP config
+    #[clap(long)]
+    mcp_servers_config_file: Option<String>,
     #[clap(long, default_value = "http://127.0.0.1:4000")]
     discovery_service_url: String,
     #[clap(long, default_value = "http://127.0.0.1:5000")]
     memory_service_url: String,
     #[clap(long, default_value = "http://127.0.0.1:7000")]
     evaluation_service_url: String,
+    /// Directory of the saved workflows `sub_workflow` activities may run by name (file name without extension).
+    /// Plans referring to a workflow by name are rejected when not set.
+    #[clap(long)]
+    workflow_dir: Option<String>,
 }
 
 /***********************************************************************************/
@@ -98,8 +107,8 @@ async fn setup_task_invoker() -> anyhow::Result<Arc<dyn TaskInvoker>> {
     Ok(greet_task_invoker)
 }
 
-async fn setup_tool_invoker(mcp_config_path: String) -> anyhow::Result<Arc<dyn ToolInvoker>> {
-    let mcp_tool_invoker = McpRuntimeToolInvoker::new(mcp_config_path).await?;
+async fn setup_tool_invoker(mcp_config_path: String, mcp_servers_config_file: Option<&str>) -> anyhow::Result<Arc<dyn ToolInvoker>> {
+    let mcp_tool_invoker = McpRuntimeToolInvoker::from_config_files(mcp_config_path, mcp_servers_config_file).await?;
     let mcp_tool_invoker = Arc::new(mcp_tool_invoker);
 
     Ok(mcp_tool_invoker)
@@ -108,6 +117,8 @@ async fn setup_tool_invoker(mcp_config_path: String) -> anyhow::Result<Arc<dyn T
 
 async fn setup_agent_invoker_v2( discovery_service_adapter: Arc<dyn DiscoveryService>) -> anyhow::Result<Arc<dyn AgentInvoker>> {
     let a2a_agent_invoker = A2AAgentInvoker::new_with_discovery(None, None, discovery_service_adapter).await?;
+    // Detects agents going down or coming back between two refreshes
+    a2a_agent_invoker.start_health_probes(DEFAULT_HEALTH_PROBE_INTERVAL);
     let a2a_agent_invoker = Arc::new(a2a_agent_invoker);
 
     Ok(a2a_agent_invoker)
@@ -124,7 +135,7 @@ async fn setup_agent_invoker_v2( discovery_service_adapter: Arc<dyn DiscoverySer
 
  
 /// Register Tasks in Discovery Service
-async fn register_tasks(discovery_service: Arc<dyn DiscoveryService>) -> anyhow::Result<()> {
+async fn register_tasks(discovery_service: Arc<dyn DiscoveryService>) -> anyhow::Result<Vec<String>> {
 
     let task_definition=TaskDefinition {
         id: "greeting".to_string(),
@@ -134,17 +145,18 @@ async fn register_tasks(discovery_service: Arc<dyn DiscoveryService>) -> anyhow:
         output_schema: json!({}),
     };
     discovery_service.register_task(&task_definition).await?;
-    Ok(())
+    Ok(vec![task_definition.id])
 }
 
 
 /// Register Tools in Discovery Service
-async fn register_tools(mcp_config_path: String,discovery_service: Arc<dyn DiscoveryService>) -> anyhow::Result<()> {
+async fn register_tools(mcp_config_path: String, mcp_servers_config_file: Option<&str>, discovery_service: Arc<dyn DiscoveryService>) -> anyhow::Result<Vec<String>> {
 
-    let mcp_tools = McpRuntimeTools::new(mcp_config_path).await?;
+    let mcp_tools = McpRuntimeTools::from_config_files(mcp_config_path, mcp_servers_config_file).await?;
     let mcp_tools = Arc::new(mcp_tools);
 
     // Register tools
+        let mut tool_ids = Vec::new();
         let list_tools= mcp_tools.get_tools_list_v2().await?;
         for tool in list_tools {
             let tool_definition=ToolDefinition {
@@ -155,9 +167,10 @@ async fn register_tools(mcp_config_path: String,discovery_service: Arc<dyn Disco
                 output_schema: json!({}),
             };
             discovery_service.register_tool(&tool_definition).await?;
+            tool_ids.push(tool_definition.id);
         }
   
-    Ok(())
+    Ok(tool_ids)
 }
 
 /***********************************************************************************/
@@ -189,8 +202,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
     /* Only Tasks and Tools need to be registered   */
     /* Agents Self Register at Launch               */
     /************************************************/ 
-    register_tasks(discovery_service.clone()).await?;
-    register_tools(args.mcp_config_path.clone(),discovery_service.clone()).await?;
+    let task_ids = register_tasks(discovery_service.clone()).await?;
+    let tool_ids = register_tools(args.mcp_config_path.clone(), args.mcp_servers_config_file.as_deref(), discovery_service.clone()).await?;
 
     /************************************************/
     /* Launch Agents from Factory                   */
@@ -200,7 +213,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
     /* Set Up Invokers                               */
     /************************************************/ 
     let task_invoker= setup_task_invoker().await?;
-    let tool_invoker = setup_tool_invoker(args.mcp_config_path.clone()).await?;
+    let tool_invoker = setup_tool_invoker(args.mcp_config_path.clone(), args.mcp_servers_config_file.as_deref()).await?;
     let agent_invoker= setup_agent_invoker_v2(discovery_service.clone()).await?;
 
     /************************************************/
@@ -210,7 +223,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
         task_invoker.clone(),
         agent_invoker.clone(),
         tool_invoker.clone(),
-    ).await?;
+    ).await?
+    .with_task_status_updates();
+
+    let workflow_registry = match &args.workflow_dir {
+        Some(dir) => WorkflowRegistry::from_dir(dir)?,
+        None => WorkflowRegistry::new(),
+    };
+    let workflow_names: Vec<String> = workflow_registry.names().cloned().collect();
+    info!("Saved workflows available to plans: {:?}", workflow_names);
+    let workflow_invokers = workflow_invokers.with_workflow_registry(Arc::new(workflow_registry));
 
     let workflow_invokers: Option<Arc<dyn WorkflowServiceApi>> = Some(Arc::new(workflow_invokers));
 
@@ -223,7 +245,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
                     discovery_service.clone(),
                             memory_service,
                                 evaluation_service,
-                                    workflow_invokers);
+                                    workflow_invokers)
+        // Plans generated by the planner may only use the tools, tasks and saved workflows set up above
+        .with_plan_resources(Capabilities::default().with_tools(tool_ids).with_tasks(task_ids).with_workflows(workflow_names));
 
     /************************************************/
     /* Set Up Registrations via discovery service           */
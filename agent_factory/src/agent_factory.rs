@@ -18,6 +18,7 @@ use configuration::McpRuntimeConfig;
 use agent_models::factory::config::FactoryMcpRuntimeConfig;
 
 use executor_agent::business_logic::executor_agent::WorkFlowInvokers;
+use workflow_management::graph::Capabilities;
 
 // Constants for LLM API endpoints
 const GROQ_CHAT_COMPLETION_ENDPOINT: &str = "https://api.groq.com/openai/v1/chat/completions";
@@ -159,6 +160,7 @@ pub struct AgentFactory {
     pub factory_memory_service: Option<Arc<dyn MemoryService>>,
     pub factory_evaluation_service: Option<Arc<dyn EvaluationService>>,
     pub workflow_service: Option<Arc<dyn WorkflowServiceApi>>, // Reverted to WorkflowServiceApi
+    pub plan_resources: Capabilities,
 }
 
 impl AgentFactory {
@@ -175,9 +177,16 @@ impl AgentFactory {
             factory_memory_service,
             factory_evaluation_service,
             workflow_service, // Stored directly
+            plan_resources: Capabilities::default(),
         }
     }
 
+    /// Tools, tasks and saved workflows the plans of launched planners are checked against.
+    pub fn with_plan_resources(mut self, plan_resources: Capabilities) -> Self {
+        self.plan_resources = plan_resources;
+        self
+    }
+
     pub fn create_agent_config(&self, factory_agent_config: &FactoryAgentConfig) -> Result<AgentConfig> {
         info!("Creating AgentConfig for agent: {}", factory_agent_config.factory_agent_name);
 
@@ -303,13 +312,22 @@ impl AgentFactory {
 
                 info!("Evaluation Service Absent for Planner Agent: {:?}", evaluation_service.is_none());
 
-                let agent = PlannerAgent::new(agent_config.clone(), 
+                let mut agent = PlannerAgent::new(agent_config.clone(), 
                     factory_agent_config.factory_agent_llm_provider_api_key.clone(),
                         None ,
                             evaluation_service.clone(),  
                                 None, 
                                     Some(self.factory_discovery_service.clone()), 
-                                        None).await?;
+                                        None).await?
+                    .with_plan_resources(self.plan_resources.clone());
+
+                // Planner and executor share the agent invoker: plans avoid the agents it reports unhealthy
+                let agent_health = self.workflow_service.as_ref()
+                    .and_then(|ws_arc| ws_arc.as_ref().as_any().downcast_ref::<WorkFlowInvokers>())
+                    .and_then(WorkFlowInvokers::agent_health_monitor);
+                if let Some(agent_health) = agent_health {
+                    agent = agent.with_agent_health(agent_health);
+                }
                 Self::launch_agent_server(agent_config, agent, None).await
             },
             
@@ -320,7 +338,18 @@ impl AgentFactory {
                             None, None, 
                             Some(self.factory_discovery_service.clone()),
                                 self.workflow_service.clone()).await?;
-                Self::launch_agent_server(agent_config, agent, None).await
+
+                // Plan progress is published as status updates of the A2A tasks handled by the executor server
+                let workflow_invokers = self.workflow_service.as_ref()
+                    .and_then(|ws_arc| ws_arc.as_ref().as_any().downcast_ref::<WorkFlowInvokers>())
+                    .cloned();
+                tokio::spawn(async move {
+                    let server = AgentServer::<ExecutorAgent>::new(agent_config, agent, None).await?;
+                    if let Some(workflow_invokers) = workflow_invokers {
+                        workflow_invokers.connect_task_manager(server.task_manager());
+                    }
+                    server.start_http().await.map_err(|e| anyhow::anyhow!("{}", e))
+                })
             },
         };
 
@@ -1,7 +1,9 @@
-use configuration::AgentConfig;
+use configuration::{AgentConfig, McpRuntimeConfig};
 use basic_agent::business_logic::basic_agent::BasicAgent;
 use agent_core::server::agent_server::AgentServer;
 use agent_core::business_logic::agent::Agent;
+use mcp_runtime::mcp_agent_logic::agent::McpAgent;
+use mcp_runtime::mcp_client::mcp_servers::McpServersConfig;
 
 
 use clap::Parser;
@@ -18,6 +20,12 @@ struct Args {
     config_file: String,
     #[clap(long, default_value = "warn")]
     log_level: String,
+    /// MCP runtime config (TOML format). The agent answers with its own LLM when not set
+    #[clap(long)]
+    mcp_config_path: Option<String>,
+    /// Optional list of MCP servers (TOML format), used instead of the server of the MCP config
+    #[clap(long, requires = "mcp_config_path")]
+    mcp_servers_config_file: Option<String>,
 }
 
 #[tokio::main]
@@ -47,6 +55,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let agent = BasicAgent::new(basic_agent_config.clone(),agent_api_key, None,None, None,None,None).await?;
 
+    let agent = match &args.mcp_config_path {
+        Some(mcp_config_path) => {
+            let mcp_agent = match &args.mcp_servers_config_file {
+                Some(mcp_servers_config_file) => {
+                    let agent_mcp_config = McpRuntimeConfig::load_agent_config(mcp_config_path)?;
+                    let mcp_servers_config = McpServersConfig::load(mcp_servers_config_file)?;
+                    McpAgent::with_mcp_servers(agent_mcp_config, None, mcp_servers_config.mcp_servers).await?
+                }
+                None => McpAgent::from_config_file(mcp_config_path, None).await?,
+            };
+            agent.with_mcp_agent(mcp_agent)
+        }
+        None => agent,
+    };
+
     // Create the modern server, and pass the runtime elements
     let server = AgentServer::<BasicAgent>::new(basic_agent_config, agent,None).await?;
 
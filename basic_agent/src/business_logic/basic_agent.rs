@@ -27,6 +27,15 @@ pub struct BasicAgent {
     mcp_agent: Option<Arc<McpAgent>>,
 }
 
+impl BasicAgent {
+    /// Answers with `mcp_agent` rather than with the agent LLM, e.g. an `McpAgent` using the
+    /// tools of several MCP servers.
+    pub fn with_mcp_agent(mut self, mcp_agent: McpAgent) -> Self {
+        self.mcp_agent = Some(Arc::new(mcp_agent));
+        self
+    }
+}
+
 #[async_trait]
 impl Agent for BasicAgent {
     /// Creation of a new simple a2a agent
@@ -64,7 +73,7 @@ impl Agent for BasicAgent {
         request: AgentRequest,
     ) -> anyhow::Result<ExecutionResult> {
         let request_id = uuid::Uuid::new_v4().to_string();
-        let conversation_id = Uuid::new_v4().to_string();
+        let conversation_id = request.session_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
 
         let user_query = request.user_query();
 
@@ -77,7 +86,11 @@ impl Agent for BasicAgent {
 
         // Use MCP LLM to answer if there is a MCP runtime, Agent LLM otherwise 
         let response = if let Some(ref agent) = self.mcp_agent {
-            agent.run_agent_internal(llm_msg).await?
+            // Requests of the same session share their conversation history
+            match &request.session_id {
+                Some(session_id) => agent.run_agent_in_session(session_id, llm_msg, None).await?,
+                None => agent.run_agent_internal(llm_msg).await?,
+            }
         } else {
             self.llm_interaction
                 .call_api_simple("user".to_string(), user_query)
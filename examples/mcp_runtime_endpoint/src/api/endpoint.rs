@@ -1,16 +1,18 @@
 use axum::{
     Json,
     Router,
-    extract::State,
+    extract::{Path, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response}, // Use IntoResponse for better error handling
-    routing::{get, post},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{delete, get, post},
 };
+use futures::stream::{self, Stream};
 use llm_api::chat::Message;
 
 use tracing::{info, error,warn};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc; // Use log
 
 use crate::AppState;
@@ -22,6 +24,12 @@ struct ApiError {
     message: String,
 }
 
+// Messages sent with a session id are turns of that conversation
+#[derive(Deserialize)]
+struct SessionParams {
+    session_id: Option<String>,
+}
+
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         (StatusCode::INTERNAL_SERVER_ERROR, Json(self)).into_response()
@@ -34,10 +42,7 @@ pub async fn run_endpoint(app_state: AppState) -> Result<(), Box<dyn std::error:
     // tracing_subscriber::fmt::init();
     info!("Initializing API endpoint...");
 
-    let app = Router::new()
-        .route("/", get(root))
-        .route("/msg", post(post_msg))
-        .with_state(app_state.clone()); // Pass the cloned AppState
+    let app = router(app_state.clone()); // Pass the cloned AppState
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await?;
     info!("API Listener bound to 0.0.0.0:3000");
@@ -47,16 +52,17 @@ pub async fn run_endpoint(app_state: AppState) -> Result<(), Box<dyn std::error:
     // This logic might be better placed in main.rs after the server stops,
     // especially if AppState holds the only Arc reference when the server exits.
     info!("Shutting down endpoint. Attempting to cancel MCP client...");
-    let mcp_client = app_state.mcp_agent.mcp_client; // Get client from state
-
-    // Attempt to get exclusive ownership to cancel.
-    match Arc::try_unwrap(mcp_client) {
-        Ok(mcp_client_owned) => {
-            info!("Successfully obtained exclusive ownership of MCP client. Cancelling...");
-            mcp_client_owned.cancel().await?;
-            info!("MCP client cancelled successfully.");
+    let mcp_servers = app_state.mcp_agent.mcp_servers; // Get clients from state
+
+    // Attempt to get exclusive ownership to cancel. An agent replaying a cassette has no client.
+    match mcp_servers.map(Arc::try_unwrap) {
+        None => info!("No MCP client to cancel."),
+        Some(Ok(mcp_servers_owned)) => {
+            info!("Successfully obtained exclusive ownership of MCP clients. Cancelling...");
+            mcp_servers_owned.cancel().await?;
+            info!("MCP clients cancelled successfully.");
         }
-        Err(_original_arc) => {
+        Some(Err(_original_arc)) => {
             // This error is expected if the state was cloned elsewhere or held onto.
             error!("Cannot cancel MCP client: still shared. Was the server shut down gracefully?");
             // Return an error or handle as appropriate for your application shutdown.
@@ -71,6 +77,15 @@ pub async fn run_endpoint(app_state: AppState) -> Result<(), Box<dyn std::error:
     Ok(())
 }
 
+fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/", get(root))
+        .route("/msg", post(post_msg))
+        .route("/msg/stream", post(post_msg_stream))
+        .route("/sessions/{session_id}", delete(delete_session))
+        .with_state(app_state)
+}
+
 // basic handler that responds with a static string
 async fn root() -> &'static str {
     "Hello, World! Agent is running."
@@ -79,17 +94,18 @@ async fn root() -> &'static str {
 // Updated handler to accept AppState
 async fn post_msg(
     State(state): State<AppState>, // Extract the AppState
+    Query(params): Query<SessionParams>,
     Json(payload): Json<Message>,
 ) -> Result<impl IntoResponse, ApiError> {
     // Return Result for error handling
     info!("Received message: {:?}", payload);
 
-    // Call run_agent, passing both project and agent configs
-    match state.mcp_agent.run_agent_internal(
-        payload,
-    )
-    .await
-    {
+    // Call run_agent, within the session if one is given
+    let result = match params.session_id {
+        Some(session_id) => state.mcp_agent.run_agent_in_session(&session_id, payload, None).await,
+        None => state.mcp_agent.run_agent_internal(payload).await,
+    };
+    match result {
         Ok(Some(msg)) => {
             info!("Agent returned response: {:?}", msg);
             let _= state.mcp_agent.reset_messages();
@@ -121,4 +137,73 @@ async fn post_msg(
     }
 }
 
+// Same as post_msg, but streams the tokens of the answer and the progress of the agent as server-sent events
+async fn post_msg_stream(
+    State(state): State<AppState>,
+    Query(params): Query<SessionParams>,
+    Json(payload): Json<Message>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    info!("Received message to stream: {:?}", payload);
+
+    let events = state.mcp_agent.run_agent_stream(params.session_id, payload, None);
+    let stream = stream::unfold(events, |mut events| async move {
+        let event = events.recv().await?;
+        Some((Event::default().json_data(&event), events))
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
 
+async fn delete_session(State(state): State<AppState>, Path(session_id): Path<String>) -> StatusCode {
+    state.mcp_agent.reset_session(&session_id);
+    StatusCode::NO_CONTENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use configuration::McpRuntimeConfig;
+    use mcp_runtime::mcp_agent_logic::agent::McpAgent;
+    use serde_json::json;
+    use test_support::{MockLlm, MockMcpServer};
+
+    #[tokio::test]
+    async fn test_msg_stream_sends_agent_events() {
+        let mcp_server = MockMcpServer::new().start().await.unwrap();
+        let llm = MockLlm::new()
+            .with_stream(vec![
+                MockLlm::chunk(json!({"role": "assistant", "content": "Hello "}), None),
+                MockLlm::chunk(json!({"content": "there."}), Some("stop")),
+            ])
+            .start()
+            .await
+            .unwrap();
+        let config = McpRuntimeConfig {
+            agent_mcp_llm_url: llm.url(),
+            ..mcp_server.runtime_config()
+        };
+        let app_state = AppState {
+            mcp_agent: McpAgent::new(config.clone(), Some("key".to_string())).await.unwrap(),
+            agent_mcp_config: config,
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move { axum::serve(listener, router(app_state)).await });
+
+        let body = reqwest::Client::new()
+            .post(format!("http://{}/msg/stream", addr))
+            .json(&json!({"role": "user", "content": "Hi"}))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        server.abort();
+
+        assert!(body.contains(r#""type":"token","content":"Hello ","turn":0"#));
+        assert!(body.contains(r#""type":"turn_finished","turn":0,"final_answer":true"#));
+        assert!(body.contains(r#""type":"finished","content":"Hello there.""#));
+    }
+}
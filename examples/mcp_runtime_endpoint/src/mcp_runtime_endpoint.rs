@@ -11,6 +11,7 @@ use tracing_subscriber::{
 use crate::api::endpoint::run_endpoint;
 use configuration::McpRuntimeConfig;
 use mcp_runtime::mcp_agent_logic::agent::McpAgent;
+use mcp_runtime::mcp_client::mcp_servers::McpServersConfig;
 use clap::Parser;
 
 /// Command-line arguments for the reimbursement server
@@ -20,6 +21,9 @@ struct Args {
     /// Configuration file path (TOML format)
     #[clap(long, default_value = "configuration/mcp_runtime_config.toml")]
     config_file: String,
+    /// Optional list of MCP servers (TOML format), used instead of the server of the config file
+    #[clap(long)]
+    mcp_servers_config_file: Option<String>,
     #[clap(long, default_value = "warn")]
     log_level: String,
 }
@@ -87,7 +91,13 @@ let args = Args::parse();
     };
 
     let api_key = agent_mcp_config.agent_mcp_server_api_key.clone();
-    let mcp_agent = McpAgent::new(agent_mcp_config.clone(), api_key).await?;
+    let mcp_agent = match &args.mcp_servers_config_file {
+        Some(mcp_servers_config_file) => {
+            let mcp_servers_config = McpServersConfig::load(mcp_servers_config_file)?;
+            McpAgent::with_mcp_servers(agent_mcp_config.clone(), api_key, mcp_servers_config.mcp_servers).await?
+        }
+        None => McpAgent::from_config_file(&args.config_file, api_key).await?,
+    };
 
     /************************************************/
     /* MCP Agent Launched                           */
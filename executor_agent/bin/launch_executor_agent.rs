@@ -1,7 +1,7 @@
 
 use resource_invoker::McpRuntimeToolInvoker;
 use resource_invoker::GreetTask;
-use resource_invoker::A2AAgentInvoker;
+use resource_invoker::{A2AAgentInvoker, DEFAULT_HEALTH_PROBE_INTERVAL};
 
 
 use clap::Parser;
@@ -17,6 +17,8 @@ use configuration::{AgentReference};
 
 
 use executor_agent::business_logic::executor_agent::{ExecutorAgent, WorkFlowInvokers};
+use workflow_management::graph::checkpoint::RedbCheckpointStore;
+use workflow_management::graph::workflow_registry::WorkflowRegistry;
 
 use workflow_management::agent_communication::agent_invoker::AgentInvoker;
 use workflow_management::tasks::task_invoker::TaskInvoker;
@@ -44,12 +46,22 @@ struct Args {
     /// MCP Config
     #[clap(long, default_value = "./configuration/mcp_runtime_config.toml")]
     mcp_config_path: String,
+    /// Optional list of MCP servers (TOML format), used instead of the server of the MCP config
+    #[clap(long)]
+    mcp_servers_config_file: Option<String>,
     #[clap(long, default_value = "http://127.0.0.1:4000")]
     discovery_service_url: String,
     #[clap(long, default_value = "http://127.0.0.1:5000")]
     memory_service_url: String,
     #[clap(long, default_value = "http://127.0.0.1:7000")]
     evaluation_service_url: String,
+    /// Path of the redb file used to checkpoint running plans. Disabled when not set.
+    #[clap(long)]
+    checkpoint_db_path: Option<String>,
+    /// Directory of the saved workflows `sub_workflow` activities may run by name (file name without extension).
+    /// Plans referring to a workflow by name are rejected when not set.
+    #[clap(long)]
+    workflow_dir: Option<String>,
 }
 
 /***********************************************************************************/
@@ -91,8 +103,8 @@ async fn setup_task_invoker() -> anyhow::Result<Arc<dyn TaskInvoker>> {
     Ok(greet_task_invoker)
 }
 
-async fn setup_tool_invoker(mcp_config_path: String) -> anyhow::Result<Arc<dyn ToolInvoker>> {
-    let mcp_tool_invoker = McpRuntimeToolInvoker::new(mcp_config_path).await?;
+async fn setup_tool_invoker(mcp_config_path: String, mcp_servers_config_file: Option<&str>) -> anyhow::Result<Arc<dyn ToolInvoker>> {
+    let mcp_tool_invoker = McpRuntimeToolInvoker::from_config_files(mcp_config_path, mcp_servers_config_file).await?;
     let mcp_tool_invoker = Arc::new(mcp_tool_invoker);
 
     Ok(mcp_tool_invoker)
@@ -101,6 +113,8 @@ async fn setup_tool_invoker(mcp_config_path: String) -> anyhow::Result<Arc<dyn T
 
 async fn setup_agent_invoker_v2( discovery_service_adapter: Arc<dyn DiscoveryService>) -> anyhow::Result<Arc<dyn AgentInvoker>> {
     let a2a_agent_invoker = A2AAgentInvoker::new_with_discovery(None, None, discovery_service_adapter).await?;
+    // Detects agents going down or coming back between two refreshes
+    a2a_agent_invoker.start_health_probes(DEFAULT_HEALTH_PROBE_INTERVAL);
     let a2a_agent_invoker = Arc::new(a2a_agent_invoker);
 
     Ok(a2a_agent_invoker)
@@ -137,7 +151,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
     /* Set Up Invokers                               */
     /************************************************/ 
     let task_invoker= setup_task_invoker().await?;
-    let tool_invoker = setup_tool_invoker(args.mcp_config_path).await?;
+    let tool_invoker = setup_tool_invoker(args.mcp_config_path, args.mcp_servers_config_file.as_deref()).await?;
     let agent_invoker= setup_agent_invoker_v2(discovery_service.clone().expect("No Discovery Service")).await?;
 
     /************************************************/
@@ -147,11 +161,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
         task_invoker.clone(),
         agent_invoker.clone(),
         tool_invoker.clone(),
-    ).await?;
+    ).await?
+    .with_task_status_updates();
+
+    let workflow_invokers = match &args.checkpoint_db_path {
+        Some(path) => {
+            info!("Plan checkpoints persisted to: {}", path);
+            workflow_invokers.with_checkpoint_store(Arc::new(RedbCheckpointStore::open(path)?))
+        }
+        None => workflow_invokers,
+    };
+
+    let workflow_invokers = match &args.workflow_dir {
+        Some(dir) => {
+            let workflow_registry = WorkflowRegistry::from_dir(dir)?;
+            info!("Saved workflows loaded from {}: {:?}", dir, workflow_registry.names().collect::<Vec<_>>());
+            workflow_invokers.with_workflow_registry(Arc::new(workflow_registry))
+        }
+        None => workflow_invokers,
+    };
 
    // debug!("{}",workflow_invokers.list_available_resources());
 
-    let workflow_invokers: Option<Arc<dyn WorkflowServiceApi>> = Some(Arc::new(workflow_invokers));
+    let workflow_invokers = Arc::new(workflow_invokers);
+    let task_status_invokers = workflow_invokers.clone();
+    let workflow_invokers: Option<Arc<dyn WorkflowServiceApi>> = Some(workflow_invokers);
 
     /************************************************/
     /* Launch Workflow Agent                        */
@@ -164,6 +198,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
     /************************************************/ 
     // Create the modern server, and pass the runtime elements
     let server = AgentServer::<ExecutorAgent>::new(executor_agent_config, agent, discovery_service).await?;
+    // Plan progress is published as status updates of the A2A tasks handled by this server
+    task_status_invokers.connect_task_manager(server.task_manager());
    
     println!("🌐 Starting HTTP server only...");
     server.start_http().await?;
@@ -0,0 +1,193 @@
+use std::sync::{Arc, OnceLock};
+use async_trait::async_trait;
+use serde_json::Map;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::warn;
+use uuid::Uuid;
+
+use a2a_rs::domain::{Message, Part, Role, TaskState};
+use a2a_rs::port::AsyncTaskManager;
+use workflow_management::graph::execution_event::ExecutionEvent;
+
+/// Destination of the execution events of the plans run by the `ExecutorAgent`.
+#[async_trait]
+pub trait ExecutionEventPublisher: Send + Sync {
+    /// Publishes an event of the plan executed for the A2A task `task_id`.
+    async fn publish(&self, task_id: &str, event: &ExecutionEvent) -> anyhow::Result<()>;
+}
+
+/// Forwards execution events as `Working` status updates of the A2A task running the plan,
+/// except approval requests which move the task to `InputRequired` until they are answered.
+/// Each update carries the event as JSON text, with the event name in the part metadata.
+pub struct A2ATaskStatusPublisher<M: AsyncTaskManager> {
+    task_manager: Arc<M>,
+}
+
+impl<M: AsyncTaskManager> A2ATaskStatusPublisher<M> {
+    pub fn new(task_manager: Arc<M>) -> Self {
+        Self { task_manager }
+    }
+}
+
+#[async_trait]
+impl<M: AsyncTaskManager + Send + Sync + 'static> ExecutionEventPublisher for A2ATaskStatusPublisher<M> {
+    async fn publish(&self, task_id: &str, event: &ExecutionEvent) -> anyhow::Result<()> {
+        let event_json = serde_json::to_value(event)?;
+        let mut metadata = Map::new();
+        if let Some(name) = event_json.get("event") {
+            metadata.insert("execution_event".to_string(), name.clone());
+        }
+
+        let message = Message::builder()
+            .role(Role::Agent)
+            .parts(vec![Part::Text {
+                text: event_json.to_string(),
+                metadata: Some(metadata),
+            }])
+            .message_id(Uuid::new_v4().to_string())
+            .build();
+
+        self.task_manager
+            .update_task_status(task_id, task_state(event), Some(message))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to update status of task {}: {}", task_id, e))?;
+        Ok(())
+    }
+}
+
+/// State of the A2A task running a plan after `event`.
+pub fn task_state(event: &ExecutionEvent) -> TaskState {
+    match event {
+        ExecutionEvent::ApprovalRequested { .. } => TaskState::InputRequired,
+        _ => TaskState::Working,
+    }
+}
+
+/// Publisher whose destination is only known once the agent runs: the task manager of the A2A
+/// server is created after the `ExecutorAgent` it serves. Events published before `connect` are dropped.
+#[derive(Clone, Default)]
+pub struct DeferredEventPublisher {
+    target: Arc<OnceLock<Arc<dyn ExecutionEventPublisher>>>,
+}
+
+impl DeferredEventPublisher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the destination of the events, once. Later calls are ignored.
+    pub fn connect(&self, target: Arc<dyn ExecutionEventPublisher>) {
+        if self.target.set(target).is_err() {
+            warn!("Execution event publisher already connected");
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionEventPublisher for DeferredEventPublisher {
+    async fn publish(&self, task_id: &str, event: &ExecutionEvent) -> anyhow::Result<()> {
+        match self.target.get() {
+            Some(target) => target.publish(task_id, event).await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Publishes every event of `events` until the plan finishes.
+/// Publishing failures are logged and never interrupt the plan.
+pub async fn forward_execution_events(
+    publisher: Arc<dyn ExecutionEventPublisher>,
+    task_id: String,
+    mut events: UnboundedReceiver<ExecutionEvent>,
+) {
+    while let Some(event) = events.recv().await {
+        if let Err(e) = publisher.publish(&task_id, &event).await {
+            warn!("Unable to publish execution event for task '{}': {}", task_id, e);
+        }
+        if event.is_terminal() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_models::graph::graph_definition::WorkflowPlanInput;
+    use serde_json::json;
+    use std::sync::Mutex;
+    use test_support::{EchoTasks, NoAgents, NoTools};
+    use workflow_management::graph::approval::ApprovalDecision;
+    use workflow_management::graph::execution_policy::ExecutionPolicies;
+    use workflow_management::graph::graph_orchestrator::PlanExecutor;
+
+    /// Keeps the task state each published event moves the task to.
+    #[derive(Default)]
+    struct RecordingPublisher {
+        updates: Mutex<Vec<(String, TaskState)>>,
+    }
+
+    #[async_trait]
+    impl ExecutionEventPublisher for RecordingPublisher {
+        async fn publish(&self, task_id: &str, event: &ExecutionEvent) -> anyhow::Result<()> {
+            self.updates.lock().unwrap().push((task_id.to_string(), task_state(event)));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_run_publishes_working_and_input_required_updates() {
+        let workflow = json!({
+            "plan_name": "status",
+            "activities": [{
+                "id": "update",
+                "description": "update",
+                "type": "test",
+                "activity_type": "direct_task_execution",
+                "agent": {},
+                "tools": [],
+                "tasks": [{ "task_to_use": "update", "task_parameters": {} }],
+                "dependencies": [],
+                "expected_outcome": "",
+                "approval": { "prompt": "Update the record?" }
+            }]
+        });
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let workflow: WorkflowPlanInput = serde_json::from_value(workflow).unwrap();
+        let mut executor = PlanExecutor::new(
+            workflow.into(),
+            Arc::new(EchoTasks),
+            Arc::new(NoAgents),
+            Arc::new(NoTools),
+            "test".to_string(),
+        )
+        .with_execution_policies(policies);
+
+        let recorder = Arc::new(RecordingPublisher::default());
+        let publisher = DeferredEventPublisher::new();
+        publisher.connect(recorder.clone());
+        let forwarder = tokio::spawn(forward_execution_events(
+            Arc::new(publisher),
+            "task-1".to_string(),
+            executor.subscribe_events(),
+        ));
+        let approvals = executor.approval_handle();
+        let run_id = executor.run_id().to_string();
+        let run = tokio::spawn(async move { executor.execute_plan().await });
+
+        while approvals.pending().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        approvals.decide(&run_id, "update", ApprovalDecision::Approve).unwrap();
+        run.await.unwrap().unwrap();
+        forwarder.await.unwrap();
+
+        let updates = recorder.updates.lock().unwrap();
+        assert!(updates.iter().all(|(task_id, _)| task_id == "task-1"));
+        let states: Vec<&TaskState> = updates.iter().map(|(_, state)| state).collect();
+        let input_required = states.iter().position(|state| **state == TaskState::InputRequired).unwrap();
+        assert!(states[..input_required].iter().all(|state| **state == TaskState::Working));
+        assert!(!states[input_required + 1..].is_empty());
+        assert!(states[input_required + 1..].iter().all(|state| **state == TaskState::Working));
+    }
+}
@@ -1,7 +1,8 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::any::Any;
+use std::collections::HashMap;
 use async_trait::async_trait;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
 use serde_json::{Value, json};
 use uuid::Uuid;
@@ -12,11 +13,21 @@ use agent_core::business_logic::agent::Agent;
 use agent_core::business_logic::services::{DiscoveryService, MemoryService, EvaluationService, WorkflowServiceApi};
 use agent_models::graph::graph_definition::Graph;
 use agent_models::execution::execution_result::ExecutionResult;
-use workflow_management::graph::graph_orchestrator::PlanExecutor;
+use workflow_management::graph::approval::{ApprovalDecision, PlanApprovalHandle};
+use workflow_management::graph::graph_orchestrator::{
+    PlanCancellationHandle, PlanExecutor, PlanExecutorError, DEFAULT_MAX_CONCURRENCY,
+};
+use workflow_management::graph::checkpoint::CheckpointStore;
+use workflow_management::graph::execution_policy::{ExecutionPolicies, WorkflowReference};
+use workflow_management::graph::workflow_registry::WorkflowRegistry;
 use workflow_management::agent_communication::agent_invoker::AgentInvoker;
 use workflow_management::tasks::task_invoker::TaskInvoker;
 use workflow_management::tools::tool_invoker::ToolInvoker;
-use resource_invoker::A2AAgentInvoker;
+use resource_invoker::{A2AAgentInvoker, AgentHealthMonitor};
+use crate::business_logic::event_publisher::{
+    A2ATaskStatusPublisher, DeferredEventPublisher, ExecutionEventPublisher, forward_execution_events,
+};
+use a2a_rs::port::AsyncTaskManager;
 use agent_models::agent_request::AgentRequest;
 
 // TODO: Move this to a separate file if it grows
@@ -25,6 +36,10 @@ pub struct WorkFlowInvokers {
     pub task_invoker: Arc<dyn TaskInvoker>,
     pub agent_invoker: Arc<dyn AgentInvoker>,
     pub tool_invoker: Arc<dyn ToolInvoker>,
+    pub checkpoint_store: Option<Arc<dyn CheckpointStore>>,
+    pub event_publisher: Option<Arc<dyn ExecutionEventPublisher>>,
+    pub task_status_publisher: Option<DeferredEventPublisher>,
+    pub workflow_registry: Option<Arc<WorkflowRegistry>>,
 }
 
 impl WorkFlowInvokers {
@@ -37,8 +52,67 @@ impl WorkFlowInvokers {
             task_invoker,
             agent_invoker,
             tool_invoker,
+            checkpoint_store: None,
+            event_publisher: None,
+            task_status_publisher: None,
+            workflow_registry: None,
         })
     }
+
+    /// Enables durable plan state, so interrupted runs can be resumed by run id.
+    pub fn with_checkpoint_store(mut self, checkpoint_store: Arc<dyn CheckpointStore>) -> Self {
+        self.checkpoint_store = Some(checkpoint_store);
+        self
+    }
+
+    /// Publishes the progress of every executed plan, e.g. as A2A task status updates.
+    pub fn with_event_publisher(mut self, event_publisher: Arc<dyn ExecutionEventPublisher>) -> Self {
+        self.event_publisher = Some(event_publisher);
+        self
+    }
+
+    /// Publishes the progress of every executed plan as status updates of the A2A task running it.
+    /// Updates only flow once the task manager of the agent server is given to `connect_task_manager`.
+    pub fn with_task_status_updates(mut self) -> Self {
+        let publisher = DeferredEventPublisher::new();
+        self.event_publisher = Some(Arc::new(publisher.clone()));
+        self.task_status_publisher = Some(publisher);
+        self
+    }
+
+    /// Sends the task status updates enabled by `with_task_status_updates` to `task_manager`.
+    pub fn connect_task_manager<M: AsyncTaskManager + Send + Sync + 'static>(&self, task_manager: Arc<M>) {
+        if let Some(publisher) = &self.task_status_publisher {
+            publisher.connect(Arc::new(A2ATaskStatusPublisher::new(task_manager)));
+        }
+    }
+
+    /// Makes saved workflows available to the `sub_workflow` activities of executed plans.
+    pub fn with_workflow_registry(mut self, workflow_registry: Arc<WorkflowRegistry>) -> Self {
+        self.workflow_registry = Some(workflow_registry);
+        self
+    }
+
+    /// Name of a saved workflow referenced by a `sub_workflow` activity but missing from the registry.
+    pub fn unknown_sub_workflow(&self, execution_policies: &ExecutionPolicies) -> Option<String> {
+        execution_policies
+            .activities
+            .values()
+            .filter_map(|policy| match &policy.sub_workflow.as_ref()?.workflow {
+                WorkflowReference::Name(name) => Some(name),
+                WorkflowReference::Path(_) => None,
+            })
+            .find(|name| !self.workflow_registry.as_ref().is_some_and(|registry| registry.contains(name)))
+            .cloned()
+    }
+
+    /// Health of the agents delegations go to, when the agent invoker is an `A2AAgentInvoker`.
+    pub fn agent_health_monitor(&self) -> Option<AgentHealthMonitor> {
+        self.agent_invoker
+            .as_any()
+            .downcast_ref::<A2AAgentInvoker>()
+            .map(A2AAgentInvoker::health_monitor)
+    }
 }
 
 #[async_trait]
@@ -57,12 +131,21 @@ impl WorkflowServiceApi for WorkFlowInvokers {
     }
 }
 
+/// Handles used to act on a plan while it runs.
+#[derive(Clone)]
+struct RunningPlan {
+    task_id: Option<String>,
+    cancellation: PlanCancellationHandle,
+    approvals: PlanApprovalHandle,
+}
+
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct ExecutorAgent {
     agent_config: Arc<AgentConfig>,
     workflow_invokers: Arc<WorkFlowInvokers>,
     evaluation_service: Option<Arc<dyn EvaluationService>>,
+    running_plans: Arc<Mutex<HashMap<String, RunningPlan>>>,
 }
 
 #[async_trait]
@@ -87,6 +170,7 @@ impl Agent for ExecutorAgent {
             agent_config: Arc::new(agent_config),
             workflow_invokers: workflow_invokers_arc,
             evaluation_service,
+            running_plans: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
@@ -94,28 +178,182 @@ impl Agent for ExecutorAgent {
         &self,
         request: AgentRequest,
     ) -> anyhow::Result<ExecutionResult> {
-        let plan_json = request.user_query();
-        let graph: Graph = serde_json::from_str(&plan_json)?;
+        let metadata_str = |key: &str| {
+            request.metadata
+                .as_ref()
+                .and_then(|m| m.get(key))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+
+        let failure = |output: Value| ExecutionResult {
+            request_id: Uuid::new_v4().to_string(),
+            conversation_id: Uuid::new_v4().to_string(),
+            success: false,
+            output,
+        };
+
+        // A plan is cancelled by its run id, or by the id of the A2A task running it.
+        let (cancel_run_id, cancel_task_id) = (metadata_str("cancel_run_id"), metadata_str("cancel_task_id"));
+        if cancel_run_id.is_some() || cancel_task_id.is_some() {
+            let plan = self.find_running_plan(cancel_run_id.as_deref(), cancel_task_id.as_deref());
+            let cancelled = match &plan {
+                Some((_, plan)) => {
+                    plan.cancellation.cancel();
+                    true
+                }
+                None => false,
+            };
+            let run_id = plan.map(|(run_id, _)| run_id).or(cancel_run_id);
+            debug!("Cancellation requested for run {:?}, found running: {}", run_id, cancelled);
+            return Ok(ExecutionResult {
+                request_id: Uuid::new_v4().to_string(),
+                conversation_id: Uuid::new_v4().to_string(),
+                success: cancelled,
+                output: json!({ "run_id": run_id, "task_id": cancel_task_id, "cancelled": cancelled }),
+            });
+        }
 
-        let original_user_query = request.metadata
+        // A human answering an approval: {"run_id" or "task_id", "node_id", "decision": "approve" | "reject" | "edit", ...},
+        // plus "approval_run_id" for an activity of a sub-workflow
+        if let Some(approval) = request.metadata.as_ref().and_then(|m| m.get("approval")) {
+            return Ok(self.decide_approval(approval));
+        }
+
+        // Parallel execution is opt-in: {"max_concurrency": 4} runs up to four independent nodes at once
+        let max_concurrency = request.metadata
             .as_ref()
-            .and_then(|m| m.get("original_user_query"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("User query not available in executor")
-            .to_string();
+            .and_then(|m| m.get("max_concurrency"))
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+            .unwrap_or(DEFAULT_MAX_CONCURRENCY);
+
+        // A plan must not run without the retries, timeouts or error handling it asked for.
+        let execution_policies = match request.metadata.as_ref().and_then(|m| m.get("execution_policies")) {
+            Some(value) => match serde_json::from_value::<ExecutionPolicies>(value.clone()) {
+                Ok(execution_policies) => execution_policies,
+                Err(e) => {
+                    warn!("Rejecting plan with invalid execution_policies: {}", e);
+                    return Ok(failure(json!({ "error": format!("Invalid execution policies: {}", e), "plan_rejected": true })));
+                }
+            },
+            None => ExecutionPolicies::default(),
+        };
+
+        // Saved workflows are looked up by name in the registry of this executor only
+        if let Some(name) = self.workflow_invokers.unknown_sub_workflow(&execution_policies) {
+            warn!("Rejecting plan running unknown workflow '{}'", name);
+            return Ok(failure(json!({ "error": format!("Unknown sub-workflow '{}': no saved workflow has this name", name), "plan_rejected": true })));
+        }
 
         debug!("---ExecutorAgent: Starting to execute plan---");
-        debug!("Graph Received: {:#?}", graph);
 
-        let mut executor = PlanExecutor::new(
-            graph,
-            self.workflow_invokers.task_invoker.clone(),
-            self.workflow_invokers.agent_invoker.clone(),
-            self.workflow_invokers.tool_invoker.clone(),
-            original_user_query,
-        );
+        let executor = match (metadata_str("resume_run_id"), &self.workflow_invokers.checkpoint_store) {
+            (Some(run_id), Some(store)) => {
+                debug!("Resuming interrupted run '{}'", run_id);
+                PlanExecutor::resume(
+                    &run_id,
+                    store.clone(),
+                    self.workflow_invokers.task_invoker.clone(),
+                    self.workflow_invokers.agent_invoker.clone(),
+                    self.workflow_invokers.tool_invoker.clone(),
+                )
+                .await
+                .map(|executor| executor.with_max_concurrency(max_concurrency))
+            }
+            (resume_run_id, checkpoint_store) => {
+                if resume_run_id.is_some() {
+                    warn!("resume_run_id provided but no checkpoint store is configured. Executing the plan from scratch.");
+                }
 
-        match executor.execute_plan().await {
+                let plan_json = request.user_query();
+                let graph: Graph = match serde_json::from_str(&plan_json) {
+                    Ok(graph) => graph,
+                    Err(e) => {
+                        warn!("Rejecting plan that is not a valid graph: {}", e);
+                        return Ok(failure(json!({ "error": format!("Invalid workflow graph: {}", e), "plan_rejected": true })));
+                    }
+                };
+
+                let original_user_query = metadata_str("original_user_query")
+                    .unwrap_or_else(|| "User query not available in executor".to_string());
+
+                debug!("Graph Received: {:#?}", graph);
+
+                let executor = PlanExecutor::new(
+                    graph,
+                    self.workflow_invokers.task_invoker.clone(),
+                    self.workflow_invokers.agent_invoker.clone(),
+                    self.workflow_invokers.tool_invoker.clone(),
+                    original_user_query,
+                )
+                .with_max_concurrency(max_concurrency)
+                .with_execution_policies(execution_policies);
+
+                // A caller-supplied run id lets the caller cancel or resume the run it starts.
+                Ok(match (checkpoint_store, metadata_str("run_id")) {
+                    (Some(store), run_id) => {
+                        let run_id = run_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+                        executor.with_checkpoint_store(store.clone(), run_id)
+                    }
+                    (None, Some(run_id)) => executor.with_run_id(run_id),
+                    (None, None) => executor,
+                })
+            }
+        };
+
+        let mut executor = match (executor, &self.workflow_invokers.workflow_registry) {
+            (Ok(executor), Some(registry)) => executor.with_workflow_registry(registry.clone()),
+            (Ok(executor), None) => executor,
+            (Err(e), _) => {
+                warn!("Unable to prepare plan execution: {}", e);
+                return Ok(failure(json!({ "error": format!("Workflow execution failed: {}", e) })));
+            }
+        };
+        let run_id = executor.run_id().to_string();
+        let running_plan = RunningPlan {
+            task_id: metadata_str("task_id"),
+            cancellation: executor.cancellation_handle(),
+            approvals: executor.approval_handle(),
+        };
+        {
+            let mut running_plans = self.running_plans.lock().unwrap();
+            if running_plans.contains_key(&run_id) {
+                warn!("Rejecting plan: run '{}' is already running", run_id);
+                return Ok(failure(json!({ "error": format!("Run '{}' is already running", run_id), "run_id": run_id })));
+            }
+            running_plans.insert(run_id.clone(), running_plan);
+        }
+        // The run id is also published with the `plan_started` event, so the plan can be cancelled while it runs.
+        info!("Starting run '{}' for task {:?}", run_id, metadata_str("task_id"));
+
+        // The planner passes the id of the A2A task running this plan; fall back to the run id.
+        let event_forwarder = self.workflow_invokers.event_publisher.clone().map(|publisher| {
+            let task_id = metadata_str("task_id").unwrap_or_else(|| run_id.clone());
+            tokio::spawn(forward_execution_events(publisher, task_id, executor.subscribe_events()))
+        });
+
+        let execution = executor.execute_plan().await;
+        self.running_plans.lock().unwrap().remove(&run_id);
+        if let Some(event_forwarder) = event_forwarder {
+            let _ = event_forwarder.await;
+        }
+        let attempts = serde_json::to_value(executor.attempt_history()).unwrap_or(Value::Null);
+        let node_failures = serde_json::to_value(executor.node_failures()).unwrap_or(Value::Null);
+        // Once an activity completed, a corrected plan run from scratch would repeat its side effects
+        let nothing_completed = executor.activities_outcome().is_empty();
+        // Invoker calls are only returned on demand: responses can be large.
+        let record_invocations = request.metadata
+            .as_ref()
+            .and_then(|m| m.get("record_invocations"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let recording = match executor.recording() {
+            Ok(recording) if record_invocations => serde_json::to_value(recording).unwrap_or(Value::Null),
+            _ => Value::Null,
+        };
+
+        match execution {
             Ok((execution_outcome, _activities_outcome)) => {
                 debug!("\nWorkflow execution completed successfully. Outcome : {}\n", execution_outcome);
 
@@ -128,19 +366,65 @@ impl Agent for ExecutorAgent {
                     request_id: Uuid::new_v4().to_string(), // Generate a new UUID
                     conversation_id: Uuid::new_v4().to_string(), // Generate a new UUID
                     success: true,
-                    output: json!({ "text_response": parsed_outcome }),
+                    output: json!({ "text_response": parsed_outcome, "run_id": run_id, "attempts": attempts, "node_failures": node_failures, "recording": recording }),
                 })
             },
             Err(e) => {
                 warn!("Error executing plan: {}", e);
                 let error_message = format!("Workflow execution failed: {}", e);
-                Ok(ExecutionResult {
-                    request_id: Uuid::new_v4().to_string(),
-                    conversation_id: Uuid::new_v4().to_string(),
-                    success: false,
-                    output: json!({ "error": error_message }),
-                })
+                let partial_outcome = match &e {
+                    PlanExecutorError::Cancelled { activities_outcome }
+                    | PlanExecutorError::TimedOut { activities_outcome, .. } => json!(activities_outcome),
+                    _ => Value::Null,
+                };
+                Ok(failure(json!({ "error": error_message, "run_id": run_id, "attempts": attempts, "node_failures": node_failures, "partial_outcome": partial_outcome, "plan_rejected": e.is_plan_defect() && nothing_completed, "recording": recording })))
             }
         }
     }
 }
+
+impl ExecutorAgent {
+    /// The running plan with this run id or, without a run id, the one run for this A2A task.
+    fn find_running_plan(&self, run_id: Option<&str>, task_id: Option<&str>) -> Option<(String, RunningPlan)> {
+        let running_plans = self.running_plans.lock().unwrap();
+        match (run_id, task_id) {
+            (Some(run_id), _) => running_plans
+                .get(run_id)
+                .map(|plan| (run_id.to_string(), plan.clone())),
+            (None, Some(task_id)) => running_plans
+                .iter()
+                .find(|(_, plan)| plan.task_id.as_deref() == Some(task_id))
+                .map(|(run_id, plan)| (run_id.clone(), plan.clone())),
+            (None, None) => None,
+        }
+    }
+
+    /// Forwards a human decision to the running plan waiting for it.
+    fn decide_approval(&self, approval: &Value) -> ExecutionResult {
+        let field = |key: &str| approval.get(key).and_then(Value::as_str).map(str::to_string);
+        let node_id = field("node_id").unwrap_or_default();
+        let result = |success: bool, output: Value| ExecutionResult {
+            request_id: Uuid::new_v4().to_string(),
+            conversation_id: Uuid::new_v4().to_string(),
+            success,
+            output,
+        };
+
+        let decision: ApprovalDecision = match serde_json::from_value(approval.clone()) {
+            Ok(decision) => decision,
+            Err(e) => return result(false, json!({ "node_id": node_id, "error": format!("Invalid approval decision: {}", e) })),
+        };
+        let plan = self.find_running_plan(field("run_id").as_deref(), field("task_id").as_deref());
+        let Some((run_id, plan)) = plan else {
+            return result(false, json!({ "node_id": node_id, "error": "No running plan found for this approval" }));
+        };
+        // Activities of a sub-workflow wait under the run id of the sub-workflow, listed in `pending`.
+        let approval_run_id = field("approval_run_id").unwrap_or(run_id);
+
+        debug!("Approval decision for node '{}' of run '{}': {:?}", node_id, approval_run_id, decision);
+        match plan.approvals.decide(&approval_run_id, &node_id, decision) {
+            Ok(()) => result(true, json!({ "node_id": node_id, "decided": true })),
+            Err(e) => result(false, json!({ "node_id": node_id, "decided": false, "error": e.to_string(), "pending": plan.approvals.pending() })),
+        }
+    }
+}
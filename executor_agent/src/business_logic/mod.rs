@@ -1 +1,2 @@
+pub mod event_publisher;
 pub mod executor_agent;
\ No newline at end of file
@@ -4,13 +4,24 @@ use tracing::{info, error, warn, debug};
 use std::env;
 use serde_json::json;
 use std::sync::Arc;
-
-use crate::mcp_client::mcp_client::McpClient;
-use llm_api::chat::{ChatLlmInteraction, ChatCompletionRequest, ChatCompletionResponse, Choice, ToolChoice};
+use std::sync::atomic::{AtomicBool, Ordering};
+use futures::stream::{self, StreamExt};
+use tokio::sync::mpsc;
+
+use crate::mcp_agent_logic::cassette::Cassette;
+use crate::mcp_agent_logic::session::{
+    HistoryPolicy, InMemorySessionStore, Session, SessionLocks, SessionStore, history_of_run,
+};
+use crate::mcp_agent_logic::streaming::{AgentStreamEvent, stream_chat_completion};
+use crate::mcp_client::mcp_servers::{McpServerConfig, McpServers};
+use llm_api::chat::{ChatLlmInteraction, ChatCompletionRequest, ChatCompletionResponse, Choice, ToolCall, ToolChoice};
 use llm_api::tools::Tool;
 use configuration::McpRuntimeConfig;
-use crate::mcp_client::mcp_client::{execute_tool_call_v2, get_tools_list_v2, initialize_mcp_client_v2};
 use crate::mcp_tools::tools::define_all_tools;
+use rmcp::model::{CallToolResult, Tool as McpTool};
+
+/// Number of tool calls of a single LLM turn executed concurrently, unless set with `with_max_concurrent_tool_calls`.
+const DEFAULT_MAX_CONCURRENT_TOOL_CALLS: usize = 4;
 
 /// Represents the discrete states of the agent's execution loop.
 ///
@@ -36,6 +47,19 @@ pub struct McpAgentRunContext {
     pub state: AgentState,
     pub messages: Vec<Message>,
     pub llm_all_tool: Vec<Tool>,
+    /// Receives the progress of the run when it is streamed.
+    pub events: Option<mpsc::UnboundedSender<AgentStreamEvent>>,
+    /// Number of LLM turns started so far, used to tag the streamed tokens.
+    pub turn: usize,
+}
+
+impl McpAgentRunContext {
+    fn emit(&self, event: AgentStreamEvent) {
+        if let Some(events) = &self.events {
+            // The receiver may be gone, e.g. when the client disconnected
+            let _ = events.send(event);
+        }
+    }
 }
 
 /// The `McpAgent` struct encapsulates the configuration and static components for the MCP agent.
@@ -43,20 +67,76 @@ pub struct McpAgentRunContext {
 #[derive(Clone)]
 pub struct McpAgent {
     llm_interaction: ChatLlmInteraction,
-    pub mcp_client: Arc<McpClient>,
+    /// Used for streamed completions, which `ChatLlmInteraction` does not support.
+    http_client: reqwest::Client,
+    llm_api_key: String,
+    /// `None` when replaying a cassette, which needs no MCP server.
+    pub mcp_servers: Option<Arc<McpServers>>,
     agent_mcp_config: McpRuntimeConfig,
     tool_cache: Arc<std::sync::RwLock<std::collections::HashMap<String, Vec<Tool>>>>,
+    cassette: Option<Arc<Cassette>>,
+    max_concurrent_tool_calls: usize,
+    sessions: Arc<dyn SessionStore>,
+    session_locks: Arc<SessionLocks>,
+    history_policy: HistoryPolicy,
 }
 
 impl McpAgent {
+    /// Creates an agent using the tools of the server at `agent_mcp_server_url`. Use
+    /// `from_config_file` for a server launched as a child process.
     pub async fn new(
         agent_mcp_config: McpRuntimeConfig,
         mcp_runtime_api_key: Option<String>,
+    ) -> anyhow::Result<Self> {
+        Self::initialize(agent_mcp_config, mcp_runtime_api_key, Vec::new(), None).await
+    }
+
+    /// Creates an agent using the tools of several MCP servers, instead of the single server of
+    /// `agent_mcp_config`. Tools whose name is used by several servers are prefixed, see `McpServers`.
+    pub async fn with_mcp_servers(
+        agent_mcp_config: McpRuntimeConfig,
+        mcp_runtime_api_key: Option<String>,
+        mcp_servers: Vec<McpServerConfig>,
+    ) -> anyhow::Result<Self> {
+        Self::initialize(agent_mcp_config, mcp_runtime_api_key, mcp_servers, None).await
+    }
+
+    /// Creates an agent from the runtime config file at `config_path`, whose MCP server may be
+    /// launched as a child process, see `McpServerConfig::from_runtime_config_file`.
+    pub async fn from_config_file(config_path: &str, mcp_runtime_api_key: Option<String>) -> anyhow::Result<Self> {
+        let agent_mcp_config = McpRuntimeConfig::load_agent_config(config_path)
+            .context(format!("Failed to load MCP runtime config '{}'", config_path))?;
+        let server = McpServerConfig::from_runtime_config_file(config_path, &agent_mcp_config)?;
+        Self::initialize(agent_mcp_config, mcp_runtime_api_key, vec![server], None).await
+    }
+
+    /// Creates an agent whose LLM and MCP interactions go through `cassette`.
+    ///
+    /// In record mode the agent connects as usual and writes every exchange to the cassette.
+    /// In replay mode it connects to nothing and needs no API key: tools, LLM responses and
+    /// tool results all come from the cassette.
+    pub async fn with_cassette(
+        agent_mcp_config: McpRuntimeConfig,
+        mcp_runtime_api_key: Option<String>,
+        cassette: Arc<Cassette>,
+    ) -> anyhow::Result<Self> {
+        Self::initialize(agent_mcp_config, mcp_runtime_api_key, Vec::new(), Some(cassette)).await
+    }
+
+    /// `mcp_servers` empty means the single server of `agent_mcp_config`.
+    async fn initialize(
+        agent_mcp_config: McpRuntimeConfig,
+        mcp_runtime_api_key: Option<String>,
+        mcp_servers: Vec<McpServerConfig>,
+        cassette: Option<Arc<Cassette>>,
     ) -> anyhow::Result<Self> {
         let model_id = agent_mcp_config.agent_mcp_model_id.clone();
+        let replaying = cassette.as_ref().is_some_and(|cassette| cassette.is_replaying());
 
         let llm_mcp_api_key = if let Some(api_key) = mcp_runtime_api_key {
             api_key
+        } else if replaying {
+            String::new()
         } else if let Some(env_var_name) = &agent_mcp_config.agent_mcp_llm_api_key_env_var {
             env::var(env_var_name)
                 .context(format!("Environment variable '{}' for LLM API key must be set", env_var_name))?
@@ -65,13 +145,22 @@ impl McpAgent {
                 .context("LLM_MCP_API_KEY environment variable must be set")?
         };
 
-        let mcp_client = Arc::new(
-            initialize_mcp_client_v2(agent_mcp_config.clone())
-                .await
-                .context("Failed to initialize MCP client")?,
-        );
+        let mcp_servers = if replaying {
+            None
+        } else {
+            let mcp_servers = if mcp_servers.is_empty() {
+                vec![McpServerConfig::from_runtime_config(&agent_mcp_config)?]
+            } else {
+                mcp_servers
+            };
+            Some(Arc::new(
+                McpServers::connect(mcp_servers)
+                    .await
+                    .context("Failed to initialize MCP client")?,
+            ))
+        };
 
-        let list_tools = match get_tools_list_v2(mcp_client.clone()).await {
+        let list_tools = match Self::list_tools(mcp_servers.as_ref(), cassette.as_ref()).await {
             Ok(tools) => tools,
             Err(e) => {
                 warn!("⚠️ Could not retrieve tools at startup: {}", e);
@@ -94,14 +183,70 @@ impl McpAgent {
             llm_interaction: ChatLlmInteraction::new(
                 agent_mcp_config.agent_mcp_llm_url.clone(),
                 model_id,
-                llm_mcp_api_key,
+                llm_mcp_api_key.clone(),
             ),
-            mcp_client,
+            http_client: reqwest::Client::new(),
+            llm_api_key: llm_mcp_api_key,
+            mcp_servers,
             agent_mcp_config,
             tool_cache,
+            cassette,
+            max_concurrent_tool_calls: DEFAULT_MAX_CONCURRENT_TOOL_CALLS,
+            sessions: Arc::new(InMemorySessionStore::default()),
+            session_locks: Arc::new(SessionLocks::default()),
+            history_policy: HistoryPolicy::default(),
         })
     }
 
+    /// Limits how many tool calls of a single LLM turn run at the same time. `1` runs them one after the other.
+    pub fn with_max_concurrent_tool_calls(mut self, max_concurrent_tool_calls: usize) -> Self {
+        self.max_concurrent_tool_calls = max_concurrent_tool_calls.max(1);
+        self
+    }
+
+    /// Stores the history of the sessions somewhere else than in memory.
+    pub fn with_session_store(mut self, sessions: Arc<dyn SessionStore>) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    /// Sets how the history of a session is bounded. Defaults to keeping the last 40 messages.
+    pub fn with_history_policy(mut self, history_policy: HistoryPolicy) -> Self {
+        self.history_policy = history_policy;
+        self
+    }
+
+    /// Lists the tools of the MCP servers, or the tools recorded in a replayed cassette.
+    async fn list_tools(
+        mcp_servers: Option<&Arc<McpServers>>,
+        cassette: Option<&Arc<Cassette>>,
+    ) -> anyhow::Result<Vec<McpTool>> {
+        if let Some(cassette) = cassette.filter(|cassette| cassette.is_replaying()) {
+            return Ok(cassette.recorded_tools());
+        }
+        let mcp_servers = mcp_servers.ok_or_else(|| anyhow::anyhow!("MCP client is not initialized"))?;
+        let tools = mcp_servers.list_tools().await?;
+        if let Some(cassette) = cassette {
+            cassette.record_tools(&tools)?;
+        }
+        Ok(tools)
+    }
+
+    async fn call_tool(&self, tool_call: &ToolCall) -> anyhow::Result<CallToolResult> {
+        if let Some(cassette) = self.cassette.as_ref().filter(|cassette| cassette.is_replaying()) {
+            return cassette.replay_tool_call(tool_call);
+        }
+        let mcp_servers = self
+            .mcp_servers
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("MCP client is not initialized"))?;
+        let result = mcp_servers.call_tool(tool_call).await;
+        if let Some(cassette) = &self.cassette {
+            cassette.record_tool_call(tool_call, &result)?;
+        }
+        result
+    }
+
     pub fn get_available_tools(&self) -> Vec<Tool> {
         self.tool_cache.read().unwrap().get("").cloned().unwrap_or_default()
     }
@@ -116,7 +261,7 @@ impl McpAgent {
             }
         }
 
-        match get_tools_list_v2(self.mcp_client.clone()).await {
+        match Self::list_tools(self.mcp_servers.as_ref(), self.cassette.as_ref()).await {
             Ok(tools) => match define_all_tools(tools) {
                 Ok(new_tools) => {
                     let mut cache = self.tool_cache.write().unwrap();
@@ -147,15 +292,42 @@ impl McpAgent {
     ) -> anyhow::Result<ChatCompletionResponse> {
         debug!("Calling LLM API with payload: {:?}", request_payload);
 
+        if let Some(cassette) = self.cassette.as_ref().filter(|cassette| cassette.is_replaying()) {
+            return cassette.replay_llm(request_payload);
+        }
+
+        let response = self
+            .call_llm_with_retries(
+                || async {
+                    let response = self.llm_interaction.call_chat_completions_v2(request_payload).await?;
+                    debug!("LLM API Response: {:?}", response);
+                    Ok(response)
+                },
+                || true,
+            )
+            .await?;
+        if let Some(cassette) = &self.cassette {
+            cassette.record_llm(request_payload, &response)?;
+        }
+        Ok(response)
+    }
+
+    /// Runs an LLM call, retrying temporary errors with an exponential backoff while `may_retry` allows it.
+    /// Validation errors are not retried, and rate limits fail fast.
+    async fn call_llm_with_retries<T, Fut>(
+        &self,
+        mut call: impl FnMut() -> Fut,
+        may_retry: impl Fn() -> bool,
+    ) -> anyhow::Result<T>
+    where
+        Fut: std::future::Future<Output = anyhow::Result<T>>,
+    {
         let max_retries = 3;
         let mut delay = std::time::Duration::from_millis(1000);
 
         for attempt in 1..=max_retries {
-            match self.llm_interaction.call_chat_completions_v2(request_payload).await {
-                Ok(response) => {
-                    debug!("LLM API Response (attempt {}): {:?}", attempt, response);
-                    return Ok(response);
-                }
+            match call().await {
+                Ok(response) => return Ok(response),
                 Err(e) => {
                     let err_msg = e.to_string();
                     let is_rate_limit = err_msg.contains("429")
@@ -179,8 +351,8 @@ impl McpAgent {
                         ));
                     }
 
-                    if attempt == max_retries {
-                        error!("❌ LLM API call failed after {} attempts: {}", max_retries, e);
+                    if attempt == max_retries || !may_retry() {
+                        error!("❌ LLM API call failed after {} attempt(s): {}", attempt, e);
                         return Err(e).context("LLM chat completion API call failed after max retries");
                     }
 
@@ -259,19 +431,52 @@ impl McpAgent {
             },
         };
 
-        let response = self.call_api_v2(&request_payload).await?;
+        let turn = ctx.turn;
+        ctx.turn += 1;
+
+        let choice = match &ctx.events {
+            // A replayed cassette holds whole responses, which cannot be streamed
+            Some(events) if self.cassette.is_none() => {
+                // Once tokens went out, a retry would send them twice
+                let streamed = AtomicBool::new(false);
+                self.call_llm_with_retries(
+                    || {
+                        stream_chat_completion(
+                            &self.http_client,
+                            &self.agent_mcp_config.agent_mcp_llm_url,
+                            &self.llm_api_key,
+                            &request_payload,
+                            |content| {
+                                streamed.store(true, Ordering::SeqCst);
+                                let _ = events.send(AgentStreamEvent::Token { content, turn });
+                            },
+                        )
+                    },
+                    || !streamed.load(Ordering::SeqCst),
+                )
+                .await?
+            }
+            _ => {
+                let response = self.call_api_v2(&request_payload).await?;
 
-        if response.choices.is_empty() {
-            error!("LLM response contained no choices.");
-            anyhow::bail!("LLM response contained no choices.");
-        }
+                if response.choices.is_empty() {
+                    error!("LLM response contained no choices.");
+                    anyhow::bail!("LLM response contained no choices.");
+                }
 
-        let mut choice = response.choices[0].clone();
+                let mut choice = response.choices[0].clone();
 
-        // Clean <think> tags from the response content
-        if let Some(content) = choice.message.content.as_mut() {
-            *content = self.llm_interaction.remove_think_tags(content.clone()).await?;
-        }
+                // Clean <think> tags from the response content
+                if let Some(content) = choice.message.content.as_mut() {
+                    *content = self.llm_interaction.remove_think_tags(content.clone()).await?;
+                }
+
+                if let Some(content) = &choice.message.content {
+                    ctx.emit(AgentStreamEvent::Token { content: content.clone(), turn });
+                }
+                choice
+            }
+        };
 
         // Commit the assistant's response to message history
         ctx.messages.push(Message {
@@ -282,6 +487,9 @@ impl McpAgent {
         });
 
         let has_tool_calls = choice.message.tool_calls.as_ref().map(|tc| !tc.is_empty()).unwrap_or(false);
+        let requests_tools =
+            has_tool_calls || choice.finish_reason == self.agent_mcp_config.agent_mcp_finish_reason_tool_calls;
+        ctx.emit(AgentStreamEvent::TurnFinished { turn, final_answer: !requests_tools });
 
         if has_tool_calls {
             info!("LLM requested {} tool call(s).", choice.message.tool_calls.as_ref().unwrap().len());
@@ -294,7 +502,8 @@ impl McpAgent {
         }
     }
 
-    /// Executing: Run all tool calls from the LLM's response.
+    /// Executing: Run all tool calls from the LLM's response, up to `max_concurrent_tool_calls` at a time.
+    /// Results keep the order of the tool calls, as the conversation requires.
     /// Returns `Evaluating` with the tool results.
     async fn executing_step(
         &self,
@@ -304,124 +513,153 @@ impl McpAgent {
         info!("--- Executing ---");
 
         if let Some(tool_calls) = &choice.message.tool_calls {
-            let mut tool_results: Vec<Message> = Vec::new();
-
-            for tool_call in tool_calls {
-                info!("Executing tool call: {}", tool_call.id);
-                let tool_name = tool_call.function.name.clone();
-
-                match execute_tool_call_v2(self.mcp_client.clone(), tool_call.clone()).await {
-                    Ok(result) => {
-                        // Parse the result content
-                        let mut parsed_texts = Vec::new();
-                        if let Ok(json_arr) = serde_json::to_value(&result.content) {
-                            if let Some(arr) = json_arr.as_array() {
-                                for item in arr {
-                                    if item.get("type").and_then(|v| v.as_str()) == Some("text") {
-                                        if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
-                                            parsed_texts.push(text.to_string());
-                                        }
-                                    }
+            let messages = &ctx.messages;
+            let tool_results: Vec<Message> = stream::iter(tool_calls)
+                .map(|tool_call| self.tool_result_message(tool_call, messages, ctx.events.as_ref()))
+                .buffered(self.max_concurrent_tool_calls)
+                .collect()
+                .await;
+
+            if self.agent_mcp_config.agent_mcp_enable_evaluation.unwrap_or(false) {
+                Ok(AgentState::Evaluating(choice.clone(), tool_results))
+            } else {
+                ctx.messages.extend(tool_results);
+                Ok(AgentState::Thinking)
+            }
+        } else {
+            Ok(AgentState::Thinking)
+        }
+    }
+
+    /// Runs a single tool call and turns its result, or its error, into a tool message.
+    /// `messages` is the conversation so far, used to distill very large results.
+    async fn tool_result_message(
+        &self,
+        tool_call: &ToolCall,
+        messages: &[Message],
+        events: Option<&mpsc::UnboundedSender<AgentStreamEvent>>,
+    ) -> Message {
+        info!("Executing tool call: {}", tool_call.id);
+        let tool_name = tool_call.function.name.clone();
+        let emit = |event: AgentStreamEvent| {
+            if let Some(events) = events {
+                let _ = events.send(event);
+            }
+        };
+        emit(AgentStreamEvent::ToolCallStarted {
+            tool_call_id: tool_call.id.clone(),
+            name: tool_name.clone(),
+            arguments: tool_call.function.arguments.clone(),
+        });
+
+        let message = match self.call_tool(tool_call).await {
+            Ok(result) => {
+                // Parse the result content
+                let mut parsed_texts = Vec::new();
+                if let Ok(json_arr) = serde_json::to_value(&result.content) {
+                    if let Some(arr) = json_arr.as_array() {
+                        for item in arr {
+                            if item.get("type").and_then(|v| v.as_str()) == Some("text") {
+                                if let Some(text) = item.get("text").and_then(|v| v.as_str()) {
+                                    parsed_texts.push(text.to_string());
                                 }
                             }
                         }
+                    }
+                }
 
-                        let mut result_content_str = if !parsed_texts.is_empty() {
-                            parsed_texts.join("\n")
-                        } else {
-                            serde_json::to_string(&result.content).unwrap_or_else(|_| "[]".to_string())
+                let mut result_content_str = if !parsed_texts.is_empty() {
+                    parsed_texts.join("\n")
+                } else {
+                    serde_json::to_string(&result.content).unwrap_or_else(|_| "[]".to_string())
+                };
+
+                let original_len = result_content_str.chars().count();
+                info!("Tool '{}' returned {} chars.", tool_name, original_len);
+
+                // If tool output is very large, optionally sanitize/distill to avoid context overflow
+                if original_len > 8000 {
+                    if let Some(sanitizer_model) = &self.agent_mcp_config.agent_mcp_sanitizer_model_id {
+                        let user_query = messages.iter()
+                            .rev()
+                            .find(|m| m.role == "user")
+                            .and_then(|m| m.content.clone())
+                            .unwrap_or_default();
+
+                        let sys_prompt = format!(
+                            "The user wants to: {}. Below is a long tool output. Provide a comprehensive distillation of this data, keeping only relevant information, specific IDs, dates, and numbers. Output only the summarized facts.",
+                            user_query
+                        );
+
+                        let request_payload = ChatCompletionRequest {
+                            model: sanitizer_model.clone(),
+                            messages: vec![
+                                Message {
+                                    role: "system".to_string(),
+                                    content: Some(sys_prompt),
+                                    tool_call_id: None,
+                                    tool_calls: None,
+                                },
+                                Message {
+                                    role: "user".to_string(),
+                                    content: Some(if result_content_str.len() > 15000 {
+                                        result_content_str[..15000].to_string()
+                                    } else {
+                                        result_content_str.clone()
+                                    }),
+                                    tool_call_id: None,
+                                    tool_calls: None,
+                                },
+                            ],
+                            temperature: Some(0.0),
+                            max_tokens: Some(1024),
+                            top_p: Some(1.0),
+                            stop: None,
+                            stream: Some(false),
+                            tools: None,
+                            tool_choice: None,
                         };
 
-                        let original_len = result_content_str.chars().count();
-                        info!("Tool '{}' returned {} chars.", tool_name, original_len);
-
-                        // If tool output is very large, optionally sanitize/distill to avoid context overflow
-                        if original_len > 8000 {
-                            if let Some(sanitizer_model) = &self.agent_mcp_config.agent_mcp_sanitizer_model_id {
-                                let user_query = ctx.messages.iter()
-                                    .rev()
-                                    .find(|m| m.role == "user")
-                                    .and_then(|m| m.content.clone())
-                                    .unwrap_or_default();
-
-                                let sys_prompt = format!(
-                                    "The user wants to: {}. Below is a long tool output. Provide a comprehensive distillation of this data, keeping only relevant information, specific IDs, dates, and numbers. Output only the summarized facts.",
-                                    user_query
-                                );
-
-                                let request_payload = ChatCompletionRequest {
-                                    model: sanitizer_model.clone(),
-                                    messages: vec![
-                                        Message {
-                                            role: "system".to_string(),
-                                            content: Some(sys_prompt),
-                                            tool_call_id: None,
-                                            tool_calls: None,
-                                        },
-                                        Message {
-                                            role: "user".to_string(),
-                                            content: Some(if result_content_str.len() > 15000 {
-                                                result_content_str[..15000].to_string()
-                                            } else {
-                                                result_content_str.clone()
-                                            }),
-                                            tool_call_id: None,
-                                            tool_calls: None,
-                                        },
-                                    ],
-                                    temperature: Some(0.0),
-                                    max_tokens: Some(1024),
-                                    top_p: Some(1.0),
-                                    stop: None,
-                                    stream: Some(false),
-                                    tools: None,
-                                    tool_choice: None,
-                                };
-
-                                if let Ok(resp) = self.call_api_v2(&request_payload).await {
-                                    if let Some(first) = resp.choices.first() {
-                                        if let Some(text) = &first.message.content {
-                                            info!("Sanitizer compressed tool output from {} to {} chars", original_len, text.len());
-                                            result_content_str = text.clone();
-                                        }
-                                    }
+                        if let Ok(resp) = self.call_api_v2(&request_payload).await {
+                            if let Some(first) = resp.choices.first() {
+                                if let Some(text) = &first.message.content {
+                                    info!("Sanitizer compressed tool output from {} to {} chars", original_len, text.len());
+                                    result_content_str = text.clone();
                                 }
                             }
                         }
-
-                        tool_results.push(Message {
-                            role: self.agent_mcp_config.agent_mcp_role_tool.clone(),
-                            content: Some(result_content_str),
-                            tool_call_id: Some(tool_call.id.clone()),
-                            tool_calls: None,
-                        });
                     }
-                    Err(e) => {
-                        error!("Error executing tool {}: {}", tool_call.id, e);
+                }
 
-                        let error_content = json!({
-                            "error": format!("Error executing tool '{}': {}", tool_call.id, e),
-                            "tool_call_id": tool_call.id
-                        });
-                        tool_results.push(Message {
-                            role: self.agent_mcp_config.agent_mcp_role_tool.clone(),
-                            content: Some(error_content.to_string()),
-                            tool_call_id: Some(tool_call.id.clone()),
-                            tool_calls: None,
-                        });
-                    }
+                Message {
+                    role: self.agent_mcp_config.agent_mcp_role_tool.clone(),
+                    content: Some(result_content_str),
+                    tool_call_id: Some(tool_call.id.clone()),
+                    tool_calls: None,
                 }
             }
-
-            if self.agent_mcp_config.agent_mcp_enable_evaluation.unwrap_or(false) {
-                Ok(AgentState::Evaluating(choice.clone(), tool_results))
-            } else {
-                ctx.messages.extend(tool_results);
-                Ok(AgentState::Thinking)
+            Err(e) => {
+                error!("Error executing tool {}: {}", tool_call.id, e);
+
+                let error_content = json!({
+                    "error": format!("Error executing tool '{}': {}", tool_call.id, e),
+                    "tool_call_id": tool_call.id
+                });
+                Message {
+                    role: self.agent_mcp_config.agent_mcp_role_tool.clone(),
+                    content: Some(error_content.to_string()),
+                    tool_call_id: Some(tool_call.id.clone()),
+                    tool_calls: None,
+                }
             }
-        } else {
-            Ok(AgentState::Thinking)
-        }
+        };
+
+        emit(AgentStreamEvent::ToolResultReceived {
+            tool_call_id: tool_call.id.clone(),
+            name: tool_name,
+            content: message.content.clone().unwrap_or_default(),
+        });
+        message
     }
 
     /// Evaluating: Send tool results + evaluation prompt to LLM.
@@ -486,6 +724,11 @@ impl McpAgent {
                     }
                 };
 
+                ctx.emit(AgentStreamEvent::EvaluationVerdict {
+                    satisfactory: !is_unsatisfactory,
+                    reason: reason.clone(),
+                });
+
                 if is_unsatisfactory {
                     warn!("Tool execution unsatisfactory: {}", reason);
                     return Ok(AgentState::Correcting(reason));
@@ -589,10 +832,16 @@ impl McpAgent {
     }
 
     pub fn reset_messages(&self) -> anyhow::Result<()> {
-        // No-op for backwards compatibility: execution state is request-scoped
+        // No-op for backwards compatibility: execution state is request-scoped, see `reset_session`
         Ok(())
     }
 
+    /// Forgets the history of a session.
+    pub fn reset_session(&self, session_id: &str) {
+        self.sessions.remove(session_id);
+        info!("🔄 Reset session: {}", session_id);
+    }
+
     pub async fn run_agent_internal(
         &self,
         user_message: Message,
@@ -604,29 +853,186 @@ impl McpAgent {
         &self,
         user_message: Message,
         system_prompt_override: Option<String>,
+    ) -> anyhow::Result<Option<Message>> {
+        self.run_agent(None, user_message, system_prompt_override, None).await
+    }
+
+    /// Runs the agent on a message of a multi-turn conversation: the history of the session,
+    /// tool calls and results included, is sent along with the message, and the session is
+    /// updated with this turn. Turns of the same session run one at a time, in order of arrival.
+    pub async fn run_agent_in_session(
+        &self,
+        session_id: &str,
+        user_message: Message,
+        system_prompt_override: Option<String>,
+    ) -> anyhow::Result<Option<Message>> {
+        self.run_agent(Some(session_id), user_message, system_prompt_override, None).await
+    }
+
+    async fn run_agent(
+        &self,
+        session_id: Option<&str>,
+        user_message: Message,
+        system_prompt_override: Option<String>,
+        events: Option<mpsc::UnboundedSender<AgentStreamEvent>>,
     ) -> anyhow::Result<Option<Message>> {
         let llm_all_tool = self.get_tools_for_session("").await;
+        // Held until the session is saved, so that the next turn sees this one
+        let _turn = match session_id {
+            Some(session_id) => Some(self.session_locks.lock(session_id).await),
+            None => None,
+        };
+        let session = session_id.map(|session_id| self.sessions.load(session_id)).unwrap_or_default();
 
-        let system_message = system_prompt_override
+        let mut system_message = system_prompt_override
             .unwrap_or_else(|| self.agent_mcp_config.agent_mcp_system_prompt.clone());
+        if let Some(summary) = &session.summary {
+            system_message = format!("{}\n\nSummary of the earlier conversation:\n{}", system_message, summary);
+        }
 
-        let messages = vec![
-            Message {
-                role: "system".to_string(),
-                content: Some(system_message),
-                tool_call_id: None,
-                tool_calls: None,
-            },
-            user_message,
-        ];
+        let mut messages = vec![Message {
+            role: "system".to_string(),
+            content: Some(system_message),
+            tool_call_id: None,
+            tool_calls: None,
+        }];
+        messages.extend(session.messages);
+        messages.push(user_message);
 
         let mut ctx = McpAgentRunContext {
             state: AgentState::Thinking,
             messages,
             llm_all_tool,
+            events,
+            turn: 0,
         };
 
-        self.execute_loop(&mut ctx).await
+        let final_message = self.execute_loop(&mut ctx).await?;
+
+        if let Some(session_id) = session_id {
+            self.save_session(session_id, session.summary, ctx.messages, final_message.as_ref())
+                .await;
+        }
+        Ok(final_message)
+    }
+
+    /// Stores the messages of a run as the history of its session, bounded by the history policy.
+    async fn save_session(
+        &self,
+        session_id: &str,
+        mut summary: Option<String>,
+        messages: Vec<Message>,
+        final_message: Option<&Message>,
+    ) {
+        let mut history = history_of_run(messages);
+        // Fallback answers are not part of the run's messages
+        let answered = history.last().is_some_and(|message| {
+            message.role == self.agent_mcp_config.agent_mcp_role_assistant && message.tool_call_id.is_none()
+        });
+        if let (false, Some(final_message)) = (answered, final_message) {
+            history.push(Message {
+                role: final_message.role.clone(),
+                content: final_message.content.clone(),
+                tool_call_id: None,
+                tool_calls: None,
+            });
+        }
+
+        let cut_index = self.history_policy.cut_index(&history);
+        if cut_index > 0 {
+            let dropped: Vec<Message> = history.drain(..cut_index).collect();
+            if let HistoryPolicy::Summarize { .. } = self.history_policy {
+                match self.summarize_history(summary.as_deref(), &dropped).await {
+                    Ok(new_summary) => summary = Some(new_summary),
+                    Err(e) => warn!("⚠️ Failed to summarize the history of session {}, truncating it: {}", session_id, e),
+                }
+            }
+            debug!("Dropped {} message(s) from the history of session {}", dropped.len(), session_id);
+        }
+
+        self.sessions.save(session_id, Session { summary, messages: history });
+    }
+
+    /// Asks the LLM for a summary of `messages`, extending the previous summary if any.
+    async fn summarize_history(&self, previous_summary: Option<&str>, messages: &[Message]) -> anyhow::Result<String> {
+        let mut transcript = String::new();
+        if let Some(previous_summary) = previous_summary {
+            transcript.push_str(&format!("Summary of the conversation so far: {}\n", previous_summary));
+        }
+        for message in messages {
+            if let Some(content) = message.content.as_deref().filter(|content| !content.is_empty()) {
+                transcript.push_str(&format!("{}: {}\n", message.role, content));
+            }
+            for tool_call in message.tool_calls.iter().flatten() {
+                transcript.push_str(&format!(
+                    "{}: called tool {} with {}\n",
+                    message.role, tool_call.function.name, tool_call.function.arguments
+                ));
+            }
+        }
+
+        let request_payload = ChatCompletionRequest {
+            model: self.llm_interaction.model_id.clone(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: Some(
+                        "Summarize the following conversation in a few sentences. Keep the facts, names, \
+                         values and decisions needed to answer follow-up questions."
+                            .to_string(),
+                    ),
+                    tool_call_id: None,
+                    tool_calls: None,
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: Some(transcript),
+                    tool_call_id: None,
+                    tool_calls: None,
+                },
+            ],
+            temperature: Some(0.0),
+            max_tokens: Some(1024),
+            top_p: Some(1.0),
+            stop: None,
+            stream: Some(false),
+            tools: None,
+            tool_choice: None,
+        };
+
+        let response = self.call_api_v2(&request_payload).await?;
+        let content = response
+            .choices
+            .first()
+            .and_then(|choice| choice.message.content.clone())
+            .ok_or_else(|| anyhow::anyhow!("LLM returned no summary"))?;
+        Ok(self.llm_interaction.remove_think_tags(content).await?)
+    }
+
+    /// Runs the agent in the background, streaming the tokens of the LLM and the progress of the run.
+    /// The stream ends with a `Finished` or `Failed` event.
+    /// With a `session_id`, the run is a turn of that session, as in `run_agent_in_session`.
+    pub fn run_agent_stream(
+        &self,
+        session_id: Option<String>,
+        user_message: Message,
+        system_prompt_override: Option<String>,
+    ) -> mpsc::UnboundedReceiver<AgentStreamEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let agent = self.clone();
+        tokio::spawn(async move {
+            let result = agent
+                .run_agent(session_id.as_deref(), user_message, system_prompt_override, Some(sender.clone()))
+                .await;
+            let event = match result {
+                Ok(message) => AgentStreamEvent::Finished {
+                    content: message.and_then(|message| message.content),
+                },
+                Err(e) => AgentStreamEvent::Failed { error: e.to_string() },
+            };
+            let _ = sender.send(event);
+        });
+        receiver
     }
 
     pub async fn submit_user_text(&self, user_text: String) -> Result<String> {
@@ -648,12 +1054,195 @@ impl McpAgent {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::Value;
+    use test_support::{MockLlm, MockMcpServer};
 
     fn assert_send_sync<T: Send + Sync>() {}
 
+    #[tokio::test]
+    async fn test_recorded_run_replays_without_llm_or_mcp_server() {
+        let mcp_server = MockMcpServer::new()
+            .with_tool(
+                "get_weather",
+                "Weather of a city",
+                json!({"properties": {"city": {"type": "string"}}}),
+                json!("sunny"),
+            )
+            .start()
+            .await
+            .unwrap();
+        let llm = MockLlm::new()
+            .with_completion(MockLlm::completion(
+                json!({
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [MockLlm::tool_call("call_1", "get_weather", json!({"city": "Paris"}))]
+                }),
+                "tool_calls",
+            ))
+            .with_completion(MockLlm::completion(
+                json!({"role": "assistant", "content": "It is sunny in Paris."}),
+                "stop",
+            ))
+            .start()
+            .await
+            .unwrap();
+        let record_config = McpRuntimeConfig {
+            agent_mcp_llm_url: llm.url(),
+            ..mcp_server.runtime_config()
+        };
+        let replay_config = McpRuntimeConfig {
+            agent_mcp_llm_url: llm.url(),
+            ..mcp_server.runtime_config()
+        };
+        let path = std::env::temp_dir().join(format!("agent-cassette-{}.json", uuid::Uuid::new_v4()));
+
+        let recorder = McpAgent::with_cassette(record_config, Some("key".to_string()), Arc::new(Cassette::record(&path)))
+            .await
+            .unwrap();
+        let answer = recorder.submit_user_text("What is the weather in Paris?".to_string()).await.unwrap();
+        assert_eq!(answer, "It is sunny in Paris.");
+        assert_eq!(mcp_server.calls().len(), 1);
+
+        // Dropping the agent writes the cassette, and nothing answers the replay but the cassette
+        drop(recorder);
+        drop(mcp_server);
+        drop(llm);
+
+        let player = McpAgent::with_cassette(replay_config, None, Arc::new(Cassette::replay(&path).unwrap()))
+            .await
+            .unwrap();
+        assert!(player.mcp_servers.is_none());
+        assert_eq!(player.get_available_tools().len(), 1);
+        let replayed = player.submit_user_text("What is the weather in Paris?".to_string()).await.unwrap();
+        assert_eq!(replayed, answer);
+
+        let _ = std::fs::remove_file(path);
+    }
+
     #[test]
     fn test_mcp_agent_concurrency_traits() {
         assert_send_sync::<McpAgent>();
         assert_send_sync::<McpAgentRunContext>();
     }
+
+    #[tokio::test]
+    async fn test_tool_calls_run_concurrently_and_keep_their_order() {
+        let weather = |args: Value| Ok(json!(format!("sunny in {}", args["city"].as_str().unwrap_or_default())));
+        let mcp_server = MockMcpServer::new()
+            .with_tool_fn("slow_weather", "Weather of a city", json!({"properties": {"city": {"type": "string"}}}), weather)
+            .with_tool_fn("fast_weather", "Weather of a city", json!({"properties": {"city": {"type": "string"}}}), weather)
+            .with_delay("slow_weather", std::time::Duration::from_millis(200))
+            .with_delay("fast_weather", std::time::Duration::from_millis(50))
+            .start()
+            .await
+            .unwrap();
+        let llm = MockLlm::new()
+            .with_completion(MockLlm::completion(
+                json!({
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [
+                        MockLlm::tool_call("call_1", "slow_weather", json!({"city": "Paris"})),
+                        MockLlm::tool_call("call_2", "fast_weather", json!({"city": "Lyon"})),
+                        MockLlm::tool_call("call_3", "fast_weather", json!({"city": "Nice"}))
+                    ]
+                }),
+                "tool_calls",
+            ))
+            .with_completion(MockLlm::completion(
+                json!({"role": "assistant", "content": "Sunny everywhere."}),
+                "stop",
+            ))
+            .start()
+            .await
+            .unwrap();
+        let config = McpRuntimeConfig {
+            agent_mcp_llm_url: llm.url(),
+            ..mcp_server.runtime_config()
+        };
+
+        let agent = McpAgent::new(config, Some("key".to_string()))
+            .await
+            .unwrap()
+            .with_max_concurrent_tool_calls(2);
+        let answer = agent.submit_user_text("Weather in Paris, Lyon and Nice?".to_string()).await.unwrap();
+        assert_eq!(answer, "Sunny everywhere.");
+
+        assert_eq!(mcp_server.calls().len(), 3);
+        assert_eq!(mcp_server.max_concurrent_calls(), 2);
+
+        // The slow call finished last, yet its result still comes first
+        let requests = llm.requests();
+        let tool_results: Vec<(&str, &str)> = requests[1]["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|message| message["role"] == "tool")
+            .map(|message| (message["tool_call_id"].as_str().unwrap(), message["content"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            tool_results,
+            [("call_1", "sunny in Paris"), ("call_2", "sunny in Lyon"), ("call_3", "sunny in Nice")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_streamed_tokens_are_tagged_with_their_turn() {
+        let mcp_server = MockMcpServer::new()
+            .with_tool("get_weather", "Weather of a city", json!({"properties": {"city": {"type": "string"}}}), json!("sunny"))
+            .start()
+            .await
+            .unwrap();
+        let llm = MockLlm::new()
+            .with_stream(vec![
+                MockLlm::chunk(json!({"role": "assistant", "content": "Let me check."}), None),
+                MockLlm::chunk(
+                    json!({"tool_calls": [{"index": 0, "id": "call_1", "function": {"name": "get_weather", "arguments": "{\"city\": \"Paris\"}"}}]}),
+                    None,
+                ),
+                MockLlm::chunk(json!({}), Some("tool_calls")),
+            ])
+            .with_stream(vec![
+                MockLlm::chunk(json!({"role": "assistant", "content": "It is "}), None),
+                MockLlm::chunk(json!({"content": "sunny."}), Some("stop")),
+            ])
+            .start()
+            .await
+            .unwrap();
+        let config = McpRuntimeConfig {
+            agent_mcp_llm_url: llm.url(),
+            ..mcp_server.runtime_config()
+        };
+        let agent = McpAgent::new(config, Some("key".to_string())).await.unwrap();
+
+        let user_message = Message {
+            role: "user".to_string(),
+            content: Some("Weather in Paris?".to_string()),
+            tool_call_id: None,
+            tool_calls: None,
+        };
+        let mut receiver = agent.run_agent_stream(None, user_message, None);
+        let mut events = Vec::new();
+        while let Some(event) = receiver.recv().await {
+            events.push(event);
+        }
+
+        let answer: String = events
+            .iter()
+            .filter_map(|event| match event {
+                AgentStreamEvent::Token { content, turn: 1 } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(answer, "It is sunny.");
+        assert!(events.contains(&AgentStreamEvent::Token { content: "Let me check.".to_string(), turn: 0 }));
+        assert!(events.contains(&AgentStreamEvent::TurnFinished { turn: 0, final_answer: false }));
+        assert!(events.contains(&AgentStreamEvent::TurnFinished { turn: 1, final_answer: true }));
+        assert_eq!(
+            events.last(),
+            Some(&AgentStreamEvent::Finished { content: Some("It is sunny.".to_string()) })
+        );
+        assert_eq!(mcp_server.calls().len(), 1);
+    }
 }
@@ -0,0 +1,281 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+use llm_api::chat::{ChatCompletionRequest, ChatCompletionResponse, ToolCall};
+use rmcp::model::{CallToolResult, Tool};
+
+/// Whether a `Cassette` captures live interactions or plays them back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Calls go to the live LLM and MCP server, and every exchange is kept to be written to the cassette file.
+    Record,
+    /// Calls are answered from the cassette file. Nothing goes to the network.
+    Replay,
+}
+
+/// One chat-completions exchange.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LlmInteraction {
+    pub request: Value,
+    pub response: Value,
+}
+
+/// One MCP tool call, with its result or the error it raised.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ToolInteraction {
+    pub name: String,
+    /// Arguments as sent by the LLM, parsed as JSON when possible.
+    pub arguments: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<CallToolResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct CassetteContent {
+    /// Tools listed by the MCP server.
+    #[serde(default)]
+    tools: Vec<Tool>,
+    #[serde(default)]
+    llm_interactions: Vec<LlmInteraction>,
+    #[serde(default)]
+    tool_interactions: Vec<ToolInteraction>,
+}
+
+#[derive(Default)]
+struct CassetteState {
+    content: CassetteContent,
+    used_llm_interactions: Vec<bool>,
+    used_tool_interactions: Vec<bool>,
+}
+
+/// Recording of the LLM and MCP interactions of a `McpAgent`, stored as a JSON file.
+///
+/// In replay mode, a request is answered by the first unused recorded interaction with the
+/// same content, so an agent run can be reproduced offline as long as it sends the same requests.
+///
+/// In record mode the file is written by `flush`, and when the cassette is dropped.
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    state: Mutex<CassetteState>,
+}
+
+impl Cassette {
+    /// Starts an empty recording, written to `path` when flushed or dropped.
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            mode: CassetteMode::Record,
+            state: Mutex::new(CassetteState::default()),
+        }
+    }
+
+    /// Loads the recording stored at `path` for playback.
+    pub fn replay(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let raw = fs::read_to_string(path).with_context(|| format!("Failed to read cassette {}", path.display()))?;
+        let content: CassetteContent =
+            serde_json::from_str(&raw).with_context(|| format!("Invalid cassette {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            mode: CassetteMode::Replay,
+            state: Mutex::new(CassetteState {
+                used_llm_interactions: vec![false; content.llm_interactions.len()],
+                used_tool_interactions: vec![false; content.tool_interactions.len()],
+                content,
+            }),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.mode == CassetteMode::Replay
+    }
+
+    pub fn record_tools(&self, tools: &[Tool]) -> anyhow::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.content.tools = tools.to_vec();
+        Ok(())
+    }
+
+    pub fn recorded_tools(&self) -> Vec<Tool> {
+        self.state.lock().unwrap().content.tools.clone()
+    }
+
+    pub fn record_llm(&self, request: &ChatCompletionRequest, response: &ChatCompletionResponse) -> anyhow::Result<()> {
+        let interaction = LlmInteraction {
+            request: serde_json::to_value(request)?,
+            response: serde_json::to_value(response)?,
+        };
+        let mut state = self.state.lock().unwrap();
+        state.content.llm_interactions.push(interaction);
+        state.used_llm_interactions.push(false);
+        Ok(())
+    }
+
+    pub fn replay_llm(&self, request: &ChatCompletionRequest) -> anyhow::Result<ChatCompletionResponse> {
+        let request = serde_json::to_value(request)?;
+        let mut state = self.state.lock().unwrap();
+        let CassetteState {
+            content,
+            used_llm_interactions,
+            ..
+        } = &mut *state;
+        let index = content
+            .llm_interactions
+            .iter()
+            .enumerate()
+            .position(|(index, interaction)| !used_llm_interactions[index] && interaction.request == request)
+            .ok_or_else(|| anyhow::anyhow!("No recorded LLM response matches the request in cassette {}", self.path.display()))?;
+        used_llm_interactions[index] = true;
+        debug!("Replaying LLM interaction #{} from cassette", index);
+        Ok(serde_json::from_value(content.llm_interactions[index].response.clone())?)
+    }
+
+    pub fn record_tool_call(&self, tool_call: &ToolCall, result: &anyhow::Result<CallToolResult>) -> anyhow::Result<()> {
+        let (result, error) = match result {
+            Ok(result) => (Some(result.clone()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let interaction = ToolInteraction {
+            name: tool_call.function.name.clone(),
+            arguments: tool_arguments(tool_call),
+            result,
+            error,
+        };
+        let mut state = self.state.lock().unwrap();
+        state.content.tool_interactions.push(interaction);
+        state.used_tool_interactions.push(false);
+        Ok(())
+    }
+
+    pub fn replay_tool_call(&self, tool_call: &ToolCall) -> anyhow::Result<CallToolResult> {
+        let arguments = tool_arguments(tool_call);
+        let mut state = self.state.lock().unwrap();
+        let CassetteState {
+            content,
+            used_tool_interactions,
+            ..
+        } = &mut *state;
+        let index = content
+            .tool_interactions
+            .iter()
+            .enumerate()
+            .position(|(index, interaction)| {
+                !used_tool_interactions[index]
+                    && interaction.name == tool_call.function.name
+                    && interaction.arguments == arguments
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No recorded result for tool '{}' with arguments {} in cassette {}",
+                    tool_call.function.name,
+                    arguments,
+                    self.path.display()
+                )
+            })?;
+        used_tool_interactions[index] = true;
+        let interaction = &content.tool_interactions[index];
+        match (&interaction.result, &interaction.error) {
+            (Some(result), _) => Ok(result.clone()),
+            (None, error) => Err(anyhow::anyhow!(error.clone().unwrap_or_default())),
+        }
+    }
+
+    /// Writes the interactions recorded so far to the cassette file. Does nothing in replay mode.
+    pub fn flush(&self) -> anyhow::Result<()> {
+        if self.mode == CassetteMode::Record {
+            let state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            fs::write(&self.path, serde_json::to_string_pretty(&state.content)?)
+                .with_context(|| format!("Failed to write cassette {}", self.path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            warn!("⚠️ Failed to save cassette: {}", e);
+        }
+    }
+}
+
+fn tool_arguments(tool_call: &ToolCall) -> Value {
+    serde_json::from_str(&tool_call.function.arguments)
+        .unwrap_or_else(|_| Value::String(tool_call.function.arguments.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_api::chat::Message;
+    use serde_json::json;
+
+    fn request(content: &str) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: "test-model".to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: Some(content.to_string()),
+                tool_call_id: None,
+                tool_calls: None,
+            }],
+            temperature: Some(0.0),
+            max_tokens: Some(1024),
+            top_p: Some(1.0),
+            stop: None,
+            stream: Some(false),
+            tools: None,
+            tool_choice: None,
+        }
+    }
+
+    fn response(content: &str) -> ChatCompletionResponse {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "test-model",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_replay_matches_recorded_requests() {
+        let path = std::env::temp_dir().join(format!("cassette-{}.json", uuid::Uuid::new_v4()));
+        let recorder = Cassette::record(&path);
+        recorder.record_llm(&request("first"), &response("one")).unwrap();
+        recorder.record_llm(&request("second"), &response("two")).unwrap();
+        assert!(!path.exists());
+        drop(recorder);
+
+        let player = Cassette::replay(&path).unwrap();
+        let answer = |content: &str| {
+            player
+                .replay_llm(&request(content))
+                .map(|response| response.choices[0].message.content.clone().unwrap_or_default())
+        };
+        assert_eq!(answer("second").unwrap(), "two");
+        assert_eq!(answer("first").unwrap(), "one");
+        // Each recorded interaction answers once.
+        assert!(answer("first").is_err());
+        let _ = fs::remove_file(path);
+    }
+}
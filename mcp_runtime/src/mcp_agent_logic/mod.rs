@@ -1,2 +1,5 @@
 pub mod agent;
+pub mod cassette;
 pub mod process_response;
+pub mod session;
+pub mod streaming;
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::OwnedMutexGuard;
+
+use llm_api::chat::Message;
+
+/// Sessions unused for this long are dropped by the `InMemorySessionStore`.
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How the history of a session is kept within bounds after each turn.
+///
+/// History is only ever cut right before a user message, so an assistant message requesting tool
+/// calls always stays with the tool results answering it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryPolicy {
+    /// The whole history is sent to the LLM on every turn.
+    Unbounded,
+    /// Only the most recent `max_messages` messages are kept, older ones are forgotten.
+    Truncate { max_messages: usize },
+    /// Once the history exceeds `max_messages`, the messages before the most recent `keep_recent`
+    /// are replaced by an LLM-written summary, added to the system prompt of the next turns.
+    Summarize { max_messages: usize, keep_recent: usize },
+}
+
+impl Default for HistoryPolicy {
+    fn default() -> Self {
+        HistoryPolicy::Truncate { max_messages: 40 }
+    }
+}
+
+impl HistoryPolicy {
+    /// Index of the first message to keep, `0` when the history is within bounds.
+    pub(crate) fn cut_index(&self, messages: &[Message]) -> usize {
+        let keep = match *self {
+            HistoryPolicy::Unbounded => return 0,
+            HistoryPolicy::Truncate { max_messages } => max_messages,
+            HistoryPolicy::Summarize { max_messages, keep_recent } => {
+                if messages.len() <= max_messages {
+                    return 0;
+                }
+                keep_recent.min(max_messages)
+            }
+        };
+        if messages.len() <= keep {
+            return 0;
+        }
+        let earliest = messages.len() - keep;
+        // A turn longer than the bound is kept whole rather than losing its question
+        messages[earliest..]
+            .iter()
+            .position(|message| message.role == "user")
+            .map(|position| earliest + position)
+            .or_else(|| messages.iter().rposition(|message| message.role == "user"))
+            .unwrap_or(0)
+    }
+}
+
+/// Conversation of a session, without the system prompt.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// Summary of the messages dropped by a `HistoryPolicy::Summarize` policy.
+    pub summary: Option<String>,
+    /// User, assistant and tool messages, oldest first.
+    pub messages: Vec<Message>,
+}
+
+/// Keeps the messages of a finished run worth remembering: no system messages, and no tool-call
+/// requests left unanswered, which the LLM API would reject on the next turn.
+pub(crate) fn history_of_run(messages: Vec<Message>) -> Vec<Message> {
+    let mut history = Vec::new();
+    let mut messages = messages.into_iter().filter(|message| message.role != "system").peekable();
+    while let Some(message) = messages.next() {
+        let requests_tools = message.tool_calls.as_ref().is_some_and(|tool_calls| !tool_calls.is_empty());
+        if requests_tools && !messages.peek().is_some_and(|next| next.tool_call_id.is_some()) {
+            continue;
+        }
+        history.push(message);
+    }
+    history
+}
+
+/// Serializes the turns of each session, so that concurrent turns do not overwrite each other's
+/// history. Turns of different sessions still run concurrently.
+#[derive(Default)]
+pub(crate) struct SessionLocks {
+    locks: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl SessionLocks {
+    /// Waits for the previous turns of the session to finish. The session is free again when
+    /// the guard is dropped.
+    pub(crate) async fn lock(&self, session_id: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap();
+            // Locks neither held nor awaited are only referenced by the map
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            locks.entry(session_id.to_string()).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+}
+
+/// Storage of the sessions of an `McpAgent`, keyed by session id.
+pub trait SessionStore: Send + Sync {
+    /// The session, or an empty one when the id is unknown.
+    fn load(&self, session_id: &str) -> Session;
+
+    fn save(&self, session_id: &str, session: Session);
+
+    fn remove(&self, session_id: &str);
+}
+
+/// Keeps sessions in memory, dropping those idle for longer than their time-to-live.
+pub struct InMemorySessionStore {
+    ttl: Duration,
+    sessions: Mutex<HashMap<String, (Session, Instant)>>,
+}
+
+impl Default for InMemorySessionStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_TTL)
+    }
+}
+
+impl InMemorySessionStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    fn load(&self, session_id: &str) -> Session {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        sessions.retain(|_, (_, last_used)| now.duration_since(*last_used) < self.ttl);
+        sessions
+            .get(session_id)
+            .map(|(session, _)| session.clone())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, session_id: &str, session: Session) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), (session, Instant::now()));
+    }
+
+    fn remove(&self, session_id: &str) {
+        self.sessions.lock().unwrap().remove(session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_api::chat::ToolCall;
+
+    fn message(role: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: Some(role.to_string()),
+            tool_call_id: None,
+            tool_calls: None,
+        }
+    }
+
+    #[test]
+    fn test_history_is_cut_before_a_user_message() {
+        let messages: Vec<Message> = ["user", "assistant", "tool", "assistant", "user", "assistant"]
+            .iter()
+            .map(|role| message(role))
+            .collect();
+
+        assert_eq!(HistoryPolicy::Unbounded.cut_index(&messages), 0);
+        assert_eq!(HistoryPolicy::Truncate { max_messages: 10 }.cut_index(&messages), 0);
+        // Keeping 3 messages would start on a tool result, so only the last turn is kept
+        assert_eq!(HistoryPolicy::Truncate { max_messages: 3 }.cut_index(&messages), 4);
+        // A bound shorter than the last turn keeps that turn whole
+        assert_eq!(HistoryPolicy::Truncate { max_messages: 1 }.cut_index(&messages), 4);
+        assert_eq!(
+            HistoryPolicy::Summarize { max_messages: 8, keep_recent: 2 }.cut_index(&messages),
+            0
+        );
+        assert_eq!(
+            HistoryPolicy::Summarize { max_messages: 5, keep_recent: 2 }.cut_index(&messages),
+            4
+        );
+    }
+
+    #[test]
+    fn test_unanswered_tool_calls_are_not_remembered() {
+        let tool_calls: Vec<ToolCall> = serde_json::from_value(serde_json::json!([
+            {"id": "call_1", "type": "function", "function": {"name": "get_weather", "arguments": "{}"}}
+        ]))
+        .unwrap();
+        let requesting = Message {
+            tool_calls: Some(tool_calls),
+            ..message("assistant")
+        };
+        let result = Message {
+            tool_call_id: Some("call_1".to_string()),
+            ..message("tool")
+        };
+
+        let history = history_of_run(vec![
+            message("system"),
+            message("user"),
+            requesting.clone(),
+            requesting.clone(),
+            result,
+            requesting,
+            message("system"),
+            message("assistant"),
+        ]);
+        let roles: Vec<&str> = history.iter().map(|message| message.role.as_str()).collect();
+        assert_eq!(roles, ["user", "assistant", "tool", "assistant"]);
+        assert!(history[3].tool_calls.is_none());
+    }
+
+    #[test]
+    fn test_idle_sessions_expire() {
+        let store = InMemorySessionStore::new(Duration::from_millis(20));
+        store.save("a", Session {
+            summary: None,
+            messages: vec![message("user")],
+        });
+        assert_eq!(store.load("a").messages.len(), 1);
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(store.load("a").messages.is_empty());
+        assert!(store.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_turns_of_a_session_run_one_at_a_time() {
+        let locks = SessionLocks::default();
+        let turn = locks.lock("a").await;
+
+        let waiting = tokio::time::timeout(Duration::from_millis(20), locks.lock("a")).await;
+        assert!(waiting.is_err());
+        let _other_session = tokio::time::timeout(Duration::from_millis(20), locks.lock("b"))
+            .await
+            .unwrap();
+
+        drop(turn);
+        let _next_turn = tokio::time::timeout(Duration::from_millis(20), locks.lock("a"))
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::{Value, json};
+use tracing::debug;
+
+use llm_api::chat::{ChatCompletionRequest, Choice};
+
+/// Progress of a streamed `McpAgent` run, see `McpAgent::run_agent_stream`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentStreamEvent {
+    /// Piece of assistant text of the LLM turn `turn`, in the order generated by the LLM.
+    Token { content: String, turn: usize },
+    /// End of the LLM turn `turn`. When `final_answer` is false, the turn requested tool calls:
+    /// its tokens were commentary, and the answer comes in a later turn.
+    TurnFinished { turn: usize, final_answer: bool },
+    ToolCallStarted {
+        tool_call_id: String,
+        name: String,
+        arguments: String,
+    },
+    ToolResultReceived {
+        tool_call_id: String,
+        name: String,
+        content: String,
+    },
+    EvaluationVerdict { satisfactory: bool, reason: String },
+    /// Last event of a successful run, with the final answer.
+    Finished { content: Option<String> },
+    /// Last event of a failed run.
+    Failed { error: String },
+}
+
+/// Calls a chat-completions endpoint in streaming mode, passing each piece of assistant text
+/// to `on_token` as it arrives, and returns the assembled choice.
+pub(crate) async fn stream_chat_completion(
+    http_client: &reqwest::Client,
+    llm_url: &str,
+    api_key: &str,
+    request_payload: &ChatCompletionRequest,
+    mut on_token: impl FnMut(String),
+) -> anyhow::Result<Choice> {
+    let mut body = serde_json::to_value(request_payload)?;
+    body["stream"] = Value::Bool(true);
+
+    let mut response = http_client
+        .post(llm_url)
+        .bearer_auth(api_key)
+        .json(&body)
+        .send()
+        .await
+        .context("LLM streaming chat completion request failed")?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        anyhow::bail!("LLM streaming chat completion failed with status {}: {}", status, text);
+    }
+
+    let mut assembler = ChoiceAssembler::default();
+    let mut think_filter = ThinkFilter::default();
+    let mut lines = LineBuffer::default();
+
+    'chunks: while let Some(chunk) = response.chunk().await? {
+        // Server-sent events, one `data:` line per chunk of the completion
+        for line in lines.push(&chunk) {
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim();
+            if data == "[DONE]" {
+                assembler.done = true;
+                break 'chunks;
+            }
+            let chunk: Value = match serde_json::from_str(data) {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    debug!("Skipping malformed streaming chunk '{}': {}", data, e);
+                    continue;
+                }
+            };
+            if let Some(content) = assembler.push(&chunk) {
+                let visible = think_filter.push(&content);
+                if !visible.is_empty() {
+                    on_token(visible);
+                }
+            }
+        }
+    }
+
+    assembler.into_choice()
+}
+
+/// Splits a response body into lines. Bytes are only decoded once their line is complete, so
+/// that a character split across two network chunks is decoded whole.
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Adds received bytes, returning the lines they complete.
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(line_end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=line_end).collect();
+            lines.push(String::from_utf8_lossy(&line).into_owned());
+        }
+        lines
+    }
+}
+
+/// Rebuilds a choice from the deltas of a streamed completion.
+#[derive(Default)]
+struct ChoiceAssembler {
+    content: String,
+    tool_calls: Vec<Value>,
+    finish_reason: Option<String>,
+    /// Whether the `[DONE]` marker ending the stream was received.
+    done: bool,
+}
+
+impl ChoiceAssembler {
+    /// Adds a streamed chunk, returning the text it carries, if any.
+    fn push(&mut self, chunk: &Value) -> Option<String> {
+        let choice = chunk.get("choices")?.get(0)?;
+        if let Some(finish_reason) = choice.get("finish_reason").and_then(Value::as_str) {
+            self.finish_reason = Some(finish_reason.to_string());
+        }
+        let delta = choice.get("delta")?;
+
+        for tool_call_delta in delta.get("tool_calls").and_then(Value::as_array).into_iter().flatten() {
+            let index = tool_call_delta.get("index").and_then(Value::as_u64).unwrap_or(0) as usize;
+            while self.tool_calls.len() <= index {
+                self.tool_calls.push(json!({
+                    "id": "",
+                    "type": "function",
+                    "function": { "name": "", "arguments": "" }
+                }));
+            }
+            let tool_call = &mut self.tool_calls[index];
+            if let Some(id) = tool_call_delta.get("id").and_then(Value::as_str) {
+                tool_call["id"] = Value::String(id.to_string());
+            }
+            for field in ["name", "arguments"] {
+                if let Some(part) = tool_call_delta.pointer(&format!("/function/{}", field)).and_then(Value::as_str) {
+                    let assembled = format!("{}{}", tool_call["function"][field].as_str().unwrap_or_default(), part);
+                    tool_call["function"][field] = Value::String(assembled);
+                }
+            }
+        }
+
+        let content = delta.get("content").and_then(Value::as_str)?;
+        self.content.push_str(content);
+        Some(content.to_string())
+    }
+
+    /// Builds the choice, failing when the stream was cut before the completion ended.
+    fn into_choice(self) -> anyhow::Result<Choice> {
+        if !self.done && self.finish_reason.is_none() {
+            anyhow::bail!("LLM stream ended before the completion was finished");
+        }
+        let content = ThinkFilter::strip(&self.content);
+        let choice = json!({
+            "index": 0,
+            "message": {
+                "role": "assistant",
+                "content": if content.is_empty() { Value::Null } else { Value::String(content) },
+                "tool_calls": if self.tool_calls.is_empty() { Value::Null } else { Value::Array(self.tool_calls) },
+            },
+            "finish_reason": self.finish_reason.unwrap_or_else(|| "stop".to_string()),
+        });
+        serde_json::from_value(choice).context("Failed to assemble the streamed LLM response")
+    }
+}
+
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
+
+/// Hides `<think>...</think>` sections from streamed text, even when tags are split across chunks.
+#[derive(Default)]
+struct ThinkFilter {
+    in_think: bool,
+    /// Text held back because it may be the start of a tag.
+    held: String,
+}
+
+impl ThinkFilter {
+    fn push(&mut self, text: &str) -> String {
+        self.held.push_str(text);
+        let mut visible = String::new();
+        loop {
+            let tag = if self.in_think { THINK_CLOSE } else { THINK_OPEN };
+            if let Some(position) = self.held.find(tag) {
+                if !self.in_think {
+                    visible.push_str(&self.held[..position]);
+                }
+                self.held.drain(..position + tag.len());
+                self.in_think = !self.in_think;
+                continue;
+            }
+            // Keep a possible partial tag at the end for the next chunk
+            let keep = (1..tag.len())
+                .rev()
+                .find(|length| self.held.ends_with(&tag[..*length]))
+                .unwrap_or(0);
+            let emitted: String = self.held.drain(..self.held.len() - keep).collect();
+            if !self.in_think {
+                visible.push_str(&emitted);
+            }
+            return visible;
+        }
+    }
+
+    fn strip(text: &str) -> String {
+        let mut filter = Self::default();
+        let mut visible = filter.push(text);
+        if !filter.in_think {
+            visible.push_str(&filter.held);
+        }
+        visible.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_think_sections_are_hidden_across_chunks() {
+        let mut filter = ThinkFilter::default();
+        let chunks = ["<th", "ink>plan", "ning</thi", "nk>The answer", " is 42"];
+        let visible: String = chunks.iter().map(|chunk| filter.push(chunk)).collect();
+        assert_eq!(visible, "The answer is 42");
+    }
+
+    #[test]
+    fn test_characters_split_across_chunks_are_decoded_whole() {
+        let mut lines = LineBuffer::default();
+        let bytes = "data: café\n".as_bytes();
+        let split = bytes.len() - 2;
+
+        assert!(lines.push(&bytes[..split]).is_empty());
+        assert_eq!(lines.push(&bytes[split..]), ["data: café\n"]);
+    }
+
+    #[test]
+    fn test_tool_call_deltas_are_assembled() {
+        let mut assembler = ChoiceAssembler::default();
+        for chunk in [
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "id": "call_1", "function": {"name": "get_weather", "arguments": "{\"city\""}}]}}]}),
+            json!({"choices": [{"delta": {"tool_calls": [{"index": 0, "function": {"arguments": ": \"Paris\"}"}}]}}]}),
+            json!({"choices": [{"delta": {}, "finish_reason": "tool_calls"}]}),
+        ] {
+            assert_eq!(assembler.push(&chunk), None);
+        }
+        assert_eq!(assembler.tool_calls[0]["function"]["arguments"], "{\"city\": \"Paris\"}");
+        assert_eq!(assembler.finish_reason.as_deref(), Some("tool_calls"));
+    }
+
+    #[test]
+    fn test_stream_cut_before_its_end_is_an_error() {
+        let mut assembler = ChoiceAssembler::default();
+        assembler.push(&json!({"choices": [{"delta": {"content": "The answer"}}]}));
+        assert!(assembler.into_choice().is_err());
+    }
+}
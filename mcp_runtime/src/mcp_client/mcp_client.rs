@@ -8,7 +8,10 @@ use rmcp::model::{
 };
 use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
 use rmcp::transport::StreamableHttpClientTransport;
+use rmcp::transport::TokioChildProcess;
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 use llm_api::chat::ToolCall;
@@ -48,6 +51,22 @@ pub fn create_transport(
     StreamableHttpClientTransport::with_client(client, config)
 }
 
+/// Spawns an MCP server speaking over its standard input and output.
+/// The child is killed when the transport is dropped.
+pub fn create_child_process_transport(
+    program: &str,
+    args: &[String],
+    env: &HashMap<String, String>,
+    cwd: Option<&Path>,
+) -> std::io::Result<TokioChildProcess> {
+    let mut command = tokio::process::Command::new(program);
+    command.args(args).envs(env).kill_on_drop(true);
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    TokioChildProcess::new(command)
+}
+
 
 /// Initializes the MCP client and connects to the server.
 /// Initializes logging (potentially repeated if called multiple times).
@@ -0,0 +1,640 @@
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use rmcp::model::{CallToolRequestParams, CallToolResult, ClientCapabilities, Implementation, InitializeRequestParams, JsonObject, Tool};
+use rmcp::service::ServiceError;
+
+use configuration::McpRuntimeConfig;
+use llm_api::chat::ToolCall;
+
+use crate::mcp_client::mcp_client::{
+    McpClient, create_child_process_transport, create_transport, execute_tool_call_v2, get_tools_list_v2,
+};
+
+/// Placed between the prefix of a server and the name of a tool whose name collides with a tool
+/// of another server. LLM APIs only accept letters, digits, `_` and `-` in tool names.
+pub const TOOL_PREFIX_SEPARATOR: &str = "__";
+
+/// How often the child processes of servers launched as commands are checked for having exited.
+pub const CHILD_WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Command line of an MCP server launched as a child process, speaking over its standard input and output.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct McpServerCommand {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Added to the environment inherited from the runtime.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Working directory of the child, the one of the runtime when not set.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+}
+
+/// How to reach an MCP server: either `url = "..."`, or `program = "..."` with optional `args`, `env` and `cwd`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum McpServerTransport {
+    /// Streamable HTTP.
+    Http { url: String },
+    /// Child process, restarted when it crashes.
+    Stdio(McpServerCommand),
+}
+
+impl fmt::Display for McpServerTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            McpServerTransport::Http { url } => write!(f, "{}", url),
+            McpServerTransport::Stdio(command) => write!(f, "{} {}", command.program, command.args.join(" ")),
+        }
+    }
+}
+
+/// An MCP server to connect to, with its own credentials.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct McpServerConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub transport: McpServerTransport,
+    /// Sent as bearer token to HTTP servers.
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Environment variable holding the API key, read when `api_key` is not set.
+    #[serde(default)]
+    pub api_key_env_var: Option<String>,
+    /// Prefix of the tools of this server whose name is also used by another server.
+    /// Defaults to the name of the server.
+    #[serde(default)]
+    pub tool_prefix: Option<String>,
+}
+
+impl McpServerConfig {
+    /// The single server of a runtime configuration.
+    pub fn from_runtime_config(agent_mcp_config: &McpRuntimeConfig) -> anyhow::Result<Self> {
+        let url = agent_mcp_config
+            .agent_mcp_server_url
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Missing MCP server URL in agent_mcp_config"))?;
+        Ok(Self {
+            name: "default".to_string(),
+            transport: McpServerTransport::Http { url },
+            api_key: agent_mcp_config.agent_mcp_server_api_key.clone(),
+            api_key_env_var: None,
+            tool_prefix: None,
+        })
+    }
+
+    /// The single server of the runtime config file at `path`: the child process of its
+    /// `[agent_mcp_server_command]` table when it has one, the server at `agent_mcp_server_url`
+    /// otherwise. `McpRuntimeConfig` has no command fields, so they are read from the file here.
+    pub fn from_runtime_config_file(path: &str, agent_mcp_config: &McpRuntimeConfig) -> anyhow::Result<Self> {
+        #[derive(Deserialize)]
+        struct ServerCommandSettings {
+            #[serde(default)]
+            agent_mcp_server_command: Option<McpServerCommand>,
+        }
+
+        let content = std::fs::read_to_string(path).context(format!("Failed to read MCP runtime config '{}'", path))?;
+        let settings: ServerCommandSettings =
+            toml::from_str(&content).context(format!("Failed to parse MCP runtime config '{}'", path))?;
+        match settings.agent_mcp_server_command {
+            Some(command) => Ok(Self {
+                name: "default".to_string(),
+                transport: McpServerTransport::Stdio(command),
+                api_key: None,
+                api_key_env_var: None,
+                tool_prefix: None,
+            }),
+            None => Self::from_runtime_config(agent_mcp_config),
+        }
+    }
+
+    pub fn tool_prefix(&self) -> &str {
+        self.tool_prefix.as_deref().unwrap_or(&self.name)
+    }
+
+    fn api_key(&self) -> anyhow::Result<Option<String>> {
+        match (&self.api_key, &self.api_key_env_var) {
+            (Some(api_key), _) => Ok(Some(api_key.clone())),
+            (None, Some(env_var_name)) => env::var(env_var_name)
+                .map(Some)
+                .context(format!("Environment variable '{}' for the API key of MCP server '{}' must be set", env_var_name, self.name)),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// List of MCP servers, as loaded from a TOML file of `[[mcp_servers]]` tables.
+#[derive(Debug, Clone, Deserialize)]
+pub struct McpServersConfig {
+    pub mcp_servers: Vec<McpServerConfig>,
+}
+
+impl McpServersConfig {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path).context(format!("Failed to read MCP servers config '{}'", path))?;
+        toml::from_str(&content).context(format!("Failed to parse MCP servers config '{}'", path))
+    }
+}
+
+/// Server owning a tool, and the name of the tool on that server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolRoute {
+    pub server: String,
+    pub tool_name: String,
+}
+
+/// Merges the tool lists of several servers into one namespace. Tools whose name is used by more
+/// than one server are renamed `<prefix>__<name>`, others keep their name. Collisions are found in
+/// `known_tools`, the last tool list of every server, so that a tool keeps its name while another
+/// server fails to answer.
+pub(crate) fn merge_tools(
+    server_tools: Vec<(&McpServerConfig, Vec<Tool>)>,
+    known_tools: &[Vec<Tool>],
+) -> (Vec<Tool>, HashMap<String, ToolRoute>) {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    for tools in known_tools {
+        for tool in tools {
+            *occurrences.entry(tool.name.to_string()).or_default() += 1;
+        }
+    }
+
+    let mut merged = Vec::new();
+    let mut routes = HashMap::new();
+    for (server, tools) in server_tools {
+        for mut tool in tools {
+            let tool_name = tool.name.to_string();
+            let exposed_name = if occurrences[&tool_name] > 1 {
+                format!("{}{}{}", server.tool_prefix(), TOOL_PREFIX_SEPARATOR, tool_name)
+            } else {
+                tool_name.clone()
+            };
+            tool.name = exposed_name.clone().into();
+            let route = ToolRoute {
+                server: server.name.clone(),
+                tool_name,
+            };
+            if let Some(shadowed) = routes.insert(exposed_name.clone(), route) {
+                warn!("⚠️ Tool '{}' of MCP server '{}' is hidden by another tool of the same name", exposed_name, shadowed.server);
+                merged.retain(|merged_tool: &Tool| merged_tool.name != exposed_name);
+            }
+            merged.push(tool);
+        }
+    }
+    (merged, routes)
+}
+
+/// Client of one server. The client of a child process is replaced when the child is restarted.
+struct ServerConnection {
+    config: McpServerConfig,
+    client: RwLock<Arc<McpClient>>,
+    /// Tools of the server at its last successful listing.
+    known_tools: RwLock<Vec<Tool>>,
+    /// Held while restarting, so that concurrent failures spawn a single new child.
+    restart_lock: tokio::sync::Mutex<()>,
+}
+
+impl ServerConnection {
+    fn client(&self) -> Arc<McpClient> {
+        self.client.read().unwrap().clone()
+    }
+
+    fn is_child_process(&self) -> bool {
+        matches!(self.config.transport, McpServerTransport::Stdio(_))
+    }
+
+    /// Replaces the client of a crashed child process, unless another caller already did.
+    async fn restart(&self, failed_client: &Arc<McpClient>) -> anyhow::Result<Arc<McpClient>> {
+        let _restarting = self.restart_lock.lock().await;
+        let current_client = self.client();
+        if !Arc::ptr_eq(&current_client, failed_client) {
+            return Ok(current_client);
+        }
+
+        let client = Arc::new(
+            McpServers::connect_server(&self.config)
+                .await
+                .context(format!("Failed to restart MCP server '{}'", self.config.name))?,
+        );
+        *self.client.write().unwrap() = client.clone();
+        info!("🔄 Restarted MCP server '{}'", self.config.name);
+        Ok(client)
+    }
+}
+
+/// Restarts the child process of a connection as soon as it exits, rather than when the next
+/// call fails. Stops once the connection is dropped.
+fn watch_child_process(connection: Weak<ServerConnection>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHILD_WATCH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(connection) = connection.upgrade() else {
+                return;
+            };
+            let client = connection.client();
+            if client.peer().is_transport_closed() {
+                warn!("⚠️ MCP server '{}' exited, restarting it", connection.config.name);
+                if let Err(e) = connection.restart(&client).await {
+                    warn!("⚠️ {:#}", e);
+                }
+            }
+        }
+    })
+}
+
+/// Whether an operation failed because the connection to the server closed. A timeout is not
+/// one: the server is only slow, and other calls on the same connection may still succeed.
+fn is_transport_closed(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<ServiceError>(), Some(ServiceError::TransportClosed))
+}
+
+/// Clients of several MCP servers, seen as a single server offering the tools of all of them.
+///
+/// Servers launched as child processes are supervised: a child that exits, or whose connection
+/// closes during a call, is spawned again. Listing tools is then retried once, while a failed
+/// tool call is not, as the tool may have run already.
+pub struct McpServers {
+    /// In configuration order.
+    connections: Vec<Arc<ServerConnection>>,
+    routes: RwLock<HashMap<String, ToolRoute>>,
+    /// One per child process.
+    watchers: Vec<JoinHandle<()>>,
+}
+
+impl McpServers {
+    /// Connects to every server. Servers that cannot be reached are left out, unless none can.
+    pub async fn connect(servers: Vec<McpServerConfig>) -> anyhow::Result<Self> {
+        if servers.is_empty() {
+            anyhow::bail!("No MCP server to connect to");
+        }
+        // Tool calls are routed by server name, and colliding tools renamed with the server prefix
+        let mut names = HashSet::new();
+        if let Some(duplicate) = servers.iter().find(|server| !names.insert(server.name.as_str())) {
+            anyhow::bail!("Several MCP servers are named '{}'", duplicate.name);
+        }
+        let mut prefixes = HashSet::new();
+        if let Some(duplicate) = servers.iter().find(|server| !prefixes.insert(server.tool_prefix())) {
+            anyhow::bail!("Several MCP servers use the tool prefix '{}'", duplicate.tool_prefix());
+        }
+
+        let server_count = servers.len();
+        let mut connections = Vec::new();
+        let mut last_error = None;
+        for server in servers {
+            match Self::connect_server(&server).await {
+                Ok(client) => {
+                    info!("Connected to MCP server '{}' at {}", server.name, server.transport);
+                    connections.push(Arc::new(ServerConnection {
+                        config: server,
+                        client: RwLock::new(Arc::new(client)),
+                        known_tools: RwLock::new(Vec::new()),
+                        restart_lock: tokio::sync::Mutex::new(()),
+                    }));
+                }
+                Err(e) => {
+                    warn!("⚠️ Could not connect to MCP server '{}' at {}: {}", server.name, server.transport, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if connections.is_empty() {
+            let error = last_error.unwrap_or_else(|| anyhow::anyhow!("No MCP server reachable"));
+            return Err(error.context(format!("Could not connect to any of the {} MCP server(s)", server_count)));
+        }
+        let watchers = connections
+            .iter()
+            .filter(|connection| connection.is_child_process())
+            .map(|connection| watch_child_process(Arc::downgrade(connection)))
+            .collect();
+        Ok(Self {
+            connections,
+            routes: RwLock::new(HashMap::new()),
+            watchers,
+        })
+    }
+
+    async fn connect_server(server: &McpServerConfig) -> anyhow::Result<McpClient> {
+        let client_info = InitializeRequestParams::new(
+            ClientCapabilities::default(),
+            Implementation::new("tool execution client", "0.0.1"),
+        );
+        match &server.transport {
+            McpServerTransport::Http { url } => {
+                let transport = create_transport(url.as_str(), server.api_key()?);
+                Ok(rmcp::serve_client(client_info, transport).await?)
+            }
+            McpServerTransport::Stdio(command) => {
+                let transport = create_child_process_transport(
+                    &command.program,
+                    &command.args,
+                    &command.env,
+                    command.cwd.as_deref(),
+                )
+                .context(format!("Failed to spawn MCP server '{}'", command.program))?;
+                Ok(rmcp::serve_client(client_info, transport).await?)
+            }
+        }
+    }
+
+    /// Runs `operation` with the client of `connection`. A child process whose connection closed
+    /// is restarted, and the operation retried once when `idempotent`. Other failures, timeouts
+    /// included, are returned as they are.
+    async fn with_restart<T, F, Fut>(&self, connection: &ServerConnection, idempotent: bool, operation: F) -> anyhow::Result<T>
+    where
+        F: Fn(Arc<McpClient>) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut client = connection.client();
+        // The child may have exited since the watcher last looked
+        if connection.is_child_process() && client.peer().is_transport_closed() {
+            client = connection.restart(&client).await?;
+        }
+        match operation(client.clone()).await {
+            Err(e) if connection.is_child_process() && (is_transport_closed(&e) || client.peer().is_transport_closed()) => {
+                warn!("⚠️ MCP server '{}' closed its connection, restarting it: {}", connection.config.name, e);
+                let client = connection.restart(&client).await?;
+                if idempotent {
+                    operation(client).await
+                } else {
+                    Err(e)
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Client of the first connected server.
+    pub fn primary_client(&self) -> Arc<McpClient> {
+        self.connections[0].client()
+    }
+
+    pub fn server_names(&self) -> Vec<String> {
+        self.connections.iter().map(|connection| connection.config.name.clone()).collect()
+    }
+
+    /// Lists the tools of every server under their merged names. A server failing to answer is
+    /// left out of the list until the next call.
+    pub async fn list_tools(&self) -> anyhow::Result<Vec<Tool>> {
+        let mut server_tools = Vec::new();
+        for connection in &self.connections {
+            match self.with_restart(connection, true, get_tools_list_v2).await {
+                Ok(tools) => {
+                    *connection.known_tools.write().unwrap() = tools.clone();
+                    server_tools.push((&connection.config, tools));
+                }
+                Err(e) => warn!("⚠️ Could not list the tools of MCP server '{}': {}", connection.config.name, e),
+            }
+        }
+        if server_tools.is_empty() {
+            anyhow::bail!("No MCP server returned its tools");
+        }
+
+        let known_tools: Vec<Vec<Tool>> = self
+            .connections
+            .iter()
+            .map(|connection| connection.known_tools.read().unwrap().clone())
+            .collect();
+        let (tools, routes) = merge_tools(server_tools, &known_tools);
+        *self.routes.write().unwrap() = routes;
+        Ok(tools)
+    }
+
+    /// Server owning the tool exposed as `name`, and its connection. Tools are listed again when
+    /// `name` is unknown, in case its server failed the previous listing.
+    async fn route(&self, name: &str) -> anyhow::Result<(ToolRoute, &Arc<ServerConnection>)> {
+        let known_route = self.routes.read().unwrap().get(name).cloned();
+        let route = match known_route {
+            Some(route) => route,
+            None => {
+                self.list_tools().await?;
+                self.routes
+                    .read()
+                    .unwrap()
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Unknown tool '{}'", name))?
+            }
+        };
+        let connection = self
+            .connections
+            .iter()
+            .find(|connection| connection.config.name == route.server)
+            .ok_or_else(|| anyhow::anyhow!("MCP server '{}' is not connected", route.server))?;
+        Ok((route, connection))
+    }
+
+    /// Runs a tool call of the LLM on the server owning the tool.
+    pub async fn call_tool(&self, tool_call: &ToolCall) -> anyhow::Result<CallToolResult> {
+        let (route, connection) = self.route(&tool_call.function.name).await?;
+        let mut routed_call = tool_call.clone();
+        routed_call.function.name = route.tool_name;
+        self.with_restart(connection, false, |client| execute_tool_call_v2(client, routed_call.clone()))
+            .await
+    }
+
+    /// Calls the tool exposed as `name` with already parsed arguments.
+    pub async fn call_tool_with_arguments(&self, name: &str, arguments: JsonObject) -> anyhow::Result<CallToolResult> {
+        let (route, connection) = self.route(name).await?;
+        self.with_restart(connection, false, |client| {
+            let request = CallToolRequestParams::new(route.tool_name.clone()).with_arguments(arguments.clone());
+            async move { Ok(client.call_tool(request).await?) }
+        })
+        .await
+    }
+
+    /// Closes the connection to every server, stopping the child processes.
+    pub async fn cancel(mut self) -> anyhow::Result<()> {
+        // Stopped first, so that no child is restarted while being cancelled
+        for watcher in self.watchers.drain(..) {
+            watcher.abort();
+            let _ = watcher.await;
+        }
+        for connection in std::mem::take(&mut self.connections) {
+            let name = connection.config.name.clone();
+            let client = match Arc::try_unwrap(connection) {
+                Ok(connection) => Arc::try_unwrap(connection.client.into_inner().unwrap()),
+                Err(connection) => Err(connection.client()),
+            };
+            match client {
+                Ok(client) => {
+                    client.cancel().await?;
+                }
+                Err(_) => warn!("Cannot cancel the client of MCP server '{}': still shared", name),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for McpServers {
+    fn drop(&mut self) {
+        for watcher in &self.watchers {
+            watcher.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(name: &str) -> McpServerConfig {
+        McpServerConfig {
+            name: name.to_string(),
+            transport: McpServerTransport::Http {
+                url: format!("http://{}/mcp", name),
+            },
+            api_key: None,
+            api_key_env_var: None,
+            tool_prefix: None,
+        }
+    }
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(name.to_string(), format!("Tool {}", name), Arc::new(JsonObject::new()))
+    }
+
+    #[test]
+    fn test_colliding_tool_names_are_prefixed() {
+        let weather = server("weather");
+        let customer = McpServerConfig {
+            tool_prefix: Some("crm".to_string()),
+            ..server("customer")
+        };
+
+        let weather_tools = vec![tool("get_weather"), tool("search")];
+        let customer_tools = vec![tool("get_customer_details"), tool("search")];
+        let (tools, routes) = merge_tools(
+            vec![(&weather, weather_tools.clone()), (&customer, customer_tools.clone())],
+            &[weather_tools.clone(), customer_tools.clone()],
+        );
+
+        let names: Vec<String> = tools.iter().map(|tool| tool.name.to_string()).collect();
+        assert_eq!(names, ["get_weather", "weather__search", "get_customer_details", "crm__search"]);
+        assert_eq!(routes["crm__search"], ToolRoute {
+            server: "customer".to_string(),
+            tool_name: "search".to_string(),
+        });
+        assert_eq!(routes["get_weather"].server, "weather");
+
+        // A server failing to answer does not rename the tools of the others
+        let (tools, _) = merge_tools(vec![(&weather, weather_tools.clone())], &[weather_tools, customer_tools]);
+        let names: Vec<String> = tools.iter().map(|tool| tool.name.to_string()).collect();
+        assert_eq!(names, ["get_weather", "weather__search"]);
+    }
+
+    #[test]
+    fn test_server_config_accepts_a_url_or_a_command_line() {
+        let config: McpServersConfig = toml::from_str(
+            r#"
+            [[mcp_servers]]
+            name = "weather"
+            url = "http://localhost:8000/mcp"
+
+            [[mcp_servers]]
+            name = "files"
+            program = "npx"
+            args = ["-y", "@modelcontextprotocol/server-filesystem", "."]
+            env = { NODE_ENV = "production" }
+            cwd = "/tmp"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.mcp_servers[0].transport, McpServerTransport::Http {
+            url: "http://localhost:8000/mcp".to_string(),
+        });
+        let McpServerTransport::Stdio(command) = &config.mcp_servers[1].transport else {
+            panic!("Expected a command line");
+        };
+        assert_eq!(command.program, "npx");
+        assert_eq!(command.args.len(), 3);
+        assert_eq!(command.env["NODE_ENV"], "production");
+        assert_eq!(command.cwd, Some(PathBuf::from("/tmp")));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_program_fails_to_connect() {
+        let result = McpServers::connect(vec![McpServerConfig {
+            transport: McpServerTransport::Stdio(McpServerCommand {
+                program: "swarm-no-such-mcp-server".to_string(),
+                args: Vec::new(),
+                env: HashMap::new(),
+                cwd: None,
+            }),
+            ..server("missing")
+        }])
+        .await;
+
+        let error = result.err().expect("Spawning a missing program must fail");
+        assert!(format!("{:#}", error).contains("Failed to spawn MCP server"));
+    }
+
+    #[tokio::test]
+    async fn test_runtime_config_file_may_hold_a_command_line() {
+        let mock_server = test_support::MockMcpServer::new().start().await.unwrap();
+        let agent_mcp_config = mock_server.runtime_config();
+        let path = std::env::temp_dir().join(format!("mcp-runtime-config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "agent_mcp_model_id = \"model\"\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let server = McpServerConfig::from_runtime_config_file(path, &agent_mcp_config).unwrap();
+        assert_eq!(server.transport, McpServerTransport::Http {
+            url: agent_mcp_config.agent_mcp_server_url.clone().unwrap(),
+        });
+
+        std::fs::write(path, "agent_mcp_model_id = \"model\"\n\n[agent_mcp_server_command]\nprogram = \"npx\"\nargs = [\"-y\", \"server\"]\n")
+            .unwrap();
+        let server = McpServerConfig::from_runtime_config_file(path, &agent_mcp_config).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let McpServerTransport::Stdio(command) = server.transport else {
+            panic!("Expected a command line");
+        };
+        assert_eq!(command.program, "npx");
+        assert_eq!(command.args, ["-y", "server"]);
+    }
+
+    #[test]
+    fn test_only_a_closed_transport_restarts_the_server() {
+        let closed = anyhow::Error::from(ServiceError::TransportClosed);
+        let timeout = anyhow::Error::from(ServiceError::Timeout {
+            timeout: Duration::from_secs(30),
+        });
+
+        assert!(is_transport_closed(&closed));
+        // A slow call must not kill the child and the other calls running on it
+        assert!(!is_transport_closed(&timeout));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_tool_prefixes_are_rejected() {
+        let customer = McpServerConfig {
+            tool_prefix: Some("weather".to_string()),
+            ..server("customer")
+        };
+        let result = McpServers::connect(vec![server("weather"), customer]).await;
+
+        let error = result.err().expect("Duplicate tool prefixes must be rejected");
+        assert!(error.to_string().contains("Several MCP servers use the tool prefix 'weather'"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_server_names_are_rejected() {
+        let result = McpServers::connect(vec![server("weather"), server("weather")]).await;
+
+        let error = result.err().expect("Duplicate server names must be rejected");
+        assert!(error.to_string().contains("Several MCP servers are named 'weather'"));
+    }
+}
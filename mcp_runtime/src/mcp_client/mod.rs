@@ -1 +1,2 @@
 pub mod mcp_client;
+pub mod mcp_servers;
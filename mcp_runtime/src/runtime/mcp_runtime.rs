@@ -5,19 +5,17 @@ use std::sync::{Arc, RwLock};
 use rmcp::RoleClient;
 use rmcp::model::InitializeRequestParams;
 use rmcp::service::RunningService;
-use rmcp::model::{
-    CallToolRequestParams, CallToolResult, ClientCapabilities, Implementation, ListToolsResult, Tool,
-};
+use rmcp::model::{CallToolResult, JsonObject, Tool};
 
 use llm_api::chat::ToolCall;
 use configuration::McpRuntimeConfig;
-use crate::mcp_client::mcp_client::create_transport;
+use crate::mcp_client::mcp_servers::{McpServerConfig, McpServers};
 
 pub type McpClient = RunningService<RoleClient, InitializeRequestParams>;
 
 pub struct McpRuntime {
     agent_mcp_config: McpRuntimeConfig,
-    client: McpClient,
+    servers: McpServers,
     tool_cache: Arc<RwLock<HashMap<String, Vec<Tool>>>>,
 }
 
@@ -25,32 +23,25 @@ impl McpRuntime {
     /// Initializes the MCP client and connects to the server.
     pub async fn initialize_mcp_client_v2(agent_mcp_config: McpRuntimeConfig)
         -> anyhow::Result<Self> {
-        
-        let mcp_server_url_string = agent_mcp_config
-            .agent_mcp_server_url.clone()
-            .ok_or_else(|| anyhow::anyhow!("Missing MCP server URL in agent_mcp_config"))?;
-        let mcp_server_url = mcp_server_url_string.as_str();
-
-        let api_key = agent_mcp_config.agent_mcp_server_api_key.clone();
-        
-        let transport = create_transport(mcp_server_url, api_key);
-
-        let client_info = InitializeRequestParams::new(
-            ClientCapabilities::default(),
-            Implementation::new("tool execution client", "0.0.1"),
-        );
+        let server = McpServerConfig::from_runtime_config(&agent_mcp_config)?;
+        Self::connect_servers(agent_mcp_config, vec![server]).await
+    }
 
-        let client = rmcp::serve_client(client_info, transport).await?;
+    /// Connects to several MCP servers, whose tools are merged as described in `McpServers`.
+    pub async fn connect_servers(agent_mcp_config: McpRuntimeConfig, servers: Vec<McpServerConfig>)
+        -> anyhow::Result<Self> {
+        let servers = McpServers::connect(servers).await?;
 
         Ok(Self {
             agent_mcp_config,
-            client,
+            servers,
             tool_cache: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
-    pub fn get_client(&self) -> anyhow::Result<&McpClient> {
-        Ok(&self.client)
+    /// Client of the first MCP server. Use `call_tool` to reach the tools of every server.
+    pub fn get_client(&self) -> anyhow::Result<Arc<McpClient>> {
+        Ok(self.servers.primary_client())
     }
 
     pub fn get_config(&self) -> &McpRuntimeConfig {
@@ -67,10 +58,15 @@ impl McpRuntime {
             }
         }
 
-        let list_tools: ListToolsResult = self.client.list_tools(Default::default()).await?;
+        let tools = self.servers.list_tools().await?;
         let mut cache = self.tool_cache.write().unwrap();
-        cache.insert("".to_string(), list_tools.tools.clone());
-        Ok(list_tools.tools)
+        cache.insert("".to_string(), tools.clone());
+        Ok(tools)
+    }
+
+    /// Calls a tool, by its name in `get_tools_list_v2`, on the server owning it.
+    pub async fn call_tool(&self, name: &str, arguments: JsonObject) -> anyhow::Result<CallToolResult> {
+        self.servers.call_tool_with_arguments(name, arguments).await
     }
 
     pub fn invalidate_tool_cache(&self, session_key: &str) {
@@ -83,31 +79,6 @@ impl McpRuntime {
         &self,
         tool_call: ToolCall,
     ) -> anyhow::Result<CallToolResult> {
-        let args: Result<serde_json::Value, _> = serde_json::from_str(&tool_call.function.arguments);
-
-        let tool_result = match args {
-            Ok(parsed_args) => {
-                self.client
-                    .call_tool(CallToolRequestParams::new(tool_call.function.name.clone())
-                        .with_arguments(parsed_args.as_object().cloned().unwrap_or_default()))
-                    .await?
-            }
-            Err(e) => {
-                tracing::error!(
-                    "Failed to parse arguments for {}: {}",
-                    tool_call.function.name,
-                    e
-                );
-                CallToolResult::error(vec![
-                    rmcp::model::Content::text(format!(
-                        "Failed to parse tool arguments: {}", e
-                    ))
-                ])
-            }
-        };
-
-        tracing::info!("Tool result: {tool_result:#?}");
-
-        Ok(tool_result)
+        self.servers.call_tool(&tool_call).await
     }
 }
\ No newline at end of file
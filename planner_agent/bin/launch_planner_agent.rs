@@ -1,15 +1,17 @@
 use resource_invoker::McpRuntimeToolInvoker as McpRuntimeTools;
+use resource_invoker::{A2AAgentInvoker, AgentHealthMonitor, DEFAULT_HEALTH_PROBE_INTERVAL};
 use std::env;
 
 use clap::Parser;
 use std::sync::Arc;
-use tracing::{ info};
+use tracing::{ info, warn};
 
 use configuration::{setup_logging, AgentConfig};
 
 use serde_json::json;
 
 use planner_agent::business_logic::planner_agent::PlannerAgent;
+use workflow_management::graph::Capabilities;
 
 // Registration via discovery service
 use agent_models::registry::registry_models::{TaskDefinition,AgentDefinition,ToolDefinition};
@@ -33,6 +35,9 @@ struct Args {
     /// MCP Config
     #[clap(long, default_value = "./configuration/mcp_runtime_config.toml")]
     mcp_config_path: String,
+    /// Optional list of MCP servers (TOML format), used instead of the server of the MCP config
+    #[clap(long)]
+    mcp_servers_config_file: Option<String>,
     #[clap(long, default_value = "http://127.0.0.1:4000")]
     discovery_service_url: String,
     #[clap(long, default_value = "http://127.0.0.1:5000")]
@@ -60,13 +65,28 @@ async fn setup_discovery_service(discovery_service_url: String) -> Option<Arc<dy
     Some(Arc::new(adapter))
 }
 
+/// Probes the discovered agents in the background, so that plans avoid those that do not answer.
+/// A standalone planner delegates nothing itself: only the probes feed the health of the agents.
+async fn setup_agent_health(discovery_service: Arc<dyn DiscoveryService>) -> Option<AgentHealthMonitor> {
+    match A2AAgentInvoker::new_with_discovery(None, None, discovery_service).await {
+        Ok(a2a_agent_invoker) => {
+            a2a_agent_invoker.start_health_probes(DEFAULT_HEALTH_PROBE_INTERVAL);
+            Some(a2a_agent_invoker.health_monitor())
+        }
+        Err(e) => {
+            warn!("Agents could not be discovered, plans will not take their health into account: {}", e);
+            None
+        }
+    }
+}
+
 /********************************************************************/
 // Registration via discovery Service of the resources 
 // that we will make available
 /********************************************************************/
 
 /// Register Tasks in Discovery Service
-async fn register_tasks(discovery_service: Arc<dyn DiscoveryService>) -> anyhow::Result<()> {
+async fn register_tasks(discovery_service: Arc<dyn DiscoveryService>) -> anyhow::Result<Vec<String>> {
 
     let task_definition=TaskDefinition {
         id: "greeting".to_string(),
@@ -76,7 +96,7 @@ async fn register_tasks(discovery_service: Arc<dyn DiscoveryService>) -> anyhow:
         output_schema: json!({}),
     };
     discovery_service.register_task(&task_definition).await?;
-    Ok(())
+    Ok(vec![task_definition.id])
 }
 
 /// Register Agents in Discovery Service
@@ -95,12 +115,13 @@ async fn register_agents(discovery_service: Arc<dyn DiscoveryService>) -> anyhow
 }
 
 /// Register Agents in Discovery Service
-async fn register_tools(mcp_config_path: String,discovery_service: Arc<dyn DiscoveryService>) -> anyhow::Result<()> {
+async fn register_tools(mcp_config_path: String, mcp_servers_config_file: Option<&str>, discovery_service: Arc<dyn DiscoveryService>) -> anyhow::Result<Vec<String>> {
 
-    let mcp_tools = McpRuntimeTools::new(mcp_config_path).await?;
+    let mcp_tools = McpRuntimeTools::from_config_files(mcp_config_path, mcp_servers_config_file).await?;
     let mcp_tools = Arc::new(mcp_tools);
 
     // Register tools
+        let mut tool_ids = Vec::new();
         let list_tools= mcp_tools.get_tools_list_v2().await?;
         for tool in list_tools {
             let tool_definition=ToolDefinition {
@@ -111,9 +132,10 @@ async fn register_tools(mcp_config_path: String,discovery_service: Arc<dyn Disco
                 output_schema: json!({}),
             };    
             discovery_service.register_tool(&tool_definition).await?;
+            tool_ids.push(tool_definition.id);
         }
   
-    Ok(())
+    Ok(tool_ids)
 }
 
 
@@ -147,14 +169,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>>{
     /************************************************/
     /* Set Up Registrations via discovery service           */
     /************************************************/ 
-    register_tasks(discovery_service.clone().unwrap()).await?;
+    let task_ids = register_tasks(discovery_service.clone().unwrap()).await?;
     register_agents(discovery_service.clone().unwrap()).await?;
-    register_tools(args.mcp_config_path.clone(),discovery_service.clone().unwrap()).await?;
+    let tool_ids = register_tools(args.mcp_config_path.clone(), args.mcp_servers_config_file.as_deref(), discovery_service.clone().unwrap()).await?;
 
     /************************************************/
     /* Launch Workflow Agent                        */
     /************************************************/ 
-    let agent = PlannerAgent::new(planner_agent_config.clone(),agent_api_key,None, evaluation_service, memory_service, discovery_service.clone(), None).await?;
+    let mut agent = PlannerAgent::new(planner_agent_config.clone(),agent_api_key,None, evaluation_service, memory_service, discovery_service.clone(), None).await?
+        // Generated plans may only use the tools and tasks registered above
+        .with_plan_resources(Capabilities::default().with_tools(tool_ids).with_tasks(task_ids));
+    if let Some(agent_health) = setup_agent_health(discovery_service.clone().unwrap()).await {
+        agent = agent.with_agent_health(agent_health);
+    }
     
     /************************************************/
     /* Launch Workflow Agent Server                 */
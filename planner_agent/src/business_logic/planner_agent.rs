@@ -3,7 +3,8 @@ use async_trait::async_trait;
 use tracing::{info, debug, error, warn};
 
 use anyhow::{Context, bail, Result};
-use serde_json::{Map, Value};
+use serde::Serialize;
+use serde_json::{Map, Value, json};
 use llm_api::chat::ChatLlmInteraction;
 
 use configuration::{AgentConfig};
@@ -16,19 +17,41 @@ use agent_models::execution::execution_result::ExecutionResult;
 use a2a_rs::{HttpClient, domain::{Message, Part, Role, TaskState}};
 use uuid::Uuid;
 use a2a_rs::services::AsyncA2AClient;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use workflow_management::graph::config::load_graph_from_file;
+use workflow_management::graph::config::load_workflow_from_file;
+use workflow_management::graph::execution_policy::ExecutionPolicies;
+use workflow_management::graph::{validate_plan, Capabilities};
+use resource_invoker::AgentHealthMonitor;
 use agent_models::evaluation::evaluation_models::{AgentEvaluationLogData};
 
 const DEFAULT_WORKFLOW_PROMPT_TEMPLATE: &str = include_str!("../../../configuration/prompts/detailed_workflow_agent_prompt.txt");
 const DEFAULT_HIGH_LEVEL_PLAN_PROMPT_TEMPLATE: &str = include_str!("../../../configuration/prompts/high_level_plan_workflow_agent_prompt.txt");
+const WORKFLOW_REPAIR_PROMPT_TEMPLATE: &str = include_str!("../../../configuration/prompts/workflow_repair_prompt.txt");
 const A2A_TIMEOUT_SECONDS: u32 = 50;
 const MAX_RETRIES: u8 = 3;
 const TRIGGER_RETRY: u8 = 3;
+/// Number of plans the LLM may generate for a request, repairs included, unless `max_plan_attempts` is set in the request metadata.
+const DEFAULT_MAX_PLAN_ATTEMPTS: usize = 3;
 
 use agent_models::agent_request::AgentRequest;
 
+/// One plan generated by the LLM while creating a workflow, kept in the execution result.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanAttempt {
+    pub attempt: usize,
+    pub accepted: bool,
+    /// Why the plan was rejected: parse error, validation problems or executor rejection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<String>,
+}
+
+/// A rejected plan, sent back to the LLM to be repaired.
+struct PlanFeedback {
+    plan_json: String,
+    problems: String,
+}
+
 #[derive(Clone)]
 pub struct PlannerAgent {
     agent_config: Arc<AgentConfig>,
@@ -36,6 +59,8 @@ pub struct PlannerAgent {
     discovery_service: Arc<dyn DiscoveryService>,
     evaluation_service: Option<Arc<dyn EvaluationService>>,
     client: Arc<HttpClient>,
+    agent_health: Option<AgentHealthMonitor>,
+    plan_resources: Capabilities,
 }
 
 #[async_trait]
@@ -67,6 +92,8 @@ impl Agent for PlannerAgent {
             discovery_service,
             evaluation_service,
             client: Arc::new(HttpClient::new(executor_url)),
+            agent_health: None,
+            plan_resources: Capabilities::default(),
         })
     }
 
@@ -99,6 +126,11 @@ impl Agent for PlannerAgent {
                 })
             },
             PlanningStrategy::Dynamic => {
+                let max_plan_attempts = request.metadata.as_ref()
+                    .and_then(|m| m.get("max_plan_attempts"))
+                    .and_then(Value::as_u64)
+                    .map(|v| (v as usize).max(1))
+                    .unwrap_or(DEFAULT_MAX_PLAN_ATTEMPTS);
                 let mut retry_count = 0;
                 loop {
                     match self.execute_dynamic_plan_loop_iteration(
@@ -107,6 +139,7 @@ impl Agent for PlannerAgent {
                         &request_id,
                         &conversation_id,
                         &mut retry_count,
+                        max_plan_attempts,
                     ).await? {
                         Some(result) => return Ok(result),
                         None => { /* continue loop for retry */ },
@@ -118,6 +151,20 @@ impl Agent for PlannerAgent {
 }
 
 impl PlannerAgent {
+    /// Leaves the agents reported unhealthy by `agent_health` out of the capabilities given to the LLM
+    /// and of those generated plans are validated against. Without it, every discovered agent is offered.
+    pub fn with_agent_health(mut self, agent_health: AgentHealthMonitor) -> Self {
+        self.agent_health = Some(agent_health);
+        self
+    }
+
+    /// Tools, tasks and saved workflows generated plans are checked against, e.g. those registered
+    /// in the discovery service. Agents always come from the discovery service.
+    pub fn with_plan_resources(mut self, plan_resources: Capabilities) -> Self {
+        self.plan_resources = plan_resources;
+        self
+    }
+
     async fn execute_from_file(
         &self,
         file_path: &str,
@@ -126,10 +173,10 @@ impl PlannerAgent {
         conversation_id: &str,
     ) -> anyhow::Result<ExecutionResult> {
         info!("PlannerAgent: Loading workflow from file: {}", file_path);
-        let graph = load_graph_from_file(file_path)
+        let (graph, execution_policies) = load_workflow_from_file(file_path)
             .with_context(|| format!("Failed to load workflow from file: {}", file_path))?;
         
-        let execution_result = self.internal_execute_plan(graph, original_user_query, request_id, conversation_id).await;
+        let execution_result = self.internal_execute_plan(graph, &execution_policies, original_user_query, request_id, conversation_id).await;
 
         let agent_output_string = match &execution_result {
             Ok(result) => {
@@ -182,11 +229,51 @@ impl PlannerAgent {
         request_id: &str,
         conversation_id: &str,
         retry_count: &mut u8,
+        max_plan_attempts: usize,
     ) -> anyhow::Result<Option<ExecutionResult>> {
         info!("PlannerAgent: No workflow file specified in metadata, creating workflow dynamically.");
-        let graph = self.create_plan(user_query).await?;
-        
-        let execution_result = self.internal_execute_plan(graph, original_user_query, request_id, conversation_id).await;
+        let mut plan_attempts = Vec::new();
+        let mut feedback = None;
+
+        // A plan rejected by the executor as structurally defective is repaired like an invalid one.
+        let mut execution_result = loop {
+            let (graph, execution_policies, plan_json) = match self
+                .create_plan_with_repair(user_query, max_plan_attempts, feedback.take(), &mut plan_attempts)
+                .await
+            {
+                Ok(plan) => plan,
+                Err(e) => {
+                    // The rejected attempts tell the caller why no plan could be run
+                    warn!("No workflow plan to execute: {}", e);
+                    return Ok(Some(ExecutionResult {
+                        request_id: request_id.to_string(),
+                        conversation_id: conversation_id.to_string(),
+                        success: false,
+                        output: json!({ "error": format!("Dynamic plan creation failed: {}", e), "plan_attempts": plan_attempts }),
+                    }));
+                }
+            };
+
+            let execution_result = self.internal_execute_plan(graph, &execution_policies, original_user_query, request_id, conversation_id).await;
+
+            let rejection = execution_result.as_ref().ok().and_then(Self::plan_rejection);
+            if let (Some(problems), Some(last_attempt)) = (&rejection, plan_attempts.last_mut()) {
+                last_attempt.accepted = false;
+                last_attempt.rejection = Some(problems.clone());
+            }
+
+            match rejection {
+                Some(problems) if plan_attempts.len() < max_plan_attempts => {
+                    warn!("Executor rejected the plan: {}. Asking the LLM for a corrected plan.", problems);
+                    feedback = Some(PlanFeedback { plan_json, problems });
+                }
+                _ => break execution_result,
+            }
+        };
+
+        if let Ok(ExecutionResult { output: Value::Object(output), .. }) = &mut execution_result {
+            output.insert("plan_attempts".to_string(), json!(plan_attempts));
+        }
 
         let agent_output_string = match &execution_result {
             Ok(result) => {
@@ -240,18 +327,28 @@ impl PlannerAgent {
         }
     }
 
-    async fn internal_execute_plan(&self, graph: Graph, original_user_query: &str, request_id: &str, conversation_id: &str) -> Result<ExecutionResult> {
+    async fn internal_execute_plan(&self, graph: Graph, execution_policies: &ExecutionPolicies, original_user_query: &str, request_id: &str, conversation_id: &str) -> Result<ExecutionResult> {
         let graph_json = serde_json::to_string(&graph)
             .context("Failed to serialize graph to JSON")?;
         
         let task_id = format!("task-{}", Uuid::new_v4());
         let message_id = Uuid::new_v4().to_string();
 
+        let mut metadata = self.extract_metadata_for_executor(original_user_query, request_id, conversation_id);
+        // Lets the executor attach its progress updates to this task.
+        metadata.insert("task_id".to_string(), Value::String(task_id.clone()));
+        if !execution_policies.is_empty() {
+            metadata.insert(
+                "execution_policies".to_string(),
+                serde_json::to_value(execution_policies).context("Failed to serialize execution policies")?,
+            );
+        }
+
         let a2a_message = Message::builder()
             .role(Role::User)
             .parts(vec![Part::Text {
                 text: graph_json,
-                metadata: Some(self.extract_metadata_for_executor(original_user_query, request_id, conversation_id)),
+                metadata: Some(metadata),
             }])
             .message_id(message_id)
             .build();
@@ -350,28 +447,122 @@ impl PlannerAgent {
         }
     }
 
-    pub async fn create_plan(&self, user_query: &str) -> Result<Graph> {
-        let capabilities = self.discovery_service.list_available_resources().await?;
+    pub async fn create_plan(&self, user_query: &str) -> Result<(Graph, ExecutionPolicies)> {
+        let (graph, execution_policies, _) = self
+            .create_plan_with_repair(user_query, DEFAULT_MAX_PLAN_ATTEMPTS, None, &mut Vec::new())
+            .await?;
+        Ok((graph, execution_policies))
+    }
+
+    /// Asks the LLM for a plan until one parses and validates, sending the problems of each
+    /// rejected plan back for repair. Stops once `attempts` holds `max_attempts` entries.
+    async fn create_plan_with_repair(
+        &self,
+        user_query: &str,
+        max_attempts: usize,
+        mut feedback: Option<PlanFeedback>,
+        attempts: &mut Vec<PlanAttempt>,
+    ) -> Result<(Graph, ExecutionPolicies, String)> {
+        let unhealthy_agents = self.unhealthy_agents().await;
+        let mut capabilities = self.discovery_service.list_available_resources().await?;
+        if !unhealthy_agents.is_empty() {
+            let mut unhealthy_agents: Vec<&String> = unhealthy_agents.iter().collect();
+            unhealthy_agents.sort();
+            capabilities.push_str(&format!(
+                "\n\nThe following agents are currently unavailable and must not be used: {}",
+                unhealthy_agents.into_iter().map(String::as_str).collect::<Vec<_>>().join(", ")
+            ));
+        }
         debug!("Capabilities for plan creation: \n {}", capabilities);
 
+        let plan_capabilities = self.get_plan_capabilities(&unhealthy_agents).await?;
+
         let prompt_template = DEFAULT_WORKFLOW_PROMPT_TEMPLATE;
 
         let prompt = prompt_template
             .replacen("{}", user_query, 1)
             .replacen("{}", &capabilities, 1);
 
-        debug!("Prompt for Plan creation : {}", prompt);
+        while attempts.len() < max_attempts {
+            let attempt = attempts.len() + 1;
+            let prompt = match &feedback {
+                Some(feedback) => {
+                    let repair = WORKFLOW_REPAIR_PROMPT_TEMPLATE
+                        .replacen("{}", &feedback.problems, 1)
+                        .replacen("{}", &feedback.plan_json, 1);
+                    format!("{}\n\n{}", prompt, repair)
+                }
+                None => prompt.clone(),
+            };
 
-        let response_content = self.llm_interaction.call_api_simple_v2("user".to_string(), prompt.to_string()).await?
-            .context("LLM returned no content")?;
-        info!("LLM responded with plan content: {:?}", response_content);
+            debug!("Prompt for Plan creation (attempt {}): {}", attempt, prompt);
+
+            let response_content = self.llm_interaction.call_api_simple_v2("user".to_string(), prompt).await?
+                .context("LLM returned no content")?;
+            info!("LLM responded with plan content: {:?}", response_content);
+
+            let json_string = self.extract_json_from_response(&response_content)?;
+            debug!("WorkFlow Generated: {}", json_string);
 
-        let json_string = self.extract_json_from_response(&response_content)?;
-        debug!("WorkFlow Generated: {}", json_string);
+            match Self::parse_plan(&json_string, &plan_capabilities) {
+                Ok((graph, execution_policies)) => {
+                    attempts.push(PlanAttempt { attempt, accepted: true, rejection: None });
+                    return Ok((graph, execution_policies, json_string));
+                }
+                Err(problems) => {
+                    warn!("Plan attempt {}/{} rejected: {}", attempt, max_attempts, problems);
+                    attempts.push(PlanAttempt { attempt, accepted: false, rejection: Some(problems.clone()) });
+                    feedback = Some(PlanFeedback { plan_json: json_string, problems });
+                }
+            }
+        }
 
-        let workflow: WorkflowPlanInput = serde_json::from_str(&json_string)
-            .context("Failed to deserialize workflow plan from LLM response")?;
-        Ok(workflow.into())
+        let last_problems = feedback.map(|feedback| feedback.problems).unwrap_or_default();
+        bail!("No valid workflow plan after {} attempt(s). Last problems: {}", attempts.len(), last_problems)
+    }
+
+    /// Parses and validates a generated plan, describing what is wrong with it on failure.
+    fn parse_plan(json_string: &str, capabilities: &Capabilities) -> std::result::Result<(Graph, ExecutionPolicies), String> {
+        let workflow: WorkflowPlanInput = serde_json::from_str(json_string)
+            .map_err(|e| format!("The workflow is not valid JSON for the expected format: {}", e))?;
+        let document: Value = serde_json::from_str(json_string)
+            .map_err(|e| format!("The workflow is not valid JSON: {}", e))?;
+        let execution_policies = ExecutionPolicies::from_workflow_json(&document)
+            .map_err(|e| format!("The execution policies of the workflow are invalid: {}", e))?;
+
+        let graph: Graph = workflow.into();
+        validate_plan(&graph, &execution_policies, capabilities).map_err(|e| e.to_string())?;
+
+        Ok((graph, execution_policies))
+    }
+
+    /// Reason given by the executor when it refused a plan as structurally defective.
+    fn plan_rejection(result: &ExecutionResult) -> Option<String> {
+        if !result.output.get("plan_rejected").and_then(Value::as_bool).unwrap_or(false) {
+            return None;
+        }
+        let reason = result.output.get("error").and_then(Value::as_str).unwrap_or("plan rejected by the executor");
+        Some(reason.to_string())
+    }
+
+    /// Resources a generated plan is checked against, leaving out `unhealthy_agents`.
+    /// Tools, tasks and workflows are only checked when given through `with_plan_resources`.
+    async fn get_plan_capabilities(&self, unhealthy_agents: &HashSet<String>) -> Result<Capabilities> {
+        let discovered_agents = self.discovery_service.discover_agents().await?;
+        Ok(self.plan_resources.clone().with_agents(
+            discovered_agents
+                .into_iter()
+                .map(|agent| agent.id)
+                .filter(|agent_id| !unhealthy_agents.contains(agent_id)),
+        ))
+    }
+
+    /// Agents reported unhealthy by the agent health monitor, if any.
+    async fn unhealthy_agents(&self) -> HashSet<String> {
+        match &self.agent_health {
+            Some(agent_health) => agent_health.unhealthy_agents().await,
+            None => HashSet::new(),
+        }
     }
 
     pub async fn create_high_level_plan(&self, user_query: &str) -> Result<String> {
@@ -396,8 +587,10 @@ impl PlannerAgent {
     //todo: to remove
     async fn get_available_capabilities(&self) -> Result<String> {
         let discovered_agents = self.discovery_service.discover_agents().await?;
+        let unhealthy_agents = self.unhealthy_agents().await;
         
         let agent_details: Vec<String> = discovered_agents.into_iter()
+            .filter(|agent| !unhealthy_agents.contains(&agent.id))
             .map(|agent| format!("- Agent: '{}', Purpose: '{}'", agent.name, agent.description))
             .collect();
 
@@ -464,4 +657,58 @@ enum PlanningStrategy {
     FromFile(String),
     HighLevel,
     Dynamic,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_service_adapters::AgentDiscoveryServiceAdapter;
+    use test_support::MockA2aAgent;
+
+    #[tokio::test]
+    async fn test_plan_from_file_is_run_by_the_executor() {
+        let executor = MockA2aAgent::new("executor_agent")
+            .answer(json!({"text": "Welcome message sent"}))
+            .start()
+            .await
+            .unwrap();
+        let agent_config = AgentConfig::builder()
+            .agent_name("planner_agent".to_string())
+            .agent_id("planner_agent".to_string())
+            .agent_description("Planner under test".to_string())
+            .agent_model_id("mock-model".to_string())
+            .agent_llm_url("http://127.0.0.1:9/v1/chat/completions".to_string())
+            .agent_http_endpoint("http://127.0.0.1:9".to_string())
+            .agent_ws_endpoint("ws://127.0.0.1:9".to_string())
+            .agent_system_prompt("You are a planner.".to_string())
+            .agent_discoverable(false)
+            .agent_executor_url(executor.url())
+            .agent_skill_id("planning".to_string())
+            .agent_skill_name("planning".to_string())
+            .agent_skill_description("Plans workflows".to_string())
+            .agent_version("1.0.0".to_string())
+            .agent_doc_url("/docs".to_string())
+            .agent_tags(vec![])
+            .agent_examples(vec![])
+            .build()
+            .unwrap();
+        // Plans read from a file need neither the LLM nor the discovery service
+        let discovery_service: Arc<dyn DiscoveryService> = Arc::new(AgentDiscoveryServiceAdapter::new("http://127.0.0.1:9"));
+        let planner = PlannerAgent::new(agent_config, "key".to_string(), None, None, None, Some(discovery_service), None)
+            .await
+            .unwrap();
+
+        let workflow = concat!(env!("CARGO_MANIFEST_DIR"), "/../workflow_management/example_workflow/auto_generated.json");
+        let result = planner
+            .execute_from_file(workflow, "Welcome customer 12345", "request-1", "conversation-1")
+            .await
+            .unwrap();
+
+        assert!(result.success);
+        assert_eq!(result.output["text"], "Welcome message sent");
+        let requests = executor.requests();
+        assert_eq!(requests.len(), 1);
+        let plan: Value = serde_json::from_str(&requests[0]).unwrap();
+        assert!(plan.to_string().contains("activity_3"));
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use agent_core::agent_interaction_protocol::a2a_agent_interaction::A2AAgentInteraction;
+use configuration::AgentReference;
+
+use crate::agent_pool::{AgentPool, CircuitState};
+
+/// Current health of a registered agent.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentHealth {
+    pub agent_id: String,
+    pub url: String,
+    /// Whether the agent card could be fetched at the last refresh or probe.
+    pub connected: bool,
+    pub circuit: CircuitState,
+    pub in_flight: usize,
+    /// Share of failed delegations among the recent ones.
+    pub failure_rate: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Connected, circuit not open, and failure rate below the limit.
+    pub healthy: bool,
+}
+
+/// Handle on the agents of an `A2AAgentInvoker`, used to probe them and query their health,
+/// e.g. from a planner that must leave unhealthy agents out of its plans.
+#[derive(Clone)]
+pub struct AgentHealthMonitor {
+    pub(crate) agents_references: Arc<RwLock<Vec<AgentReference>>>,
+    pub(crate) client_agents: Arc<RwLock<HashMap<String, A2AAgentInteraction>>>,
+    pub(crate) agent_pool: Arc<AgentPool>,
+}
+
+impl AgentHealthMonitor {
+    pub async fn agent_health(&self) -> Vec<AgentHealth> {
+        let agents_references = self.agents_references.read().await;
+        let client_agents = self.client_agents.read().await;
+
+        agents_references
+            .iter()
+            .map(|agent_reference| {
+                let connected = client_agents.contains_key(&agent_reference.id);
+                let stats = self.agent_pool.snapshot(&agent_reference.id);
+                AgentHealth {
+                    agent_id: agent_reference.id.clone(),
+                    url: agent_reference.url.clone(),
+                    connected,
+                    circuit: stats.circuit,
+                    in_flight: stats.in_flight,
+                    failure_rate: stats.failure_rate,
+                    last_error: stats.last_error,
+                    healthy: connected && stats.healthy,
+                }
+            })
+            .collect()
+    }
+
+    pub async fn unhealthy_agents(&self) -> HashSet<String> {
+        self.agent_health()
+            .await
+            .into_iter()
+            .filter(|health| !health.healthy)
+            .map(|health| health.agent_id)
+            .collect()
+    }
+
+    /// Fetches the agent card of every registered agent. Agents answering are (re)connected,
+    /// failures count towards opening their circuit.
+    pub async fn probe_agents(&self) {
+        let agents_references = self.agents_references.read().await.clone();
+
+        for agent_reference in agents_references {
+            let probe = match agent_reference.get_agent_reference().await {
+                Ok(agent_details) => A2AAgentInteraction::new(agent_details.id.clone(), agent_details.url.clone()).await,
+                Err(e) => Err(e),
+            };
+
+            match probe {
+                Ok(client) => {
+                    debug!("Health probe of agent '{}' succeeded", agent_reference.id);
+                    self.client_agents.write().await.insert(agent_reference.id.clone(), client);
+                    self.agent_pool.record_probe(&agent_reference.id, Ok(()));
+                }
+                Err(e) => {
+                    warn!("Health probe of agent '{}' at {} failed: {}", agent_reference.id, agent_reference.url, e);
+                    self.agent_pool.record_probe(&agent_reference.id, Err(e.to_string()));
+                }
+            }
+        }
+    }
+
+    /// Probes the agents every `interval` until the returned task is aborted.
+    pub fn start_probes(&self, interval: Duration) -> JoinHandle<()> {
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                monitor.probe_agents().await;
+            }
+        })
+    }
+}
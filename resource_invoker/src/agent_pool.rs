@@ -0,0 +1,372 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Number of recent call outcomes kept per agent.
+const HEALTH_WINDOW: usize = 10;
+/// Outcomes needed before an agent can be considered unhealthy.
+const MIN_SAMPLES: usize = 3;
+/// Share of failed calls in the window from which an agent is considered unhealthy.
+const MAX_FAILURE_RATE: f64 = 0.5;
+
+/// How delegations are spread across the agents offering the same skill.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LoadBalancing {
+    /// Each delegation starts with the next agent in turn.
+    #[default]
+    RoundRobin,
+    /// Each delegation starts with the agent running the fewest delegations.
+    LeastInFlight,
+}
+
+/// When the circuit of an agent opens, and for how long delegations skip it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerPolicy {
+    /// Consecutive failed calls or health probes that open the circuit.
+    pub failure_threshold: u32,
+    /// Time during which an open circuit receives no delegation. A single trial
+    /// delegation is then let through: its success closes the circuit, its failure reopens it.
+    pub open_duration: Duration,
+}
+
+impl Default for CircuitBreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    /// The agent receives no delegation.
+    Open,
+    /// The agent receives a single trial delegation.
+    HalfOpen,
+}
+
+#[derive(Debug, Default)]
+struct AgentStats {
+    recent_outcomes: VecDeque<bool>,
+    in_flight: usize,
+    consecutive_failures: u32,
+    /// Set while the circuit is open or half-open: the time from which a trial delegation is allowed.
+    open_until: Option<Instant>,
+    /// Set while a delegation holds the trial of a half-open circuit, see `TrialReservations`.
+    trial_in_progress: bool,
+    last_error: Option<String>,
+}
+
+impl AgentStats {
+    fn failure_rate(&self) -> f64 {
+        if self.recent_outcomes.is_empty() {
+            return 0.0;
+        }
+        let failures = self.recent_outcomes.iter().filter(|success| !**success).count();
+        (failures as f64) / (self.recent_outcomes.len() as f64)
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.recent_outcomes.len() < MIN_SAMPLES || self.failure_rate() < MAX_FAILURE_RATE
+    }
+
+    fn circuit(&self, now: Instant) -> CircuitState {
+        match self.open_until {
+            None => CircuitState::Closed,
+            Some(open_until) if now < open_until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    fn record(&mut self, success: bool, error: Option<String>, policy: &CircuitBreakerPolicy) {
+        let now = Instant::now();
+        if success {
+            self.consecutive_failures = 0;
+            self.open_until = None;
+            return;
+        }
+        self.consecutive_failures += 1;
+        self.last_error = error;
+        if self.circuit(now) == CircuitState::HalfOpen || self.consecutive_failures >= policy.failure_threshold {
+            self.open_until = Some(now + policy.open_duration);
+        }
+    }
+}
+
+/// Health of an agent as seen from its recent delegations and probes.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentStatsSnapshot {
+    pub circuit: CircuitState,
+    pub in_flight: usize,
+    /// Share of failed delegations among the recent ones.
+    pub failure_rate: f64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Closed or half-open circuit, and a failure rate below the limit.
+    pub healthy: bool,
+}
+
+/// Tracks in-flight delegations, recent outcomes and circuit of each agent, and orders the
+/// agents to try for a delegation.
+#[derive(Debug, Default)]
+pub(crate) struct AgentPool {
+    /// Set in place, so that the health monitors already handed out keep watching this pool.
+    load_balancing: Mutex<LoadBalancing>,
+    circuit_breaker: Mutex<CircuitBreakerPolicy>,
+    stats: Arc<Mutex<HashMap<String, AgentStats>>>,
+    next: AtomicUsize,
+}
+
+impl AgentPool {
+    pub(crate) fn set_load_balancing(&self, load_balancing: LoadBalancing) {
+        *self.load_balancing.lock().unwrap() = load_balancing;
+    }
+
+    pub(crate) fn set_circuit_breaker(&self, circuit_breaker: CircuitBreakerPolicy) {
+        *self.circuit_breaker.lock().unwrap() = circuit_breaker;
+    }
+
+    fn circuit_breaker(&self) -> CircuitBreakerPolicy {
+        *self.circuit_breaker.lock().unwrap()
+    }
+
+    /// Orders `candidates` in the order they should be tried: healthy agents first,
+    /// spread according to the load-balancing strategy, then unhealthy ones as a last resort.
+    /// Agents whose circuit is open are left out, as are half-open ones whose trial is taken. The trial
+    /// of a half-open agent is reserved in `trials` under the same lock, so that a single delegation gets it.
+    pub(crate) fn order(&self, mut candidates: Vec<String>, trials: &mut TrialReservations) -> Vec<String> {
+        if candidates.len() > 1 {
+            let start = self.next.fetch_add(1, Ordering::Relaxed) % candidates.len();
+            candidates.rotate_left(start);
+        }
+
+        let now = Instant::now();
+        let mut stats = self.stats.lock().unwrap();
+        candidates.retain(|id| {
+            let Some(agent_stats) = stats.get_mut(id) else {
+                return true;
+            };
+            match agent_stats.circuit(now) {
+                CircuitState::Closed => true,
+                CircuitState::Open => false,
+                CircuitState::HalfOpen if agent_stats.in_flight > 0 || agent_stats.trial_in_progress => false,
+                CircuitState::HalfOpen => {
+                    agent_stats.trial_in_progress = true;
+                    trials.agent_ids.push(id.clone());
+                    true
+                }
+            }
+        });
+
+        let in_flight = |id: &String| stats.get(id).map_or(0, |stats| stats.in_flight);
+        let circuit = |id: &String| stats.get(id).map_or(CircuitState::Closed, |stats| stats.circuit(now));
+        let healthy = |id: &String| stats.get(id).is_none_or(AgentStats::is_healthy);
+
+        let (mut ordered, unhealthy): (Vec<String>, Vec<String>) = candidates
+            .into_iter()
+            .partition(|id| healthy(id) && circuit(id) == CircuitState::Closed);
+        if *self.load_balancing.lock().unwrap() == LoadBalancing::LeastInFlight {
+            // Stable sort: agents as busy as each other keep the round-robin order
+            ordered.sort_by_key(|id| in_flight(id));
+        }
+        ordered.extend(unhealthy);
+        ordered
+    }
+
+    /// Holds the trials reserved by `order` for one delegation.
+    pub(crate) fn trials(&self) -> TrialReservations {
+        TrialReservations {
+            agent_ids: Vec::new(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Counts a delegation to `agent_id` as in flight until the returned guard is finished or dropped.
+    pub(crate) fn start(&self, agent_id: &str) -> InFlight {
+        self.stats
+            .lock()
+            .unwrap()
+            .entry(agent_id.to_string())
+            .or_default()
+            .in_flight += 1;
+        InFlight {
+            agent_id: agent_id.to_string(),
+            stats: self.stats.clone(),
+            circuit_breaker: self.circuit_breaker(),
+            outcome: None,
+        }
+    }
+
+    /// Records the outcome of a health probe. Probes feed the circuit breaker, not the failure rate,
+    /// and a successful probe lets a trial delegation through an open circuit right away.
+    pub(crate) fn record_probe(&self, agent_id: &str, result: Result<(), String>) {
+        let circuit_breaker = self.circuit_breaker();
+        let mut stats = self.stats.lock().unwrap();
+        let agent_stats = stats.entry(agent_id.to_string()).or_default();
+        match result {
+            Ok(()) => {
+                if agent_stats.open_until.is_some() {
+                    agent_stats.open_until = Some(Instant::now());
+                }
+            }
+            Err(error) => agent_stats.record(false, Some(error), &circuit_breaker),
+        }
+    }
+
+    pub(crate) fn snapshot(&self, agent_id: &str) -> AgentStatsSnapshot {
+        let now = Instant::now();
+        let stats = self.stats.lock().unwrap();
+        let default_stats = AgentStats::default();
+        let agent_stats = stats.get(agent_id).unwrap_or(&default_stats);
+        let circuit = agent_stats.circuit(now);
+        AgentStatsSnapshot {
+            circuit,
+            in_flight: agent_stats.in_flight,
+            failure_rate: agent_stats.failure_rate(),
+            consecutive_failures: agent_stats.consecutive_failures,
+            last_error: agent_stats.last_error.clone(),
+            healthy: circuit != CircuitState::Open && agent_stats.is_healthy(),
+        }
+    }
+}
+
+/// Trials of half-open circuits reserved for a delegation. Dropping it frees them, whether the
+/// delegation ran them or succeeded on another agent first.
+pub(crate) struct TrialReservations {
+    agent_ids: Vec<String>,
+    stats: Arc<Mutex<HashMap<String, AgentStats>>>,
+}
+
+impl Drop for TrialReservations {
+    fn drop(&mut self) {
+        let mut stats = self.stats.lock().unwrap();
+        for agent_id in &self.agent_ids {
+            if let Some(agent_stats) = stats.get_mut(agent_id) {
+                agent_stats.trial_in_progress = false;
+            }
+        }
+    }
+}
+
+/// A delegation in flight. Dropping it without calling `succeed` or `fail` counts neither a success nor a failure.
+pub(crate) struct InFlight {
+    agent_id: String,
+    stats: Arc<Mutex<HashMap<String, AgentStats>>>,
+    circuit_breaker: CircuitBreakerPolicy,
+    outcome: Option<Result<(), String>>,
+}
+
+impl InFlight {
+    pub(crate) fn succeed(mut self) {
+        self.outcome = Some(Ok(()));
+    }
+
+    pub(crate) fn fail(mut self, error: String) {
+        self.outcome = Some(Err(error));
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut stats = self.stats.lock().unwrap();
+        let agent_stats = stats.entry(self.agent_id.clone()).or_default();
+        agent_stats.in_flight = agent_stats.in_flight.saturating_sub(1);
+        if let Some(outcome) = self.outcome.take() {
+            agent_stats.recent_outcomes.push_back(outcome.is_ok());
+            if agent_stats.recent_outcomes.len() > HEALTH_WINDOW {
+                agent_stats.recent_outcomes.pop_front();
+            }
+            agent_stats.record(outcome.is_ok(), outcome.err(), &self.circuit_breaker);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(load_balancing: LoadBalancing, circuit_breaker: CircuitBreakerPolicy) -> AgentPool {
+        let pool = AgentPool::default();
+        pool.set_load_balancing(load_balancing);
+        pool.set_circuit_breaker(circuit_breaker);
+        pool
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_unhealthy_agents_are_tried_last() {
+        let pool = pool(LoadBalancing::RoundRobin, CircuitBreakerPolicy {
+            failure_threshold: u32::MAX,
+            ..Default::default()
+        });
+        assert_eq!(pool.order(ids(&["a", "b"]), &mut pool.trials())[0], "a");
+        assert_eq!(pool.order(ids(&["a", "b"]), &mut pool.trials())[0], "b");
+
+        for _ in 0..MIN_SAMPLES {
+            pool.start("a").fail("down".to_string());
+        }
+        for _ in 0..4 {
+            assert_eq!(pool.order(ids(&["a", "b"]), &mut pool.trials()), ids(&["b", "a"]));
+        }
+    }
+
+    #[test]
+    fn test_least_in_flight_prefers_idle_agents() {
+        let pool = pool(LoadBalancing::LeastInFlight, CircuitBreakerPolicy::default());
+        let _busy = pool.start("a");
+        assert_eq!(pool.order(ids(&["a", "b"]), &mut pool.trials())[0], "b");
+        assert_eq!(pool.order(ids(&["a", "b"]), &mut pool.trials())[0], "b");
+    }
+
+    #[test]
+    fn test_circuit_opens_then_lets_one_trial_through() {
+        let pool = pool(LoadBalancing::RoundRobin, CircuitBreakerPolicy {
+            failure_threshold: 2,
+            open_duration: Duration::from_millis(50),
+        });
+        pool.start("a").fail("timeout".to_string());
+        assert_eq!(pool.snapshot("a").circuit, CircuitState::Closed);
+        pool.record_probe("a", Err("unreachable".to_string()));
+
+        let snapshot = pool.snapshot("a");
+        assert_eq!(snapshot.circuit, CircuitState::Open);
+        assert_eq!(snapshot.last_error.as_deref(), Some("unreachable"));
+        assert!(!snapshot.healthy);
+        assert_eq!(pool.order(ids(&["a", "b"]), &mut pool.trials()), ids(&["b"]));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(pool.snapshot("a").circuit, CircuitState::HalfOpen);
+        let trial = pool.start("a");
+        assert_eq!(pool.order(ids(&["a", "b"]), &mut pool.trials()), ids(&["b"]));
+        trial.succeed();
+        assert_eq!(pool.snapshot("a").circuit, CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_trial_is_reserved_by_a_single_delegation() {
+        let pool = pool(LoadBalancing::RoundRobin, CircuitBreakerPolicy {
+            failure_threshold: 1,
+            open_duration: Duration::from_millis(10),
+        });
+        pool.start("a").fail("timeout".to_string());
+        std::thread::sleep(Duration::from_millis(20));
+
+        // Both delegations order the agents before either starts its trial
+        let mut first_trials = pool.trials();
+        let mut second_trials = pool.trials();
+        assert_eq!(pool.order(ids(&["a"]), &mut first_trials), ids(&["a"]));
+        assert!(pool.order(ids(&["a"]), &mut second_trials).is_empty());
+
+        // Freed when the first delegation ends without running it
+        drop(first_trials);
+        assert_eq!(pool.order(ids(&["a"]), &mut second_trials), ids(&["a"]));
+    }
+}
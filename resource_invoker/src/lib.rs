@@ -1,6 +1,15 @@
+mod agent_health;
+mod agent_pool;
+
+pub use agent_health::{AgentHealth, AgentHealthMonitor};
+pub use agent_pool::{CircuitBreakerPolicy, CircuitState, LoadBalancing};
+use agent_pool::{AgentPool, TrialReservations};
+
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 
 use async_trait::async_trait;
 use tracing::{debug, warn, info};
@@ -13,19 +22,22 @@ use agent_core::agent_interaction_protocol::a2a_agent_interaction::A2AAgentInter
 use agent_core::business_logic::services::{EvaluationService, MemoryService, DiscoveryService, WorkflowServiceApi};
 use configuration::{AgentReference, McpRuntimeConfig};
 
-use rmcp::model::CallToolRequestParams;
 use mcp_runtime::runtime::mcp_runtime::{McpRuntime};
+use mcp_runtime::mcp_client::mcp_servers::{McpServerConfig, McpServersConfig};
 
 // Re-export the traits from workflow_management for convenience
-pub use workflow_management::agent_communication::agent_invoker::AgentInvoker;
+pub use workflow_management::agent_communication::agent_invoker::{AgentAnswer, AgentInvoker};
 pub use workflow_management::tasks::task_invoker::TaskInvoker;
 pub use workflow_management::tools::tool_invoker::ToolInvoker;
 
 
-use rmcp::model::{ListToolsResult, Tool as RmcpTool}; // Alias for clarity
+use rmcp::model::Tool as RmcpTool; // Alias for clarity
 use llm_api::tools::{FunctionDefinition, FunctionParameters, Tool};
 use std::any::Any;
 
+/// Interval between two health probes of the agents, for launchers that do not configure it.
+pub const DEFAULT_HEALTH_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
 /// An AgentInvoker that communicates using the A2A protocol over HTTP.
 #[allow(dead_code)]
 pub struct A2AAgentInvoker {
@@ -33,26 +45,54 @@ pub struct A2AAgentInvoker {
     client_agents: Arc<RwLock<HashMap<String, A2AAgentInteraction>>>,
     evaluation_service: Option<Arc<dyn EvaluationService>>,
     memory_service: Option<Arc<dyn MemoryService>>,
-    discovery_service_client: Arc<dyn DiscoveryService>,
+    /// `None` when the agents are given up front instead of discovered.
+    discovery_service_client: Option<Arc<dyn DiscoveryService>>,
+    agent_pool: Arc<AgentPool>,
 }
 
 #[async_trait]
 impl AgentInvoker for A2AAgentInvoker {
     /// This function is called by the workflow_runtime when an activity is delegated to an agent in order to execute an activity.
-    #[allow(unused_variables)]
     async fn interact(&self, agent_id: String, message:  String, skill_to_use: String ) -> anyhow::Result<Value> {
-       
-        let client_agents_read_guard = self.client_agents.read().await; // Acquire read lock
-        let agent_client = client_agents_read_guard
-            .get(&agent_id)
-            .ok_or(anyhow!("Agent '{}' not found", agent_id))?;
+        self.delegate(agent_id, message, skill_to_use).await.map(|answer| answer.response)
+    }
 
-        // execute the task by remote agent
-        let outcome = agent_client.execute_task(&message, "default_skill").await?;
-        
-        debug!("A2AAgentInvoker : {}", outcome);
+    /// Delegations are spread across the agents advertising `skill_to_use`, then tried on the preferred agent,
+    /// falling back to the default agent, and retried on the next agent when one fails. The answer reports
+    /// the agent that actually ran the activity.
+    async fn delegate(&self, agent_id: String, message:  String, skill_to_use: String ) -> anyhow::Result<AgentAnswer> {
+
+        // Held until the delegation ends, so that no other delegation takes the trial of a half-open agent
+        let mut trials = self.agent_pool.trials();
+        let candidates = self.candidates(&agent_id, &skill_to_use, &mut trials).await;
+        if candidates.is_empty() {
+            return Err(anyhow!("No agent found for agent '{}' or skill '{}'", agent_id, skill_to_use));
+        }
 
-        Ok(serde_json::Value::String(outcome))
+        let skill = if skill_to_use.is_empty() { "default_skill" } else { skill_to_use.as_str() };
+
+        let mut last_error = None;
+        for agent_client in candidates {
+            let in_flight = self.agent_pool.start(&agent_client.id);
+
+            // execute the task by remote agent
+            match agent_client.execute_task(&message, skill).await {
+                Ok(outcome) => {
+                    in_flight.succeed();
+                    debug!("A2AAgentInvoker : agent '{}' answered {}", agent_client.id, outcome);
+                    return Ok(AgentAnswer {
+                        agent_id: agent_client.id.clone(),
+                        response: Value::String(outcome),
+                    });
+                }
+                Err(e) => {
+                    warn!("Agent '{}' failed to execute the task: {}. Trying next agent, if any.", agent_client.id, e);
+                    in_flight.fail(e.to_string());
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("No agent could execute the task")))
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -73,8 +113,17 @@ impl WorkflowServiceApi for A2AAgentInvoker {
     async fn refresh_agents(&self) -> anyhow::Result<()> {
         info!("Refreshing agent list...");
 
+        // Without discovery service, reconnect to the agents given at creation
+        let Some(discovery_service_client) = &self.discovery_service_client else {
+            let agents_references = self.agents_references.read().await.clone();
+            let new_client_agents = Self::connect_to_a2a_agents(&agents_references).await?;
+            *self.client_agents.write().await = new_client_agents;
+            info!("Agent list refreshed successfully.");
+            return Ok(());
+        };
+
         // 1. Discover agent definitions using the DiscoveryService trait method
-        let agent_definitions = self.discovery_service_client.discover_agents().await?;
+        let agent_definitions = discovery_service_client.discover_agents().await?;
         info!("Discovered {} agent definitions during refresh.", agent_definitions.len());
 
         // 2. Convert AgentDefinition to AgentReference
@@ -120,12 +169,92 @@ impl A2AAgentInvoker {
             client_agents: Arc::new(RwLock::new(HashMap::new())),
             evaluation_service,
             memory_service,
-            discovery_service_client,
+            discovery_service_client: Some(discovery_service_client),
+            agent_pool: Arc::new(AgentPool::default()),
         };
         invoker.refresh_agents().await?; // Call refresh during initialization
         Ok(invoker)
     }
 
+    /// Instantiates an A2AAgentInvoker connected to a fixed list of agents, without discovery service.
+    pub async fn new_with_agents(agents_references: Vec<AgentReference>) -> anyhow::Result<Self> {
+        let invoker = Self {
+            agents_references: Arc::new(RwLock::new(agents_references)),
+            client_agents: Arc::new(RwLock::new(HashMap::new())),
+            evaluation_service: None,
+            memory_service: None,
+            discovery_service_client: None,
+            agent_pool: Arc::new(AgentPool::default()),
+        };
+        invoker.refresh_agents().await?;
+        Ok(invoker)
+    }
+
+    /// Sets how delegations are spread across agents offering the same skill. Defaults to round-robin.
+    pub fn with_load_balancing(self, load_balancing: LoadBalancing) -> Self {
+        self.agent_pool.set_load_balancing(load_balancing);
+        self
+    }
+
+    /// Sets when the circuit of a failing agent opens. Defaults to 3 consecutive failures, open for 30 seconds.
+    pub fn with_circuit_breaker(self, circuit_breaker: CircuitBreakerPolicy) -> Self {
+        self.agent_pool.set_circuit_breaker(circuit_breaker);
+        self
+    }
+
+    /// Handle to probe the agents and query their health.
+    pub fn health_monitor(&self) -> AgentHealthMonitor {
+        AgentHealthMonitor {
+            agents_references: self.agents_references.clone(),
+            client_agents: self.client_agents.clone(),
+            agent_pool: self.agent_pool.clone(),
+        }
+    }
+
+    /// Probes the agents every `interval`, see `AgentHealthMonitor::probe_agents`.
+    pub fn start_health_probes(&self, interval: Duration) -> JoinHandle<()> {
+        self.health_monitor().start_probes(interval)
+    }
+
+    /// Agents to try for a delegation, in order: the agents advertising the skill, ordered by the load-balancing
+    /// strategy, then the preferred agent when it does not advertise the skill. Without skill, the preferred agent only.
+    /// Falls back to the default agent when none of them can be tried, e.g. when their circuits are all open.
+    async fn candidates(&self, agent_id: &str, skill: &str, trials: &mut TrialReservations) -> Vec<A2AAgentInteraction> {
+        let client_agents = self.client_agents.read().await;
+        let mut skilled_ids: Vec<String> = client_agents
+            .iter()
+            .filter(|(id, agent)| if skill.is_empty() { id.as_str() == agent_id } else { agent.has_skill(skill) })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        // Sorted so that round-robin follows a stable order
+        skilled_ids.sort();
+        let mut agent_ids = self.agent_pool.order(skilled_ids, trials);
+        if !skill.is_empty() && client_agents.get(agent_id).is_some_and(|agent| !agent.has_skill(skill)) {
+            agent_ids.extend(self.agent_pool.order(vec![agent_id.to_string()], trials));
+        }
+
+        if agent_ids.is_empty() {
+            let skill_advertised = !skill.is_empty() && client_agents.values().any(|agent| agent.has_skill(skill));
+            warn!(
+                "No agent available for agent '{}' or skill '{}'. Looking for the default agent.",
+                agent_id, skill
+            );
+            // Agents advertising the skill all have an open circuit: only the default agent is left
+            if skill_advertised {
+                let agents_references = self.agents_references.read().await;
+                return Self::default_agent(&client_agents, &agents_references).into_iter().collect();
+            }
+            drop(client_agents);
+            return self.find_agent_with_skill(skill, "").await.into_iter().collect();
+        }
+
+        agent_ids
+            .into_iter()
+            .filter_map(|id| client_agents.get(&id).cloned())
+            .collect()
+    }
+
     /// This function retrieves a list of clients agents , the list of agents that are referenced
     async fn connect_to_a2a_agents(
         agents_references: &[AgentReference],
@@ -152,7 +281,8 @@ impl A2AAgentInvoker {
                     client_agents.insert(client.id.clone(), client);
                 }
                 Err(e) => {
-                    debug!(
+                    // Reported as not connected by the health monitor, and reconnected by the next probe that succeeds
+                    warn!(
                         "Warning: Failed to connect to A2A agent '{}' at {}: {}",
                         agent_details.id, agent_details.url, e
                     );
@@ -168,7 +298,6 @@ impl A2AAgentInvoker {
         Ok(client_agents)
     }
 
-    #[allow(dead_code)]
     pub async fn find_agent_with_skill(&self, skill: &str, _task_id: &str) -> Option<A2AAgentInteraction> { // Return owned A2AAgentInteraction
         let client_agents_read_guard = self.client_agents.read().await; // Acquire read lock
         let agents_references_read_guard = self.agents_references.read().await; // Acquire read lock
@@ -187,24 +316,30 @@ impl A2AAgentInvoker {
          // 2. If no agent with the specific skill is found, try to find the default agent
          warn!("WorkFlow Management: No agent found with skill '{}' . Attempting to find default agent.", skill);
 
-         for agent_ref_config in agents_references_read_guard.iter() {
-             if agent_ref_config.is_default == Some(true) {
-                 // We need to find the A2AClient instance associated with this default SimpleAgentReference
-                 // We can do this by matching the name or ID. Assuming client.id is agent_reference.name
-                 if let Some(default_agent_client) = client_agents_read_guard.get(&agent_ref_config.id) {
-                     info!(
-                         "WorkFlow Management: Found default agent '{}' as fallback.",
-                         default_agent_client.id
-                     );
-                     return Some(default_agent_client.clone()); // Clone the agent to return an owned value
-                 }
-             }
+         if let Some(default_agent_client) = Self::default_agent(&client_agents_read_guard, &agents_references_read_guard) {
+             return Some(default_agent_client);
          }
 
          // 3. If no agent with the skill and no default agent are found
          warn!("WorkFlow Management: No suitable agent (skill-matching or default) found for skill '{}'", skill);
          None
     }
+
+    /// The connected agent marked as default in its reference, if any.
+    fn default_agent(
+        client_agents: &HashMap<String, A2AAgentInteraction>,
+        agents_references: &[AgentReference],
+    ) -> Option<A2AAgentInteraction> {
+        let default_agent_client = agents_references
+            .iter()
+            .filter(|agent_reference| agent_reference.is_default == Some(true))
+            .find_map(|agent_reference| client_agents.get(&agent_reference.id))?;
+        info!(
+            "WorkFlow Management: Found default agent '{}' as fallback.",
+            default_agent_client.id
+        );
+        Some(default_agent_client.clone())
+    }
 }
 
 pub struct GreetTask;
@@ -238,16 +373,41 @@ impl McpRuntimeToolInvoker  {
         Ok(Self { mcp_runtime  })
     }
 
+    pub async fn from_config(agent_mcp_config: McpRuntimeConfig) -> anyhow::Result<Self> {
+        let mcp_runtime = Arc::new(McpRuntime::initialize_mcp_client_v2(agent_mcp_config).await?);
+        Ok(Self { mcp_runtime })
+    }
+
+    /// Invoker reaching the tools of several MCP servers, merged in a single namespace.
+    pub async fn from_servers(agent_mcp_config: McpRuntimeConfig, servers: Vec<McpServerConfig>) -> anyhow::Result<Self> {
+        let mcp_runtime = Arc::new(McpRuntime::connect_servers(agent_mcp_config, servers).await?);
+        Ok(Self { mcp_runtime })
+    }
+
+    /// Invoker of the servers listed in `mcp_servers_config_path` when given, of the single
+    /// server of the runtime configuration at `mcp_config_path` otherwise.
+    pub async fn from_config_files(mcp_config_path: String, mcp_servers_config_path: Option<&str>) -> anyhow::Result<Self> {
+        let Some(mcp_servers_config_path) = mcp_servers_config_path else {
+            return Self::new(mcp_config_path).await;
+        };
+        let agent_mcp_config = McpRuntimeConfig::load_agent_config(mcp_config_path.as_str())
+            .context("Error loading MCP config")?;
+        let mcp_servers_config = McpServersConfig::load(mcp_servers_config_path)?;
+        Self::from_servers(agent_mcp_config, mcp_servers_config.mcp_servers).await
+    }
+
+    /// Runtime reaching the server of the config file at `mcp_config_path`, launching it when the
+    /// file holds its command line.
     pub async fn initialize_mcp_agent(mcp_config_path: String) -> anyhow::Result<McpRuntime> {
         let agent_mcp_config = McpRuntimeConfig::load_agent_config(mcp_config_path.as_str())
             .context("Error loading MCP config for planner")?;
-        let mcp_runtime = McpRuntime::initialize_mcp_client_v2(agent_mcp_config).await?;
+        let server = McpServerConfig::from_runtime_config_file(&mcp_config_path, &agent_mcp_config)?;
+        let mcp_runtime = McpRuntime::connect_servers(agent_mcp_config, vec![server]).await?;
         Ok(mcp_runtime)
     }
 
     pub async fn get_tools_list_v2(&self) -> anyhow::Result<Vec<Tool>> {
-        let list_tools: ListToolsResult = self.mcp_runtime.get_client()?.list_tools(Default::default()).await?;
-        let list_tools:Vec<RmcpTool> = list_tools.tools;
+        let list_tools:Vec<RmcpTool> = self.mcp_runtime.get_tools_list_v2().await?;
         let tools=McpRuntimeToolInvoker::transcode_tools(list_tools);
         Ok(tools?)
     }
@@ -295,12 +455,303 @@ impl ToolInvoker for McpRuntimeToolInvoker  {
     async fn invoke(&self, tool_id:String,params: &Value) -> anyhow::Result<serde_json::Value>  {
         let arguments_map = from_value(params.clone())?;
 
-        let call_tool_request_param = CallToolRequestParams::new(tool_id)
-            .with_arguments(arguments_map);
-
-        let tool_result = self.mcp_runtime.get_client()?.call_tool(call_tool_request_param).await?;
+        // Routed to the MCP server owning the tool
+        let tool_result = self.mcp_runtime.call_tool(&tool_id, arguments_map).await?;
         
         let tool_result_value = serde_json::to_value(&tool_result.content)?;
         Ok(tool_result_value)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::{MockA2aAgent, MockMcpServer};
+    use mcp_runtime::mcp_client::mcp_servers::McpServerTransport;
+    use agent_models::graph::graph_definition::WorkflowPlanInput;
+    use workflow_management::graph::execution_event::ExecutionEvent;
+    use workflow_management::graph::graph_orchestrator::PlanExecutor;
+
+    #[tokio::test]
+    async fn test_mcp_tool_invoker_calls_mock_server() {
+        let server = MockMcpServer::new()
+            .with_tool_fn(
+                "get_customer_details",
+                "Give customer details",
+                json!({"properties": {"customer_id": {"type": "string"}}}),
+                |args| Ok(json!({"customer_id": args["customer_id"], "name": "Jane"})),
+            )
+            .start()
+            .await
+            .unwrap();
+
+        let invoker = McpRuntimeToolInvoker::from_config(server.runtime_config()).await.unwrap();
+        let tools = invoker.get_tools_list_v2().await.unwrap();
+        assert_eq!(tools[0].function.name, "get_customer_details");
+
+        let output = ToolInvoker::invoke(&invoker, "get_customer_details".to_string(), &json!({"customer_id": "1234"}))
+            .await
+            .unwrap();
+        assert!(output.to_string().contains("Jane"));
+        assert_eq!(server.calls()[0].arguments, json!({"customer_id": "1234"}));
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tool_invoker_routes_tools_of_several_servers() {
+        let weather = MockMcpServer::new()
+            .with_tool("get_weather", "Weather of a city", json!({}), json!("sunny"))
+            .with_tool("search", "Search forecasts", json!({}), json!("forecasts"))
+            .start()
+            .await
+            .unwrap();
+        let customer = MockMcpServer::new()
+            .with_tool("search", "Search customers", json!({}), json!("customers"))
+            .start()
+            .await
+            .unwrap();
+        let server = |name: &str, url: String| McpServerConfig {
+            name: name.to_string(),
+            transport: McpServerTransport::Http { url },
+            api_key: None,
+            api_key_env_var: None,
+            tool_prefix: None,
+        };
+
+        let invoker = McpRuntimeToolInvoker::from_servers(
+            weather.runtime_config(),
+            vec![server("weather", weather.url()), server("customer", customer.url())],
+        )
+        .await
+        .unwrap();
+        let names: Vec<String> = invoker
+            .get_tools_list_v2()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tool| tool.function.name)
+            .collect();
+        assert_eq!(names, ["get_weather", "weather__search", "customer__search"]);
+
+        let output = ToolInvoker::invoke(&invoker, "customer__search".to_string(), &json!({})).await.unwrap();
+        assert!(output.to_string().contains("customers"));
+        assert_eq!(customer.calls()[0].name, "search");
+        assert!(weather.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_a2a_agent_invoker_interacts_with_mock_agent() {
+        let agent = MockA2aAgent::new("weather_agent")
+            .with_skill("weather_skill")
+            .answer_when("Boston", json!("sunny"))
+            .start()
+            .await
+            .unwrap();
+
+        let invoker = A2AAgentInvoker::new_with_agents(vec![agent.agent_reference()]).await.unwrap();
+        let output = invoker
+            .interact("weather_agent".to_string(), "Weather in Boston?".to_string(), "weather_skill".to_string())
+            .await
+            .unwrap();
+
+        assert!(output.as_str().unwrap().contains("sunny"));
+        assert_eq!(agent.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_a2a_agent_invoker_routes_by_skill() {
+        let weather = MockA2aAgent::new("weather_agent").with_skill("weather_skill").start().await.unwrap();
+        let general = MockA2aAgent::new("general_agent").as_default().start().await.unwrap();
+        let invoker = A2AAgentInvoker::new_with_agents(vec![weather.agent_reference(), general.agent_reference()])
+            .await
+            .unwrap();
+
+        // Unknown preference: the agent advertising the skill is chosen
+        let answer = invoker
+            .delegate("missing_agent".to_string(), "Weather?".to_string(), "weather_skill".to_string())
+            .await
+            .unwrap();
+        assert_eq!(answer.agent_id, "weather_agent");
+        assert!(answer.response.as_str().unwrap().contains("Answer from weather_agent"));
+
+        // No agent with the skill: the default agent is chosen
+        let answer = invoker
+            .delegate(String::new(), "Stocks?".to_string(), "finance_skill".to_string())
+            .await
+            .unwrap();
+        assert_eq!(answer.agent_id, "general_agent");
+    }
+
+    #[tokio::test]
+    async fn test_a2a_agent_invoker_fails_over_to_healthy_replica() {
+        let failing = MockA2aAgent::new("weather_1").with_skill("weather_skill").fail_first(usize::MAX).start().await.unwrap();
+        let healthy = MockA2aAgent::new("weather_2").with_skill("weather_skill").start().await.unwrap();
+        let invoker = A2AAgentInvoker::new_with_agents(vec![failing.agent_reference(), healthy.agent_reference()])
+            .await
+            .unwrap();
+
+        for _ in 0..6 {
+            let answer = invoker
+                .delegate("weather_1".to_string(), "Weather?".to_string(), "weather_skill".to_string())
+                .await
+                .unwrap();
+            assert_eq!(answer.agent_id, "weather_2");
+        }
+        assert_eq!(healthy.requests().len(), 6);
+        // Once its circuit is open, the failing replica is no longer tried
+        assert!(failing.requests().len() < 6);
+
+        let unhealthy = invoker.health_monitor().unhealthy_agents().await;
+        assert!(unhealthy.contains("weather_1"));
+        assert!(!unhealthy.contains("weather_2"));
+    }
+
+    #[tokio::test]
+    async fn test_a2a_agent_invoker_prefers_agents_advertising_the_skill() {
+        let weather = MockA2aAgent::new("weather_agent").with_skill("weather_skill").start().await.unwrap();
+        let general = MockA2aAgent::new("general_agent").as_default().start().await.unwrap();
+        let invoker = A2AAgentInvoker::new_with_agents(vec![weather.agent_reference(), general.agent_reference()])
+            .await
+            .unwrap();
+
+        // The preferred agent does not advertise the skill, so it takes no turn in the round-robin
+        for _ in 0..4 {
+            let answer = invoker
+                .delegate("general_agent".to_string(), "Weather?".to_string(), "weather_skill".to_string())
+                .await
+                .unwrap();
+            assert_eq!(answer.agent_id, "weather_agent");
+        }
+        assert!(general.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_a2a_agent_invoker_falls_back_to_default_agent_when_circuits_are_open() {
+        let weather = MockA2aAgent::new("weather_agent")
+            .with_skill("weather_skill")
+            .fail_first(usize::MAX)
+            .start()
+            .await
+            .unwrap();
+        let general = MockA2aAgent::new("general_agent").as_default().start().await.unwrap();
+        let invoker = A2AAgentInvoker::new_with_agents(vec![weather.agent_reference(), general.agent_reference()])
+            .await
+            .unwrap();
+        // Taken before the policy is set, the monitor still watches the agents the invoker delegates to
+        let health_monitor = invoker.health_monitor();
+        let invoker = invoker.with_circuit_breaker(CircuitBreakerPolicy {
+                failure_threshold: 1,
+                open_duration: Duration::from_secs(60),
+            });
+
+        // The failing agent opens its circuit, and the preferred agent is tried after it
+        let answer = invoker
+            .delegate("general_agent".to_string(), "Weather?".to_string(), "weather_skill".to_string())
+            .await
+            .unwrap();
+        assert_eq!(answer.agent_id, "general_agent");
+
+        let answer = invoker
+            .delegate(String::new(), "Weather?".to_string(), "weather_skill".to_string())
+            .await
+            .unwrap();
+        assert_eq!(answer.agent_id, "general_agent");
+        assert_eq!(weather.requests().len(), 1);
+        assert!(health_monitor.unhealthy_agents().await.contains("weather_agent"));
+    }
+
+    #[tokio::test]
+    async fn test_plan_executor_runs_a_plan_against_mock_servers() {
+        let mcp = MockMcpServer::new()
+            .with_tool(
+                "get_customer_city",
+                "City of a customer",
+                json!({"properties": {"customer_id": {"type": "string"}}}),
+                json!("Paris"),
+            )
+            .with_tool_fn(
+                "get_weather",
+                "Weather of a city",
+                json!({"properties": {"city": {"type": "string"}}}),
+                |args| Ok(json!(format!("sunny in {}", args["city"].as_str().unwrap_or_default()))),
+            )
+            .start()
+            .await
+            .unwrap();
+        let writer = MockA2aAgent::new("writer_agent")
+            .with_skill("writing_skill")
+            .answer(json!("Welcome to Paris, enjoy the sun!"))
+            .start()
+            .await
+            .unwrap();
+
+        let workflow: WorkflowPlanInput = serde_json::from_value(json!({
+            "plan_name": "welcome",
+            "activities": [
+                {
+                    "id": "customer",
+                    "description": "Find the city of the customer",
+                    "type": "DirectToolUse",
+                    "activity_type": "direct_tool_use",
+                    "agent": {},
+                    "tools": [{ "tool_to_use": "get_customer_city", "tool_parameters": { "customer_id": "12345" } }],
+                    "tasks": [],
+                    "dependencies": [],
+                    "expected_outcome": "City of the customer"
+                },
+                {
+                    "id": "weather",
+                    "description": "Weather in the city of the customer",
+                    "type": "DirectToolUse",
+                    "activity_type": "direct_tool_use",
+                    "agent": {},
+                    "tools": [{ "tool_to_use": "get_weather", "tool_parameters": { "city": "{{customer[0].text}}" } }],
+                    "tasks": [],
+                    "dependencies": [{ "source": "customer" }],
+                    "expected_outcome": "Weather of the city"
+                },
+                {
+                    "id": "welcome",
+                    "description": "Write a welcome message mentioning the weather",
+                    "type": "DelegationAgent",
+                    "activity_type": "delegation_agent",
+                    "agent": { "skill_to_use": "writing_skill", "assigned_agent_id_preference": "writer_agent" },
+                    "tools": [],
+                    "tasks": [],
+                    "dependencies": [{ "source": "weather" }],
+                    "expected_outcome": "A welcome message"
+                }
+            ]
+        }))
+        .unwrap();
+
+        let mut executor = PlanExecutor::new(
+            workflow.into(),
+            Arc::new(GreetTask),
+            Arc::new(A2AAgentInvoker::new_with_agents(vec![writer.agent_reference()]).await.unwrap()),
+            Arc::new(McpRuntimeToolInvoker::from_config(mcp.runtime_config()).await.unwrap()),
+            "Welcome customer 12345".to_string(),
+        );
+        let mut events = executor.subscribe_events();
+        let (_, outcomes) = executor.execute_plan().await.unwrap();
+
+        let calls = mcp.calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].arguments, json!({"customer_id": "12345"}));
+        assert_eq!(calls[1].name, "get_weather");
+        assert_eq!(calls[1].arguments, json!({"city": "Paris"}));
+        assert!(outcomes["weather"].contains("sunny in Paris"));
+
+        let requests = writer.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("Write a welcome message mentioning the weather"));
+        assert!(outcomes["welcome"].contains("enjoy the sun"));
+        // The outcome is the answer itself, the agent that gave it is reported as an event
+        assert!(!outcomes["welcome"].contains("agent_id"));
+        let mut chosen = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let ExecutionEvent::AgentChosen { node_id, agent_id, .. } = event {
+                chosen.push((node_id, agent_id));
+            }
+        }
+        assert_eq!(chosen, [("welcome".to_string(), "writer_agent".to_string())]);
+    }
+}
@@ -0,0 +1,61 @@
+//! In-process doubles of the services the swarm talks to over the network, so that
+//! `McpRuntimeToolInvoker`, `A2AAgentInvoker`, `McpAgent` and the components built on them can be
+//! exercised in `cargo test`.
+//!
+//! The doubles bind to an ephemeral port on 127.0.0.1 and stop when their handle is dropped.
+//! Invokers running plans without any service are re-exported from `workflow_management`,
+//! whose own tests use them as well.
+
+pub mod mock_a2a_agent;
+pub mod mock_llm;
+pub mod mock_mcp_server;
+
+pub use mock_a2a_agent::{MockA2aAgent, RunningMockA2aAgent};
+pub use mock_llm::{MockLlm, RunningMockLlm};
+pub use mock_mcp_server::{MockMcpServer, RunningMockMcpServer};
+pub use workflow_management::testing::{EchoTasks, EchoTools, NoAgents, NoTools};
+
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Delay after which a double that does not accept connections is reported as failed.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of ports tried by a double whose server binds its address itself.
+const START_ATTEMPTS: usize = 5;
+
+/// Reserves a free port on 127.0.0.1, for servers that bind their address themselves.
+///
+/// The port is released before the server binds it, so another process may take it in between:
+/// such servers are started with `wait_until_serving`, and started again on another port when it fails.
+pub(crate) fn free_local_addr() -> anyhow::Result<SocketAddr> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    Ok(listener.local_addr()?)
+}
+
+/// Waits until `server` accepts connections on `addr`.
+///
+/// Fails when the task of the server ends first, which happens when it could not bind `addr`.
+pub(crate) async fn wait_until_serving(addr: SocketAddr, server: &JoinHandle<()>) -> anyhow::Result<()> {
+    let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        if server.is_finished() {
+            anyhow::bail!("Server on {} stopped before accepting connections", addr);
+        }
+        match tokio::net::TcpStream::connect(addr).await {
+            // The connection may have reached another process owning the port, unless the server is still up
+            Ok(_) => {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                if server.is_finished() {
+                    anyhow::bail!("Server on {} stopped before accepting connections", addr);
+                }
+                return Ok(());
+            }
+            Err(e) if tokio::time::Instant::now() >= deadline => {
+                anyhow::bail!("Nothing listening on {} after {:?}: {}", addr, STARTUP_TIMEOUT, e)
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use agent_core::business_logic::agent::Agent;
+use agent_core::business_logic::mcp_runtime::McpRuntimeDetails;
+use agent_core::business_logic::services::{DiscoveryService, EvaluationService, MemoryService, WorkflowServiceApi};
+use agent_core::server::agent_server::AgentServer;
+use agent_models::agent_request::AgentRequest;
+use agent_models::execution::execution_result::ExecutionResult;
+use configuration::{AgentConfig, AgentReference};
+
+use crate::{START_ATTEMPTS, free_local_addr, wait_until_serving};
+
+#[derive(Clone, Debug)]
+enum Reply {
+    Answer(Value),
+    Fail(String),
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    /// Text the user query must contain for the rule to apply.
+    contains: String,
+    reply: Reply,
+}
+
+#[derive(Clone, Debug)]
+struct Script {
+    rules: Vec<Rule>,
+    default_reply: Reply,
+    delay: Option<Duration>,
+    failures_before_success: usize,
+}
+
+impl Script {
+    fn new(agent_id: &str) -> Self {
+        Self {
+            rules: Vec::new(),
+            default_reply: Reply::Answer(Value::String(format!("Answer from {}", agent_id))),
+            delay: None,
+            failures_before_success: 0,
+        }
+    }
+}
+
+/// Builder of a scriptable A2A agent, served by the same `AgentServer` as the real agents.
+///
+/// Replies are chosen by the first rule whose text the user query contains, falling back
+/// to the default answer.
+///
+/// ```ignore
+/// let agent = MockA2aAgent::new("weather_agent")
+///     .with_skill("weather_skill")
+///     .answer_when("Boston", json!("sunny"))
+///     .with_delay(Duration::from_millis(100))
+///     .start()
+///     .await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockA2aAgent {
+    id: String,
+    skill_id: String,
+    is_default: bool,
+    script: Script,
+}
+
+impl MockA2aAgent {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            skill_id: "generic_skill".to_string(),
+            is_default: false,
+            script: Script::new(id),
+        }
+    }
+
+    /// Skill advertised in the agent card. Defaults to `generic_skill`.
+    pub fn with_skill(mut self, skill_id: &str) -> Self {
+        self.skill_id = skill_id.to_string();
+        self
+    }
+
+    /// Marks the agent as the default one in its `AgentReference`.
+    pub fn as_default(mut self) -> Self {
+        self.is_default = true;
+        self
+    }
+
+    /// Answer given when no rule applies.
+    pub fn answer(mut self, answer: Value) -> Self {
+        self.script.default_reply = Reply::Answer(answer);
+        self
+    }
+
+    pub fn answer_when(mut self, query_contains: &str, answer: Value) -> Self {
+        self.script.rules.push(Rule {
+            contains: query_contains.to_string(),
+            reply: Reply::Answer(answer),
+        });
+        self
+    }
+
+    pub fn fail_when(mut self, query_contains: &str, message: &str) -> Self {
+        self.script.rules.push(Rule {
+            contains: query_contains.to_string(),
+            reply: Reply::Fail(message.to_string()),
+        });
+        self
+    }
+
+    /// Waits `delay` before answering each request.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.script.delay = Some(delay);
+        self
+    }
+
+    /// Fails the first `count` requests, whatever their content.
+    pub fn fail_first(mut self, count: usize) -> Self {
+        self.script.failures_before_success = count;
+        self
+    }
+
+    /// Starts the agent on a free port, trying another port when the reserved one got taken.
+    pub async fn start(self) -> anyhow::Result<RunningMockA2aAgent> {
+        let mut last_error = None;
+        for attempt in 1..=START_ATTEMPTS {
+            match self.try_start().await {
+                Ok(agent) => return Ok(agent),
+                Err(e) => {
+                    debug!("Mock A2A agent '{}' failed to start (attempt {}/{}): {}", self.id, attempt, START_ATTEMPTS, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Mock A2A agent '{}' did not start", self.id)))
+    }
+
+    async fn try_start(&self) -> anyhow::Result<RunningMockA2aAgent> {
+        let http_addr = free_local_addr()?;
+        let ws_addr = free_local_addr()?;
+        let url = format!("http://{}", http_addr);
+
+        let agent_config = AgentConfig::builder()
+            .agent_name(self.id.clone())
+            .agent_id(self.id.clone())
+            .agent_description(format!("Mock agent {}", self.id))
+            .agent_model_id("mock-model".to_string())
+            .agent_llm_url("http://127.0.0.1:9/v1/chat/completions".to_string())
+            .agent_http_endpoint(url.clone())
+            .agent_ws_endpoint(format!("ws://{}", ws_addr))
+            .agent_system_prompt("You are a mock agent.".to_string())
+            .agent_discoverable(false)
+            .agent_skill_id(self.skill_id.clone())
+            .agent_skill_name(self.skill_id.clone())
+            .agent_skill_description(format!("Mock skill {}", self.skill_id))
+            .agent_version("1.0.0".to_string())
+            .agent_doc_url("/docs".to_string())
+            .agent_tags(vec![self.skill_id.clone()])
+            .agent_examples(vec![])
+            .build()?;
+
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let agent = MockAgent {
+            script: Arc::new(self.script.clone()),
+            requests: requests.clone(),
+        };
+
+        let server = tokio::spawn(async move {
+            let result = match AgentServer::<MockAgent>::new(agent_config, agent, None).await {
+                Ok(server) => server.start_http().await.map_err(|e| anyhow::anyhow!("{}", e)),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                debug!("Mock A2A agent on {} stopped: {}", http_addr, e);
+            }
+        });
+        if let Err(e) = wait_until_serving(http_addr, &server).await {
+            server.abort();
+            return Err(e);
+        }
+        debug!("Mock A2A agent '{}' listening on {}", self.id, url);
+
+        Ok(RunningMockA2aAgent {
+            id: self.id.clone(),
+            is_default: self.is_default,
+            addr: http_addr,
+            requests,
+            server,
+        })
+    }
+}
+
+/// A started `MockA2aAgent`. The agent stops when this handle is dropped.
+pub struct RunningMockA2aAgent {
+    id: String,
+    is_default: bool,
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+    server: JoinHandle<()>,
+}
+
+impl RunningMockA2aAgent {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Reference to give to an `A2AAgentInvoker`.
+    pub fn agent_reference(&self) -> AgentReference {
+        AgentReference {
+            id: self.id.clone(),
+            url: self.url(),
+            is_default: self.is_default.then_some(true),
+        }
+    }
+
+    /// User queries received so far, in order of arrival.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for RunningMockA2aAgent {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[derive(Clone)]
+struct MockAgent {
+    script: Arc<Script>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Agent for MockAgent {
+    async fn new(
+        agent_config: AgentConfig,
+        _agent_api_key: String,
+        _mcp_runtime_details: Option<McpRuntimeDetails>,
+        _evaluation_service: Option<Arc<dyn EvaluationService>>,
+        _memory_service: Option<Arc<dyn MemoryService>>,
+        _discovery_service: Option<Arc<dyn DiscoveryService>>,
+        _workflow_service: Option<Arc<dyn WorkflowServiceApi>>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            script: Arc::new(Script::new(&agent_config.agent_id())),
+            requests: Arc::new(Mutex::new(Vec::new())),
+        })
+    }
+
+    async fn handle_request(&self, request: AgentRequest) -> anyhow::Result<ExecutionResult> {
+        let user_query = request.user_query();
+        let request_number = {
+            let mut requests = self.requests.lock().unwrap();
+            requests.push(user_query.clone());
+            requests.len()
+        };
+
+        if let Some(delay) = self.script.delay {
+            tokio::time::sleep(delay).await;
+        }
+        if request_number <= self.script.failures_before_success {
+            anyhow::bail!("Injected failure {} of {}", request_number, self.script.failures_before_success);
+        }
+
+        let reply = self
+            .script
+            .rules
+            .iter()
+            .find(|rule| user_query.contains(&rule.contains))
+            .map(|rule| &rule.reply)
+            .unwrap_or(&self.script.default_reply);
+
+        match reply {
+            Reply::Answer(output) => Ok(ExecutionResult {
+                request_id: uuid::Uuid::new_v4().to_string(),
+                conversation_id: uuid::Uuid::new_v4().to_string(),
+                success: true,
+                output: output.clone(),
+            }),
+            Reply::Fail(message) => anyhow::bail!("{}", message),
+        }
+    }
+}
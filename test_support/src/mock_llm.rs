@@ -0,0 +1,141 @@
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+#[derive(Clone, Debug)]
+enum Reply {
+    Completion(Value),
+    /// Chunks sent as server-sent events, followed by `[DONE]`.
+    Stream(Vec<Value>),
+}
+
+/// Builder of a scriptable chat-completions endpoint, answering each request with the next
+/// scripted reply, whatever the request.
+///
+/// ```ignore
+/// let llm = MockLlm::new()
+///     .with_completion(MockLlm::completion(json!({"role": "assistant", "content": "Hello"}), "stop"))
+///     .start()
+///     .await?;
+/// let config = McpRuntimeConfig { agent_mcp_llm_url: llm.url(), ..mcp_server.runtime_config() };
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MockLlm {
+    replies: Vec<Reply>,
+}
+
+impl MockLlm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replies with a whole chat completion, see `MockLlm::completion`.
+    pub fn with_completion(mut self, completion: Value) -> Self {
+        self.replies.push(Reply::Completion(completion));
+        self
+    }
+
+    /// Replies with a streamed chat completion made of `chunks`, see `MockLlm::chunk`.
+    pub fn with_stream(mut self, chunks: Vec<Value>) -> Self {
+        self.replies.push(Reply::Stream(chunks));
+        self
+    }
+
+    /// A chat completion whose only choice holds `message`.
+    pub fn completion(message: Value, finish_reason: &str) -> Value {
+        json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "mock-model",
+            "choices": [{ "index": 0, "message": message, "finish_reason": finish_reason }],
+            "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 }
+        })
+    }
+
+    /// A chunk of a streamed chat completion carrying `delta`.
+    pub fn chunk(delta: Value, finish_reason: Option<&str>) -> Value {
+        json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "mock-model",
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }]
+        })
+    }
+
+    /// A tool call, as found in the `tool_calls` of an assistant message.
+    pub fn tool_call(id: &str, name: &str, arguments: Value) -> Value {
+        json!({ "id": id, "type": "function", "function": { "name": name, "arguments": arguments.to_string() } })
+    }
+
+    pub async fn start(self) -> anyhow::Result<RunningMockLlm> {
+        let replies = Arc::new(Mutex::new(VecDeque::from(self.replies)));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+
+        let handler = move |axum::Json(request): axum::Json<Value>| {
+            received.lock().unwrap().push(request);
+            let reply = replies.lock().unwrap().pop_front();
+            async move { respond(reply) }
+        };
+        let app = axum::Router::new().route("/v1/chat/completions", axum::routing::post(handler));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                debug!("Mock LLM on {} stopped: {}", addr, e);
+            }
+        });
+        debug!("Mock LLM listening on {}", addr);
+
+        Ok(RunningMockLlm { addr, requests, server })
+    }
+}
+
+fn respond(reply: Option<Reply>) -> Response {
+    match reply {
+        Some(Reply::Completion(completion)) => axum::Json(completion).into_response(),
+        Some(Reply::Stream(chunks)) => {
+            let mut body: String = chunks.iter().map(|chunk| format!("data: {}\n\n", chunk)).collect();
+            body.push_str("data: [DONE]\n\n");
+            ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response()
+        }
+        None => (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            "No scripted reply left in the mock LLM",
+        )
+            .into_response(),
+    }
+}
+
+/// A started `MockLlm`. The endpoint stops when this handle is dropped.
+pub struct RunningMockLlm {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Value>>>,
+    server: JoinHandle<()>,
+}
+
+impl RunningMockLlm {
+    /// URL of the chat-completions endpoint, to use as `agent_mcp_llm_url`.
+    pub fn url(&self) -> String {
+        format!("http://{}/v1/chat/completions", self.addr)
+    }
+
+    /// Bodies of the requests received so far, in order of arrival.
+    pub fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for RunningMockLlm {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
@@ -0,0 +1,246 @@
+use rmcp::{
+    ErrorData as McpError, RoleServer, ServerHandler,
+    model::*,
+    service::RequestContext,
+    transport::streamable_http_server::{
+        StreamableHttpServerConfig, StreamableHttpService, session::local::LocalSessionManager,
+    },
+};
+
+use configuration::McpRuntimeConfig;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Computes the response of a tool from its arguments. An `Err` is returned to the client
+/// as a tool result flagged as an error.
+pub type ToolHandler = Arc<dyn Fn(Value) -> Result<Value, String> + Send + Sync>;
+
+#[derive(Clone)]
+struct MockTool {
+    tool: Tool,
+    handler: ToolHandler,
+    delay: Option<Duration>,
+}
+
+/// A tool call received by a `RunningMockMcpServer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReceivedToolCall {
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// Builder of a scriptable MCP server speaking streamable HTTP.
+///
+/// ```ignore
+/// let server = MockMcpServer::new()
+///     .with_tool("get_weather", "Weather of a city", json!({"properties": {"city": {"type": "string"}}}), json!("sunny"))
+///     .start()
+///     .await?;
+/// let invoker = McpRuntimeToolInvoker::from_config(server.runtime_config()).await?;
+/// ```
+#[derive(Clone, Default)]
+pub struct MockMcpServer {
+    tools: Vec<MockTool>,
+}
+
+impl MockMcpServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares a tool always answering `response`.
+    pub fn with_tool(self, name: &str, description: &str, input_schema: Value, response: Value) -> Self {
+        self.with_tool_fn(name, description, input_schema, move |_| Ok(response.clone()))
+    }
+
+    /// Declares a tool answering with `handler`, called with the arguments of each call.
+    pub fn with_tool_fn(
+        mut self,
+        name: &str,
+        description: &str,
+        input_schema: Value,
+        handler: impl Fn(Value) -> Result<Value, String> + Send + Sync + 'static,
+    ) -> Self {
+        let mut schema = match input_schema {
+            Value::Object(schema) => schema,
+            _ => JsonObject::new(),
+        };
+        schema
+            .entry("type")
+            .or_insert_with(|| Value::String("object".to_string()));
+
+        self.tools.push(MockTool {
+            tool: Tool::new(name.to_string(), description.to_string(), Arc::new(schema)),
+            handler: Arc::new(handler),
+            delay: None,
+        });
+        self
+    }
+
+    /// Waits `delay` before answering each call of the tool `name`, declared beforehand.
+    pub fn with_delay(mut self, name: &str, delay: Duration) -> Self {
+        for mock in self.tools.iter_mut().filter(|mock| mock.tool.name == name) {
+            mock.delay = Some(delay);
+        }
+        self
+    }
+
+    pub async fn start(self) -> anyhow::Result<RunningMockMcpServer> {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let concurrency = Arc::new(CallConcurrency::default());
+        let service = MockMcpService {
+            tools: Arc::new(self.tools),
+            calls: calls.clone(),
+            concurrency: concurrency.clone(),
+        };
+
+        let http_service = StreamableHttpService::new(
+            move || Ok(service.clone()),
+            Arc::new(LocalSessionManager::default()),
+            StreamableHttpServerConfig::default(),
+        );
+        let handler = move |req: axum::extract::Request| {
+            let svc = http_service.clone();
+            async move { svc.handle(req).await }
+        };
+        let app = axum::Router::new().route("/mcp", axum::routing::any(handler));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                debug!("Mock MCP server on {} stopped: {}", addr, e);
+            }
+        });
+        debug!("Mock MCP server listening on {}", addr);
+
+        Ok(RunningMockMcpServer {
+            addr,
+            calls,
+            concurrency,
+            server,
+        })
+    }
+}
+
+/// A started `MockMcpServer`. The server stops when this handle is dropped.
+pub struct RunningMockMcpServer {
+    addr: SocketAddr,
+    calls: Arc<Mutex<Vec<ReceivedToolCall>>>,
+    concurrency: Arc<CallConcurrency>,
+    server: JoinHandle<()>,
+}
+
+impl RunningMockMcpServer {
+    pub fn url(&self) -> String {
+        format!("http://{}/mcp", self.addr)
+    }
+
+    /// Tool calls received so far, in order of arrival.
+    pub fn calls(&self) -> Vec<ReceivedToolCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Largest number of tool calls answered at the same time so far.
+    pub fn max_concurrent_calls(&self) -> usize {
+        self.concurrency.peak.load(Ordering::SeqCst)
+    }
+
+    /// MCP runtime configuration pointing to this server. The LLM settings are placeholders,
+    /// to be overridden by tests that run an `McpAgent`.
+    pub fn runtime_config(&self) -> McpRuntimeConfig {
+        McpRuntimeConfig {
+            agent_mcp_role_tool: "tool".to_string(),
+            agent_mcp_role_assistant: "assistant".to_string(),
+            agent_mcp_tool_choice_auto: "auto".to_string(),
+            agent_mcp_finish_reason_tool_calls: "tool_calls".to_string(),
+            agent_mcp_finish_reason_stop: "stop".to_string(),
+            agent_mcp_max_loops: 5,
+            agent_mcp_server_url: Some(self.url()),
+            agent_mcp_server_api_key: None,
+            agent_mcp_sanitizer_model_id: None,
+            agent_mcp_model_id: "mock-model".to_string(),
+            agent_mcp_llm_url: "http://127.0.0.1:9/v1/chat/completions".to_string(),
+            agent_mcp_llm_api_key_env_var: None,
+            agent_mcp_system_prompt: "You are a helpful assistant.".to_string(),
+            agent_mcp_evaluation_prompt: String::new(),
+            agent_mcp_correction_prompt: String::new(),
+            agent_mcp_endpoint_url: None,
+            agent_mcp_nats_url: None,
+            agent_mcp_nats_dispatch_subject: None,
+            agent_mcp_enable_evaluation: None,
+        }
+    }
+}
+
+impl Drop for RunningMockMcpServer {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[derive(Default)]
+struct CallConcurrency {
+    running: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+#[derive(Clone)]
+struct MockMcpService {
+    tools: Arc<Vec<MockTool>>,
+    calls: Arc<Mutex<Vec<ReceivedToolCall>>>,
+    concurrency: Arc<CallConcurrency>,
+}
+
+impl ServerHandler for MockMcpService {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo::new(ServerCapabilities::builder().enable_tools().build())
+            .with_instructions("Mock MCP server used in tests.")
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(
+            self.tools.iter().map(|mock| mock.tool.clone()).collect(),
+        ))
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let arguments = Value::Object(request.arguments.clone().unwrap_or_default());
+        self.calls.lock().unwrap().push(ReceivedToolCall {
+            name: request.name.to_string(),
+            arguments: arguments.clone(),
+        });
+
+        let mock = self
+            .tools
+            .iter()
+            .find(|mock| mock.tool.name == request.name)
+            .ok_or_else(|| McpError::invalid_params(format!("Unknown tool '{}'", request.name), None))?;
+
+        let running = self.concurrency.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.concurrency.peak.fetch_max(running, Ordering::SeqCst);
+        if let Some(delay) = mock.delay {
+            tokio::time::sleep(delay).await;
+        }
+        self.concurrency.running.fetch_sub(1, Ordering::SeqCst);
+
+        Ok(match (mock.handler)(arguments) {
+            Ok(Value::String(text)) => CallToolResult::success(vec![Content::text(text)]),
+            Ok(value) => CallToolResult::success(vec![Content::text(value.to_string())]),
+            Err(message) => CallToolResult::error(vec![Content::text(message)]),
+        })
+    }
+}
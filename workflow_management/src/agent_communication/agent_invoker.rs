@@ -1,10 +1,26 @@
 use async_trait::async_trait;
 use std::any::Any;
 
+/// Answer of a delegation, with the agent that actually gave it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentAnswer {
+    pub agent_id: String,
+    pub response: serde_json::Value,
+}
+
 #[async_trait]
 pub trait AgentInvoker: Send + Sync + 'static {
     /// Interacts with an agent with the given ID, sending a message.
     /// The concrete implementation handles the agent-specific communication.
+    /// An empty `agent_id` lets the implementation choose an agent offering `skill`.
     async fn interact(&self, agent_id: String, message: String,skill:String) -> anyhow::Result<serde_json::Value>;
+
+    /// Same as `interact`, also telling which agent answered. Implementations that may pick
+    /// another agent than `agent_id` override it.
+    async fn delegate(&self, agent_id: String, message: String, skill: String) -> anyhow::Result<AgentAnswer> {
+        let response = self.interact(agent_id.clone(), message, skill).await?;
+        Ok(AgentAnswer { agent_id, response })
+    }
+
     fn as_any(&self) -> &dyn Any; // Added for downcasting
 }
\ No newline at end of file
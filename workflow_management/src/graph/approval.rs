@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+use crate::graph::recording::RecordedApproval;
+
+/// Human decision on an activity waiting for approval, e.g. `{"decision": "reject", "reason": "wrong customer"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approve,
+    Reject {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Approve, but run the activity with `parameters` instead of the proposed ones.
+    Edit { parameters: Value },
+}
+
+/// An activity suspended until a human decides whether it may run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingApproval {
+    /// Run of the plan the activity belongs to: a sub-workflow runs under its own run id.
+    pub run_id: String,
+    pub node_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// Interpolated parameters the activity is about to run with: the tool parameters, the
+    /// agent context, or the array of task parameters, depending on the activity type.
+    pub parameters: Value,
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ApprovalError {
+    #[error("No approval pending for node '{1}' of run '{0}'")]
+    NotPending(String, String),
+    #[error("An approval is already pending for node '{1}' of run '{0}'")]
+    AlreadyPending(String, String),
+}
+
+/// Pending approvals, keyed by run id and node id.
+type PendingApprovals = HashMap<(String, String), (PendingApproval, oneshot::Sender<ApprovalDecision>)>;
+
+/// Handle used to answer the approvals requested by a running plan, and by its sub-workflows,
+/// from outside the executor.
+#[derive(Clone, Default)]
+pub struct PlanApprovalHandle {
+    pending: Arc<Mutex<PendingApprovals>>,
+    /// Decisions taken beforehand when replaying a run, by node id. None outside of replays.
+    recorded: Option<Arc<Mutex<HashMap<String, VecDeque<ApprovalDecision>>>>>,
+}
+
+impl PlanApprovalHandle {
+    /// Approvals the plan is currently waiting for.
+    pub fn pending(&self) -> Vec<PendingApproval> {
+        let mut pending = self.pending.lock().unwrap();
+        // Requests of a cancelled or finished plan can no longer be answered.
+        pending.retain(|_, (_, sender)| !sender.is_closed());
+        pending.values().map(|(approval, _)| approval.clone()).collect()
+    }
+
+    /// Resumes the activity `node_id` of the run `run_id` with `decision`.
+    pub fn decide(&self, run_id: &str, node_id: &str, decision: ApprovalDecision) -> Result<(), ApprovalError> {
+        let not_pending = || ApprovalError::NotPending(run_id.to_string(), node_id.to_string());
+        let (_, sender) = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&(run_id.to_string(), node_id.to_string()))
+            .ok_or_else(not_pending)?;
+        sender.send(decision).map_err(|_| not_pending())
+    }
+
+    /// Handle answering every approval with the decision recorded for its node, in order.
+    /// Approvals without a recorded decision are rejected rather than waited for.
+    pub(crate) fn replaying(approvals: &[RecordedApproval]) -> Self {
+        let mut recorded: HashMap<String, VecDeque<ApprovalDecision>> = HashMap::new();
+        for approval in approvals {
+            recorded
+                .entry(approval.node_id.clone())
+                .or_default()
+                .push_back(approval.decision.clone());
+        }
+        Self {
+            pending: Arc::default(),
+            recorded: Some(Arc::new(Mutex::new(recorded))),
+        }
+    }
+
+    pub(crate) fn request(
+        &self,
+        approval: PendingApproval,
+    ) -> Result<oneshot::Receiver<ApprovalDecision>, ApprovalError> {
+        if let Some(recorded) = &self.recorded {
+            let decision = recorded
+                .lock()
+                .unwrap()
+                .get_mut(&approval.node_id)
+                .and_then(VecDeque::pop_front)
+                .unwrap_or(ApprovalDecision::Reject {
+                    reason: Some("no decision recorded for this approval".to_string()),
+                });
+            let (sender, receiver) = oneshot::channel();
+            let _ = sender.send(decision);
+            return Ok(receiver);
+        }
+
+        let mut pending = self.pending.lock().unwrap();
+        let key = (approval.run_id.clone(), approval.node_id.clone());
+        if pending.get(&key).is_some_and(|(_, sender)| !sender.is_closed()) {
+            return Err(ApprovalError::AlreadyPending(key.0, key.1));
+        }
+        let (sender, receiver) = oneshot::channel();
+        pending.insert(key, (approval, sender));
+        Ok(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(run_id: &str, node_id: &str) -> PendingApproval {
+        PendingApproval {
+            run_id: run_id.to_string(),
+            node_id: node_id.to_string(),
+            prompt: None,
+            parameters: Value::Null,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approvals_are_scoped_by_run() {
+        let approvals = PlanApprovalHandle::default();
+        let parent = approvals.request(pending("parent", "update")).unwrap();
+        let child = approvals.request(pending("child", "update")).unwrap();
+        assert_eq!(
+            approvals.request(pending("child", "update")).unwrap_err(),
+            ApprovalError::AlreadyPending("child".to_string(), "update".to_string())
+        );
+        assert_eq!(approvals.pending().len(), 2);
+
+        approvals
+            .decide("child", "update", ApprovalDecision::Reject { reason: None })
+            .unwrap();
+        approvals.decide("parent", "update", ApprovalDecision::Approve).unwrap();
+        assert_eq!(child.await.unwrap(), ApprovalDecision::Reject { reason: None });
+        assert_eq!(parent.await.unwrap(), ApprovalDecision::Approve);
+        assert_eq!(
+            approvals.decide("parent", "update", ApprovalDecision::Approve),
+            Err(ApprovalError::NotPending("parent".to_string(), "update".to_string()))
+        );
+    }
+}
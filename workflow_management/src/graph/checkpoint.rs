@@ -0,0 +1,216 @@
+use agent_models::graph::graph_definition::Graph;
+
+use crate::graph::execution_policy::{ExecutionPolicies, NodeFailure};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+const PLAN_CHECKPOINTS: TableDefinition<&str, &str> = TableDefinition::new("plan_checkpoints");
+
+/// Lifecycle of a checkpointed plan run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CheckpointStatus {
+    Running,
+    Completed,
+    Failed(String),
+}
+
+/// Snapshot of a `PlanExecutor` run, persisted after every completed node.
+/// The graph is kept alongside the outcomes so a run can be resumed from its id alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanCheckpoint {
+    pub run_id: String,
+    pub graph: Value,
+    pub user_query: String,
+    pub status: CheckpointStatus,
+    #[serde(default)]
+    pub execution_policies: ExecutionPolicies,
+    pub current_step_id: Option<String>,
+    pub activities_outcome: HashMap<String, String>,
+    #[serde(default)]
+    pub node_failures: HashMap<String, NodeFailure>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PlanCheckpoint {
+    pub fn graph(&self) -> anyhow::Result<Graph> {
+        Ok(serde_json::from_value(self.graph.clone())?)
+    }
+}
+
+#[async_trait]
+pub trait CheckpointStore: Send + Sync {
+    /// Inserts or replaces the checkpoint stored under `checkpoint.run_id`.
+    async fn save(&self, checkpoint: &PlanCheckpoint) -> anyhow::Result<()>;
+    async fn load(&self, run_id: &str) -> anyhow::Result<Option<PlanCheckpoint>>;
+    async fn delete(&self, run_id: &str) -> anyhow::Result<()>;
+}
+
+/// Non-durable store, useful for tests and single-process deployments.
+#[derive(Default)]
+pub struct InMemoryCheckpointStore {
+    checkpoints: RwLock<HashMap<String, PlanCheckpoint>>,
+}
+
+impl InMemoryCheckpointStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for InMemoryCheckpointStore {
+    async fn save(&self, checkpoint: &PlanCheckpoint) -> anyhow::Result<()> {
+        self.checkpoints
+            .write()
+            .unwrap()
+            .insert(checkpoint.run_id.clone(), checkpoint.clone());
+        Ok(())
+    }
+
+    async fn load(&self, run_id: &str) -> anyhow::Result<Option<PlanCheckpoint>> {
+        Ok(self.checkpoints.read().unwrap().get(run_id).cloned())
+    }
+
+    async fn delete(&self, run_id: &str) -> anyhow::Result<()> {
+        self.checkpoints.write().unwrap().remove(run_id);
+        Ok(())
+    }
+}
+
+/// Durable store backed by an embedded redb database file.
+/// Checkpoints are stored as JSON documents keyed by run id.
+#[derive(Clone)]
+pub struct RedbCheckpointStore {
+    db: Arc<Database>,
+}
+
+impl RedbCheckpointStore {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let db = Database::create(path)?;
+        Ok(Self { db: Arc::new(db) })
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for RedbCheckpointStore {
+    async fn save(&self, checkpoint: &PlanCheckpoint) -> anyhow::Result<()> {
+        let db = self.db.clone();
+        let run_id = checkpoint.run_id.clone();
+        let document = serde_json::to_string(checkpoint)?;
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            let write_txn = db.begin_write()?;
+            {
+                let mut table = write_txn.open_table(PLAN_CHECKPOINTS)?;
+                table.insert(run_id.as_str(), document.as_str())?;
+            }
+            write_txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn load(&self, run_id: &str) -> anyhow::Result<Option<PlanCheckpoint>> {
+        let db = self.db.clone();
+        let run_id = run_id.to_string();
+
+        let document = tokio::task::spawn_blocking(move || -> anyhow::Result<Option<String>> {
+            let read_txn = db.begin_read()?;
+            let table = match read_txn.open_table(PLAN_CHECKPOINTS) {
+                Ok(table) => table,
+                Err(redb::TableError::TableDoesNotExist(_)) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            let document = table.get(run_id.as_str())?.map(|guard| guard.value().to_string());
+            Ok(document)
+        })
+        .await??;
+
+        match document {
+            Some(document) => Ok(Some(serde_json::from_str(&document)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn delete(&self, run_id: &str) -> anyhow::Result<()> {
+        let db = self.db.clone();
+        let run_id = run_id.to_string();
+
+        tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            let write_txn = db.begin_write()?;
+            {
+                let mut table = write_txn.open_table(PLAN_CHECKPOINTS)?;
+                table.remove(run_id.as_str())?;
+            }
+            write_txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn checkpoint(run_id: &str, status: CheckpointStatus) -> PlanCheckpoint {
+        PlanCheckpoint {
+            run_id: run_id.to_string(),
+            graph: json!({ "nodes": {}, "edges": [] }),
+            user_query: "test".to_string(),
+            status,
+            execution_policies: ExecutionPolicies::default(),
+            current_step_id: Some("a".to_string()),
+            activities_outcome: HashMap::from([("a".to_string(), "{\"ok\":true}".to_string())]),
+            node_failures: HashMap::new(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    async fn assert_round_trip(store: &dyn CheckpointStore) {
+        assert!(store.load("run").await.unwrap().is_none());
+
+        store.save(&checkpoint("run", CheckpointStatus::Running)).await.unwrap();
+        store
+            .save(&checkpoint("run", CheckpointStatus::Failed("boom".to_string())))
+            .await
+            .unwrap();
+        let loaded = store.load("run").await.unwrap().unwrap();
+        assert_eq!(loaded.status, CheckpointStatus::Failed("boom".to_string()));
+        assert_eq!(loaded.graph, json!({ "nodes": {}, "edges": [] }));
+        assert_eq!(loaded.activities_outcome["a"], "{\"ok\":true}");
+        assert_eq!(loaded.current_step_id.as_deref(), Some("a"));
+
+        store.delete("run").await.unwrap();
+        assert!(store.load("run").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_store_round_trip() {
+        assert_round_trip(&InMemoryCheckpointStore::new()).await;
+    }
+
+    #[tokio::test]
+    async fn test_redb_store_round_trip_and_reopen() {
+        let path = std::env::temp_dir().join(format!("checkpoints-{}.redb", uuid::Uuid::new_v4()));
+        let store = RedbCheckpointStore::open(&path).unwrap();
+        assert_round_trip(&store).await;
+
+        store.save(&checkpoint("kept", CheckpointStatus::Completed)).await.unwrap();
+        drop(store);
+        let reopened = RedbCheckpointStore::open(&path).unwrap();
+        let loaded = reopened.load("kept").await.unwrap().unwrap();
+        assert_eq!(loaded.status, CheckpointStatus::Completed);
+
+        drop(reopened);
+        let _ = std::fs::remove_file(&path);
+    }
+}
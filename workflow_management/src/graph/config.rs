@@ -1,6 +1,8 @@
 //use agent_core::graph::graph_definition::{ Graph,  WorkflowPlanInput};
 use agent_models::graph::graph_definition::{ Graph,  WorkflowPlanInput};
 
+use crate::graph::execution_policy::ExecutionPolicies;
+
 use std::fs;
 use thiserror::Error;
 
@@ -20,3 +22,13 @@ pub fn load_graph_from_file(file_path: &str) -> Result<Graph, ConfigurationError
     // Use the From trait implementation to convert WorkflowPlanInput to Graph
     Ok(workflow.into())
 }
+
+/// Loads a workflow file together with the execution settings declared on its activities.
+pub fn load_workflow_from_file(file_path: &str) -> Result<(Graph, ExecutionPolicies), ConfigurationError> {
+    let content = fs::read_to_string(file_path)?;
+    let document: serde_json::Value = serde_json::from_str(&content)?;
+    let policies = ExecutionPolicies::from_workflow_json(&document)?;
+    let workflow: WorkflowPlanInput = serde_json::from_value(document)?;
+
+    Ok((workflow.into(), policies))
+}
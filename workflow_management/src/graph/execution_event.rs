@@ -0,0 +1,89 @@
+use crate::graph::execution_policy::OnErrorStrategy;
+
+use serde::{Deserialize, Serialize};
+
+/// Progress notification emitted by a `PlanExecutor` while a plan runs.
+/// Obtain a stream of them with `PlanExecutor::subscribe_events`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExecutionEvent {
+    PlanStarted {
+        run_id: String,
+        node_count: usize,
+    },
+    NodeStarted {
+        run_id: String,
+        node_id: String,
+    },
+    NodeSucceeded {
+        run_id: String,
+        node_id: String,
+        result: String,
+        duration_ms: u64,
+        attempts: usize,
+    },
+    NodeFailed {
+        run_id: String,
+        node_id: String,
+        error: String,
+        duration_ms: u64,
+        attempts: usize,
+        /// The `on_error` strategy applied to the failure.
+        strategy: OnErrorStrategy,
+    },
+    /// The node waits for a human decision, see `PlanExecutor::approval_handle`.
+    ApprovalRequested {
+        run_id: String,
+        node_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        prompt: Option<String>,
+        parameters: serde_json::Value,
+    },
+    ApprovalResolved {
+        run_id: String,
+        node_id: String,
+        approved: bool,
+    },
+    /// The agent that answered a delegation of the node, which may differ from its preferred agent.
+    AgentChosen {
+        run_id: String,
+        node_id: String,
+        agent_id: String,
+        skill: String,
+    },
+    /// An edge was not followed because its condition evaluated to false.
+    EdgeSkipped {
+        run_id: String,
+        source: String,
+        target: String,
+        condition: String,
+    },
+    PlanFinished {
+        run_id: String,
+        success: bool,
+        duration_ms: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+impl ExecutionEvent {
+    pub fn run_id(&self) -> &str {
+        match self {
+            ExecutionEvent::PlanStarted { run_id, .. }
+            | ExecutionEvent::NodeStarted { run_id, .. }
+            | ExecutionEvent::NodeSucceeded { run_id, .. }
+            | ExecutionEvent::NodeFailed { run_id, .. }
+            | ExecutionEvent::ApprovalRequested { run_id, .. }
+            | ExecutionEvent::ApprovalResolved { run_id, .. }
+            | ExecutionEvent::AgentChosen { run_id, .. }
+            | ExecutionEvent::EdgeSkipped { run_id, .. }
+            | ExecutionEvent::PlanFinished { run_id, .. } => run_id,
+        }
+    }
+
+    /// True for the last event of a run.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, ExecutionEvent::PlanFinished { .. })
+    }
+}
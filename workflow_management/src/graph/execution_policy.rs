@@ -0,0 +1,443 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Category of an invoker failure, used to decide whether an attempt is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    RateLimited,
+    Timeout,
+    Connection,
+    Server,
+    Other,
+}
+
+impl ErrorKind {
+    /// Best-effort classification of an invoker error message.
+    /// Invokers report errors through `anyhow`, so the message is all we have.
+    pub fn classify(message: &str) -> Self {
+        let message = message.to_lowercase();
+        if message.contains("429") || message.contains("rate limit") || message.contains("rate_limit") {
+            ErrorKind::RateLimited
+        } else if message.contains("timed out") || message.contains("timeout") || message.contains("deadline") {
+            ErrorKind::Timeout
+        } else if message.contains("connect")
+            || message.contains("broken pipe")
+            || message.contains("transport")
+        {
+            ErrorKind::Connection
+        } else if ["500", "502", "503", "504", "internal server error", "service unavailable"]
+            .iter()
+            .any(|pattern| message.contains(pattern))
+        {
+            ErrorKind::Server
+        } else {
+            ErrorKind::Other
+        }
+    }
+}
+
+/// Retry policy declared on an activity with `"retry_policy": { ... }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    #[serde(default = "RetryPolicy::default_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "RetryPolicy::default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "RetryPolicy::default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    #[serde(default = "RetryPolicy::default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    /// Error kinds worth retrying. Every kind is retried when empty.
+    #[serde(default)]
+    pub retry_on: Vec<ErrorKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: Self::default_max_attempts(),
+            initial_backoff_ms: Self::default_initial_backoff_ms(),
+            backoff_multiplier: Self::default_backoff_multiplier(),
+            max_backoff_ms: Self::default_max_backoff_ms(),
+            retry_on: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn default_max_attempts() -> u32 {
+        3
+    }
+
+    fn default_initial_backoff_ms() -> u64 {
+        500
+    }
+
+    fn default_backoff_multiplier() -> f64 {
+        2.0
+    }
+
+    fn default_max_backoff_ms() -> u64 {
+        30_000
+    }
+
+    pub fn is_retryable(&self, kind: ErrorKind) -> bool {
+        self.retry_on.is_empty() || self.retry_on.contains(&kind)
+    }
+
+    /// Delay to wait after the failed attempt number `attempt` (starting at 1).
+    pub fn backoff_after(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let delay_ms = self.initial_backoff_ms as f64 * self.backoff_multiplier.max(1.0).powi(exponent);
+        Duration::from_millis(delay_ms.min(self.max_backoff_ms as f64) as u64)
+    }
+}
+
+/// Execution settings of a single activity, read from the fields that sit next to the
+/// activity definition in a workflow document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActivityPolicy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_policy: Option<RetryPolicy>,
+    /// Maximum duration of a single attempt, declared as `"timeout_ms"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// What to do when the activity still fails after its retries, declared as `"on_error"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_error: Option<OnErrorStrategy>,
+    /// Lets the activity run even when one of its dependencies failed under the
+    /// `continue` strategy. The failed dependency's outcome is `{"error": "..."}`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tolerates_failed_dependencies: bool,
+    /// Activity undoing this one, run when a later node fails with the `compensate` strategy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compensation: Option<CompensationAction>,
+    /// How the tasks of a `DirectTaskExecution` activity are chained, declared as `"task_execution"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_execution: Option<TaskExecutionMode>,
+    /// Runs the activity once per element of an array, declared as `"for_each"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub for_each: Option<ForEachPolicy>,
+    /// Runs the activity repeatedly while or until a condition holds, declared as `"loop"`.
+    /// Ignored when `for_each` is set.
+    #[serde(default, rename = "loop", skip_serializing_if = "Option::is_none")]
+    pub repeat: Option<LoopPolicy>,
+    /// Runs another workflow as a child plan instead of the activity itself, declared as `"sub_workflow"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_workflow: Option<SubWorkflowPolicy>,
+    /// Suspends the plan before the activity runs until a human approves it, declared as `"approval"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<ApprovalPolicy>,
+}
+
+/// Human approval required before an activity runs, e.g. `"approval": {"prompt": "Update the customer record?"}`.
+///
+/// The pending decision is published as an `ApprovalRequested` event and answered through the
+/// `PlanApprovalHandle` of the executor. A rejection fails the activity, which is then handled
+/// by its `on_error` strategy.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+/// Child plan run by an activity, e.g.
+/// `"sub_workflow": {"name": "enrich_customer", "inputs": {"customer": "{{lookup.customer}}"}}`.
+///
+/// The child plan has its own context and sees `inputs`, once interpolated, as `{{input}}`,
+/// e.g. `{{input.customer}}`. Its final outcome is the outcome of the activity.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubWorkflowPolicy {
+    #[serde(flatten)]
+    pub workflow: WorkflowReference,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub inputs: Value,
+    /// User query of the child plan, which may hold placeholders. Defaults to the parent's one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_query: Option<String>,
+}
+
+/// Where a sub-workflow comes from: `{"path": "workflows/enrich.json"}` or `{"name": "enrich_customer"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkflowReference {
+    /// Workflow file, relative to the base directory of the `WorkflowRegistry`.
+    Path(String),
+    /// Workflow registered in the `WorkflowRegistry` under this name.
+    Name(String),
+}
+
+/// Map over a JSON array, e.g. `"for_each": {"items": "{{list_orders.orders}}", "max_concurrency": 4}`.
+///
+/// Parameters of the activity may reference the current element with `{{item}}`, or
+/// `{{item.field}}`, and its position with `{{item_index}}`. The activity outcome is the
+/// array of the outputs of every element, in the order of `items`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForEachPolicy {
+    /// Placeholder resolving to an array, or an array literal.
+    pub items: Value,
+    /// Number of elements processed at the same time.
+    #[serde(default = "ForEachPolicy::default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+impl ForEachPolicy {
+    fn default_max_concurrency() -> usize {
+        1
+    }
+}
+
+/// Bounded repetition, e.g. `"loop": {"until": "poll_job.status == 'done'", "max_iterations": 20, "delay_ms": 1000}`.
+///
+/// Conditions are evaluated after each iteration with the condition evaluator, against the
+/// outcomes of the plan, the output of the latest iteration as `result` (or under the
+/// activity id) and `iteration`. Parameters may reference `{{iteration}}` (starting at 1) and `{{previous}}`,
+/// the output of the previous iteration.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoopPolicy {
+    /// Keeps iterating while this condition is true.
+    #[serde(default, rename = "while", skip_serializing_if = "Option::is_none")]
+    pub while_condition: Option<String>,
+    /// Stops iterating once this condition is true.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    /// Hard bound on the number of iterations. The last output is kept when it is reached.
+    /// Without any condition, the activity runs exactly this many times.
+    #[serde(default = "LoopPolicy::default_max_iterations")]
+    pub max_iterations: u32,
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+impl LoopPolicy {
+    fn default_max_iterations() -> u32 {
+        10
+    }
+}
+
+/// Execution of the tasks listed in a `DirectTaskExecution` activity.
+///
+/// With several tasks, the activity outcome is a JSON object holding each task output
+/// under its task id. A single task keeps its raw output as the activity outcome.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskExecutionMode {
+    /// One task after the other. A task may reference the output of an earlier task of
+    /// the same activity with `{{task_id}}`, or `{{task_id.field}}`, in its parameters.
+    #[default]
+    Sequential,
+    /// All tasks at once, each one only seeing the outcomes of other activities.
+    Parallel,
+}
+
+/// Failure handling of an activity, e.g. `"on_error": {"strategy": "fallback", "node": "notify_ops"}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum OnErrorStrategy {
+    /// Fail the whole plan.
+    #[default]
+    Fail,
+    /// Mark the node as failed and keep going. Only dependents declaring
+    /// `tolerates_failed_dependencies` run; the others are skipped.
+    Continue,
+    /// Mark the node as failed and run `node` instead of its regular dependents.
+    /// The edge from the failing node to `node` is an error edge: it is only followed
+    /// on failure, and is implied when the graph does not declare it.
+    Fallback { node: String },
+    /// Run the `compensation` of every completed node, latest first, then fail the plan.
+    Compensate,
+}
+
+/// Direct invocation undoing the effect of a completed activity.
+/// Parameters may reference outcomes with `{{activity_id}}` placeholders.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CompensationAction {
+    Tool {
+        tool_to_use: String,
+        #[serde(default)]
+        tool_parameters: Value,
+    },
+    Task {
+        task_to_use: String,
+        #[serde(default)]
+        task_parameters: Value,
+    },
+    Agent {
+        agent_id: String,
+        message: String,
+        #[serde(default)]
+        skill_to_use: Option<String>,
+    },
+}
+
+/// How the executor handled a node that failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeFailure {
+    pub error: String,
+    pub strategy: OnErrorStrategy,
+    /// Nodes successfully compensated, in the order their compensations ran.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compensated: Vec<String>,
+    /// Compensations that failed, keyed by the node they were meant to undo.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub compensation_errors: HashMap<String, String>,
+    /// Nodes still running when the plan failed. They were aborted without being compensated,
+    /// although they may already have had side effects.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aborted: Vec<String>,
+}
+
+/// Execution settings of a workflow, keyed by activity id.
+///
+/// `WorkflowPlanInput` only carries the structure of the plan; these settings are read
+/// from the same JSON document and handed to the `PlanExecutor` separately.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionPolicies {
+    /// Deadline of the whole plan, declared at the top level of the workflow as `"plan_timeout_ms"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub activities: HashMap<String, ActivityPolicy>,
+}
+
+impl ExecutionPolicies {
+    /// Collects the settings declared on the `activities` of a workflow document.
+    pub fn from_workflow_json(workflow: &Value) -> Result<Self, serde_json::Error> {
+        let mut policies = Self {
+            plan_timeout_ms: workflow.get("plan_timeout_ms").and_then(Value::as_u64),
+            ..Self::default()
+        };
+        if let Some(activities) = workflow.get("activities").and_then(Value::as_array) {
+            for activity in activities {
+                let Some(id) = activity.get("id").and_then(Value::as_str) else {
+                    continue;
+                };
+                let policy: ActivityPolicy = serde_json::from_value(activity.clone())?;
+                if policy != ActivityPolicy::default() {
+                    policies.activities.insert(id.to_string(), policy);
+                }
+            }
+        }
+        Ok(policies)
+    }
+
+    pub fn for_activity(&self, activity_id: &str) -> Option<&ActivityPolicy> {
+        self.activities.get(activity_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plan_timeout_ms.is_none() && self.activities.is_empty()
+    }
+}
+
+/// One attempt at running an activity, kept in the plan's attempt history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityAttempt {
+    pub attempt: u32,
+    pub duration_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_kind: Option<ErrorKind>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_policies_from_workflow_json() {
+        let workflow = json!({
+            "plan_name": "retry",
+            "activities": [
+                {"id": "a", "activity_type": "direct_tool_use", "retry_policy": {"max_attempts": 5, "retry_on": ["timeout"]}},
+                {"id": "b", "activity_type": "direct_tool_use"}
+            ]
+        });
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let retry = policies.for_activity("a").and_then(|p| p.retry_policy.clone()).unwrap();
+        assert_eq!(retry.max_attempts, 5);
+        assert!(retry.is_retryable(ErrorKind::Timeout));
+        assert!(!retry.is_retryable(ErrorKind::Other));
+        assert!(policies.for_activity("b").is_none());
+    }
+
+    #[test]
+    fn test_on_error_strategies_from_workflow_json() {
+        let workflow = json!({
+            "activities": [
+                {"id": "charge", "on_error": {"strategy": "fallback", "node": "notify"}},
+                {"id": "reserve", "compensation": {"type": "tool", "tool_to_use": "release", "tool_parameters": {"id": "{{reserve}}"}}},
+                {"id": "ship", "on_error": {"strategy": "compensate"}},
+                {"id": "notify", "tolerates_failed_dependencies": true}
+            ]
+        });
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        assert_eq!(
+            policies.for_activity("charge").unwrap().on_error,
+            Some(OnErrorStrategy::Fallback { node: "notify".to_string() })
+        );
+        assert_eq!(
+            policies.for_activity("ship").unwrap().on_error,
+            Some(OnErrorStrategy::Compensate)
+        );
+        assert!(matches!(
+            policies.for_activity("reserve").unwrap().compensation,
+            Some(CompensationAction::Tool { ref tool_to_use, .. }) if tool_to_use == "release"
+        ));
+        assert!(policies.for_activity("notify").unwrap().tolerates_failed_dependencies);
+    }
+
+    #[test]
+    fn test_for_each_and_loop_from_workflow_json() {
+        let workflow = json!({
+            "activities": [
+                {"id": "enrich", "for_each": {"items": "{{list.ids}}", "max_concurrency": 4}},
+                {"id": "poll", "loop": {"until": "poll.status == 'done'", "delay_ms": 100}}
+            ]
+        });
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let for_each = policies.for_activity("enrich").unwrap().for_each.clone().unwrap();
+        assert_eq!(for_each.items, json!("{{list.ids}}"));
+        assert_eq!(for_each.max_concurrency, 4);
+        let repeat = policies.for_activity("poll").unwrap().repeat.clone().unwrap();
+        assert_eq!(repeat.until.as_deref(), Some("poll.status == 'done'"));
+        assert_eq!(repeat.while_condition, None);
+        assert_eq!(repeat.max_iterations, 10);
+    }
+
+    #[test]
+    fn test_sub_workflow_from_workflow_json() {
+        let workflow = json!({
+            "activities": [
+                {"id": "by_name", "sub_workflow": {"name": "enrich", "inputs": {"id": "{{lookup.id}}"}}},
+                {"id": "by_path", "sub_workflow": {"path": "workflows/notify.json"}}
+            ]
+        });
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let by_name = policies.for_activity("by_name").unwrap().sub_workflow.clone().unwrap();
+        assert_eq!(by_name.workflow, WorkflowReference::Name("enrich".to_string()));
+        assert_eq!(by_name.inputs, json!({"id": "{{lookup.id}}"}));
+        let by_path = policies.for_activity("by_path").unwrap().sub_workflow.clone().unwrap();
+        assert_eq!(by_path.workflow, WorkflowReference::Path("workflows/notify.json".to_string()));
+        assert!(by_path.inputs.is_null());
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 350,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff_after(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff_after(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff_after(3), Duration::from_millis(350));
+    }
+}
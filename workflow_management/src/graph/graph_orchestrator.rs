@@ -3,15 +3,29 @@ use agent_models::graph::graph_definition::{Activity, ActivityType, Graph, NodeT
 
 
 use crate::agent_communication::agent_invoker::AgentInvoker;
+use crate::graph::approval::{ApprovalDecision, PendingApproval, PlanApprovalHandle};
+use crate::graph::checkpoint::{CheckpointStatus, CheckpointStore, PlanCheckpoint};
+use crate::graph::execution_event::ExecutionEvent;
+use crate::graph::execution_policy::{
+    ActivityAttempt, CompensationAction, ErrorKind, ExecutionPolicies, LoopPolicy, NodeFailure, OnErrorStrategy,
+    RetryPolicy, SubWorkflowPolicy, TaskExecutionMode,
+};
+use crate::graph::recording::{agent_request, InvocationRecorder, InvokerKind, ReplayInvokers, RunRecording};
+use crate::graph::workflow_registry::WorkflowRegistry;
 use crate::tasks::condition_evaluator::evaluate_condition;
+use crate::tasks::interpolation::{InterpolationError, Interpolator};
 use crate::tasks::task_invoker::TaskInvoker;
 use crate::tools::tool_invoker::ToolInvoker;
-use regex::Regex;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
-use tracing::{debug, info};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
 
 #[derive(Error, Debug, PartialEq)]
 pub enum PlanExecutorError {
@@ -37,15 +51,807 @@ pub enum PlanExecutorError {
     InterpolationFailed(String),
     #[error("Missing task to use for DirectTaskExecution activity: {0}")]
     MissingTask(String),
+    #[error("Checkpoint store failure: {0}")]
+    CheckpointFailed(String),
+    #[error("No checkpoint found for run: {0}")]
+    CheckpointNotFound(String),
+    #[error("Checkpoint of run '{0}' belongs to another plan: {1}")]
+    CheckpointMismatch(String, String),
+    #[error("Invalid edge condition: {0}")]
+    InvalidCondition(String),
+    #[error("Activity '{0}' was rejected: {1}")]
+    ApprovalRejected(String, String),
+    #[error("Invalid edit of activity '{0}': {1}")]
+    InvalidApprovalEdit(String, String),
+    #[error("Invalid recording: {0}")]
+    InvalidRecording(String),
+    #[error("Invalid sub-workflow: {0}")]
+    InvalidSubWorkflow(String),
+    #[error("Activity '{0}' timed out after {1} ms")]
+    ActivityTimedOut(String, u64),
+    #[error("Plan cancelled after {} completed activities", .activities_outcome.len())]
+    Cancelled {
+        activities_outcome: HashMap<String, String>,
+    },
+    #[error("Plan deadline of {timeout_ms} ms exceeded after {} completed activities", .activities_outcome.len())]
+    TimedOut {
+        timeout_ms: u64,
+        activities_outcome: HashMap<String, String>,
+    },
 }
 
-pub struct PlanExecutor {
-    context: PlanContext,
+impl PlanExecutorError {
+    /// True when the error comes from the structure of the plan rather than from running it,
+    /// i.e. when a corrected plan could succeed. Failed interpolations are left out: they usually
+    /// come from the data returned by an activity, which a new plan would not change.
+    pub fn is_plan_defect(&self) -> bool {
+        matches!(
+            self,
+            PlanExecutorError::MissingNode(_)
+                | PlanExecutorError::CyclicDependency
+                | PlanExecutorError::MissingTool(_)
+                | PlanExecutorError::MissingSkill(_)
+                | PlanExecutorError::MissingTask(_)
+                | PlanExecutorError::InvalidCondition(_)
+                | PlanExecutorError::InvalidSubWorkflow(_)
+        )
+    }
+}
+
+enum PlanInterruption {
+    Cancelled,
+    DeadlineExceeded(u64),
+}
+
+/// Handle used to abort a running plan from outside the executor.
+/// Cancelling stops in-flight activities and makes `execute_plan` return
+/// `PlanExecutorError::Cancelled` with the outcomes recorded so far.
+#[derive(Clone)]
+pub struct PlanCancellationHandle {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl PlanCancellationHandle {
+    pub fn cancel(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.sender.borrow()
+    }
+}
+
+/// Default number of independent activities a `PlanExecutor` runs at the same time,
+/// see `PlanExecutor::with_max_concurrency`. Nodes run one at a time unless parallelism is asked for.
+pub const DEFAULT_MAX_CONCURRENCY: usize = 1;
+
+/// Default number of nested sub-workflows a plan may run, see `PlanExecutor::with_max_sub_workflow_depth`.
+pub const DEFAULT_MAX_SUB_WORKFLOW_DEPTH: usize = 5;
+
+/// Invokers shared by every activity dispatched by a `PlanExecutor`.
+/// Cheap to clone, so each in-flight activity owns its own handle.
+#[derive(Clone)]
+struct ActivityInvokers {
     task_invoker: Arc<dyn TaskInvoker>,
     agent_invoker: Arc<dyn AgentInvoker>,
     tool_invoker: Arc<dyn ToolInvoker>,
+    sub_workflows: SubWorkflowScope,
+    /// Answers the approvals of the plan and of its sub-workflows.
+    approvals: PlanApprovalHandle,
+    /// Records every call made through the invokers, see `PlanExecutor::recording`.
+    recorder: InvocationRecorder,
+    /// Where the agents chosen for delegations are reported, set for each dispatched node.
+    events: RunEvents,
+}
+
+/// Subscribers of a run, as seen from its running activities.
+#[derive(Clone, Default)]
+struct RunEvents {
+    run_id: String,
+    senders: Vec<mpsc::UnboundedSender<ExecutionEvent>>,
+}
+
+/// Where `sub_workflow` activities find their workflows, and the chain of workflows
+/// currently running, from the outermost child plan to the innermost one.
+#[derive(Clone)]
+struct SubWorkflowScope {
+    registry: Arc<WorkflowRegistry>,
+    max_depth: usize,
+    stack: Vec<String>,
+    /// Subscribers of the outermost plan, also told about the approvals child plans wait for.
+    approval_listeners: Vec<mpsc::UnboundedSender<ExecutionEvent>>,
+}
+
+type PlanRun = Pin<Box<dyn Future<Output = Result<(String, HashMap<String, String>), PlanExecutorError>> + Send>>;
+
+/// What a dispatched activity runs with when part of its interpolation happens after dispatch:
+/// the task parameters of a `DirectTaskExecution` activity, interpolated when each task starts,
+/// the parameters of `for_each` and `loop` activities, interpolated for each element or
+/// iteration with its `bindings`, and the inputs of a `sub_workflow`.
+#[derive(Clone, Default)]
+struct ActivityScope {
+    mode: TaskExecutionMode,
+    outcomes: HashMap<String, String>,
+    user_query: String,
+    bindings: HashMap<String, Value>,
+    sub_workflow: Option<SubWorkflowPolicy>,
+}
+
+impl ActivityScope {
+    fn interpolator(&self) -> Interpolator<'_> {
+        Interpolator::new(&self.outcomes, &self.user_query).with_bindings(&self.bindings)
+    }
+}
+
+/// How many times a dispatched activity runs.
+enum Repetition {
+    Once,
+    ForEach { items: Vec<Value>, max_concurrency: usize },
+    Loop(LoopPolicy),
+}
+
+/// Outcome of a dispatched activity, with every attempt made to run it.
+struct ActivityRun {
+    node_id: String,
+    result: Result<String, PlanExecutorError>,
+    attempts: Vec<ActivityAttempt>,
+}
+
+impl ActivityInvokers {
+    /// Runs a dispatched activity as many times as `repetition` asks.
+    /// A repeated activity is interpolated for each element or iteration.
+    async fn run_node(
+        &self,
+        node_id: String,
+        activity: Activity,
+        scope: ActivityScope,
+        repetition: Repetition,
+        retry_policy: Option<RetryPolicy>,
+        timeout: Option<Duration>,
+    ) -> ActivityRun {
+        match repetition {
+            Repetition::Once => self.run_with_retry(node_id, activity, scope, retry_policy, timeout).await,
+            Repetition::ForEach { items, max_concurrency } => {
+                self.run_for_each(node_id, activity, scope, items, max_concurrency, retry_policy, timeout)
+                    .await
+            }
+            Repetition::Loop(repeat) => {
+                self.run_loop(node_id, activity, scope, repeat, retry_policy, timeout)
+                    .await
+            }
+        }
+    }
+
+    /// Runs the activity once per item, at most `max_concurrency` at a time, each with its own
+    /// retries and timeout. The output is the array of the item outputs, in the order of `items`.
+    /// The first failing item fails the whole activity and aborts the items still running.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_for_each(
+        &self,
+        node_id: String,
+        activity: Activity,
+        scope: ActivityScope,
+        items: Vec<Value>,
+        max_concurrency: usize,
+        retry_policy: Option<RetryPolicy>,
+        timeout: Option<Duration>,
+    ) -> ActivityRun {
+        let mut outputs = vec![Value::Null; items.len()];
+        let mut attempts = Vec::new();
+        let mut pending = items.into_iter().enumerate();
+        let mut running = JoinSet::new();
+
+        loop {
+            while running.len() < max_concurrency.max(1) {
+                let Some((index, item)) = pending.next() else {
+                    break;
+                };
+                let mut scope = scope.clone();
+                scope.bindings.insert("item".to_string(), item);
+                scope.bindings.insert("item_index".to_string(), json!(index));
+                let invokers = self.clone();
+                let item_id = format!("{} (item {})", node_id, index);
+                let activity = activity.clone();
+                let retry_policy = retry_policy.clone();
+                running.spawn(async move {
+                    let run = invokers
+                        .run_with_bindings(item_id, activity, scope, retry_policy, timeout)
+                        .await;
+                    (index, run)
+                });
+            }
+
+            let Some(joined) = running.join_next().await else {
+                break;
+            };
+            let (index, run) = match joined {
+                Ok(joined) => joined,
+                Err(e) => {
+                    let result = Err(PlanExecutorError::ExecutionFailed(e.to_string()));
+                    return ActivityRun { node_id, result, attempts };
+                }
+            };
+            attempts.extend(run.attempts);
+            match run.result {
+                Ok(output) => outputs[index] = parse_outcome(&output),
+                Err(e) => {
+                    running.abort_all();
+                    return ActivityRun { node_id, result: Err(e), attempts };
+                }
+            }
+        }
+
+        let result = Ok(Value::Array(outputs).to_string());
+        ActivityRun { node_id, result, attempts }
+    }
+
+    /// Runs the activity until its `until` condition holds or its `while` condition stops
+    /// holding, checked after each iteration, and at most `max_iterations` times.
+    /// The output is the output of the last iteration.
+    async fn run_loop(
+        &self,
+        node_id: String,
+        activity: Activity,
+        mut scope: ActivityScope,
+        repeat: LoopPolicy,
+        retry_policy: Option<RetryPolicy>,
+        timeout: Option<Duration>,
+    ) -> ActivityRun {
+        let max_iterations = repeat.max_iterations.max(1);
+        let mut attempts = Vec::new();
+        let mut previous = Value::Null;
+        let mut iteration = 0;
+
+        loop {
+            iteration += 1;
+            scope.bindings.insert("iteration".to_string(), json!(iteration));
+            scope.bindings.insert("previous".to_string(), previous);
+            let iteration_id = format!("{} (iteration {})", node_id, iteration);
+            let run = self
+                .run_with_bindings(iteration_id, activity.clone(), scope.clone(), retry_policy.clone(), timeout)
+                .await;
+            attempts.extend(run.attempts);
+            let output = match run.result {
+                Ok(output) => output,
+                Err(e) => return ActivityRun { node_id, result: Err(e), attempts },
+            };
+
+            let mut outcomes = outcomes_as_json(&scope.outcomes, &node_id, &output);
+            outcomes.insert("iteration".to_string(), json!(iteration));
+            let check = |condition: &String| {
+                evaluate_condition(condition, &node_id, &outcomes).map_err(|e| {
+                    PlanExecutorError::InvalidCondition(format!("loop of '{}': {}", node_id, e))
+                })
+            };
+            let finished = match (repeat.until.as_ref().map(check), repeat.while_condition.as_ref().map(check)) {
+                (Some(Err(e)), _) | (_, Some(Err(e))) => {
+                    return ActivityRun { node_id, result: Err(e), attempts };
+                }
+                (Some(Ok(true)), _) | (_, Some(Ok(false))) => true,
+                _ => false,
+            };
+            if finished || iteration == max_iterations {
+                if !finished && (repeat.until.is_some() || repeat.while_condition.is_some()) {
+                    warn!(
+                        "Loop of '{}' stopped after reaching its {} iterations limit",
+                        node_id, max_iterations
+                    );
+                }
+                return ActivityRun { node_id, result: Ok(output), attempts };
+            }
+
+            debug!("Loop of '{}' continues after iteration {}", node_id, iteration);
+            previous = parse_outcome(&output);
+            if repeat.delay_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(repeat.delay_ms)).await;
+            }
+        }
+    }
+
+    /// Interpolates the activity with the bindings of `scope`, then runs it with retries.
+    async fn run_with_bindings(
+        &self,
+        node_id: String,
+        activity: Activity,
+        scope: ActivityScope,
+        retry_policy: Option<RetryPolicy>,
+        timeout: Option<Duration>,
+    ) -> ActivityRun {
+        let activity = match interpolate_activity(&activity, &scope.interpolator()) {
+            Ok(activity) => activity,
+            Err(e) => {
+                let result = Err(PlanExecutorError::InterpolationFailed(format!("activity '{}': {}", node_id, e)));
+                return ActivityRun { node_id, result, attempts: Vec::new() };
+            }
+        };
+        self.run_with_retry(node_id, activity, scope, retry_policy, timeout).await
+    }
+
+    /// Runs an activity, retrying invoker failures as allowed by `retry_policy`.
+    /// Structural errors (missing tool, agent or task) are never retried.
+    async fn run_with_retry(
+        &self,
+        node_id: String,
+        activity: Activity,
+        scope: ActivityScope,
+        retry_policy: Option<RetryPolicy>,
+        timeout: Option<Duration>,
+    ) -> ActivityRun {
+        let max_attempts = retry_policy.as_ref().map(|p| p.max_attempts.max(1)).unwrap_or(1);
+        let mut attempts = Vec::new();
+        let mut attempt = 1;
+
+        loop {
+            let started = Instant::now();
+            let result = match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, self.run_activity(activity.clone(), &scope))
+                    .await
+                    .unwrap_or_else(|_| {
+                        Err(PlanExecutorError::ActivityTimedOut(
+                            node_id.clone(),
+                            timeout.as_millis() as u64,
+                        ))
+                    }),
+                None => self.run_activity(activity.clone(), &scope).await,
+            };
+            let duration_ms = started.elapsed().as_millis() as u64;
+
+            let error = match result {
+                Ok(output) => {
+                    attempts.push(ActivityAttempt {
+                        attempt,
+                        duration_ms,
+                        error: None,
+                        error_kind: None,
+                    });
+                    return ActivityRun { node_id, result: Ok(output), attempts };
+                }
+                Err(e) => e,
+            };
+
+            let error_kind = match &error {
+                PlanExecutorError::ExecutionFailed(message) => Some(ErrorKind::classify(message)),
+                PlanExecutorError::ActivityTimedOut(_, _) => Some(ErrorKind::Timeout),
+                _ => None,
+            };
+            attempts.push(ActivityAttempt {
+                attempt,
+                duration_ms,
+                error: Some(error.to_string()),
+                error_kind,
+            });
+
+            let backoff = match (&retry_policy, error_kind) {
+                (Some(policy), Some(kind)) if attempt < max_attempts && policy.is_retryable(kind) => {
+                    policy.backoff_after(attempt)
+                }
+                _ => return ActivityRun { node_id, result: Err(error), attempts },
+            };
+
+            warn!(
+                "Activity '{}' failed on attempt {}/{} ({:?}): {}. Retrying in {:?}...",
+                node_id, attempt, max_attempts, error_kind, error, backoff
+            );
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    /// Runs a single, already interpolated activity against the matching invoker,
+    /// or as a child plan when the activity declares a `sub_workflow`.
+    async fn run_activity(&self, activity: Activity, scope: &ActivityScope) -> Result<String, PlanExecutorError> {
+        if let Some(sub_workflow) = &scope.sub_workflow {
+            return self.run_sub_workflow(&activity.id, sub_workflow, scope).await;
+        }
+
+        let result = match activity.activity_type {
+            ActivityType::DelegationAgent => {
+                let skill = activity.skill_to_use.clone().unwrap_or_default();
+                let agent_id = match &activity.assigned_agent_id_preference {
+                    Some(agent_id) => agent_id.clone(),
+                    // Left to the agent invoker, which picks an agent advertising the skill
+                    None if !skill.is_empty() => String::new(),
+                    None => {
+                        return Err(PlanExecutorError::AgentRunnerNotFound(
+                            "No agent preference nor skill specified".to_string(),
+                        ))
+                    }
+                };
+
+                let mut message = String::new();
+                message.push_str(&format!("Here is the user_query :"));
+                message.push_str(&activity.description.clone());
+                if let Some(context) = &activity.agent_context {
+                    message.push_str(&format!(
+                        "\nHere are contextual information to take into account when processing user_query: {}\n",
+                        context.to_string()
+                    ));
+                }
+
+                debug!(
+                    "Executing activity '{}', message: '{}' \n",
+                    &activity.id, message
+                );
+
+                self.interact(&activity.id, agent_id, message, skill)
+                    .await
+                    .map_err(|e| PlanExecutorError::ExecutionFailed(e.to_string()))?
+                    .to_string()
+            }
+            ActivityType::DirectToolUse => {
+                let tool_id = activity
+                    .tool_to_use
+                    .as_ref()
+                    .ok_or_else(|| PlanExecutorError::MissingTool(activity.id.clone()))?
+                    .clone();
+                let params = activity.tool_parameters.unwrap_or_else(|| Value::Null);
+
+                self.invoke_tool(&activity.id, tool_id, &params)
+                    .await
+                    .map_err(|e| PlanExecutorError::ExecutionFailed(e.to_string()))?
+                    .to_string()
+            }
+            ActivityType::DirectTaskExecution => {
+                let tasks: Vec<(String, Value)> = activity
+                    .tasks
+                    .as_ref()
+                    .ok_or_else(|| PlanExecutorError::MissingTask(activity.id.clone()))?
+                    .iter()
+                    .map(|task_config| {
+                        (
+                            task_config.task_to_use.as_ref().cloned().unwrap_or_default(),
+                            task_config.task_parameters.clone(),
+                        )
+                    })
+                    .collect();
+                if tasks.is_empty() {
+                    return Err(PlanExecutorError::MissingTask(activity.id.clone()));
+                }
+
+                let mut outputs = match scope.mode {
+                    TaskExecutionMode::Sequential => self.run_tasks_in_sequence(&activity.id, &tasks, scope).await?,
+                    TaskExecutionMode::Parallel => self.run_tasks_in_parallel(&activity.id, &tasks, scope).await?,
+                };
+
+                if outputs.len() == 1 {
+                    outputs.remove(0).to_string()
+                } else {
+                    let combined: serde_json::Map<String, Value> =
+                        task_output_keys(&tasks).into_iter().zip(outputs).collect();
+                    Value::Object(combined).to_string()
+                }
+            }
+        };
+
+        Ok(result)
+    }
+
+    /// Runs a child plan with its own context and returns its final outcome.
+    /// The child inherits the invokers of this plan and extends its chain of workflows.
+    async fn run_sub_workflow(
+        &self,
+        activity_id: &str,
+        sub_workflow: &SubWorkflowPolicy,
+        scope: &ActivityScope,
+    ) -> Result<String, PlanExecutorError> {
+        let interpolation_failed =
+            |e: InterpolationError| PlanExecutorError::InterpolationFailed(format!("activity '{}': {}", activity_id, e));
+        let interpolator = scope.interpolator();
+        let mut inputs = sub_workflow.inputs.clone();
+        interpolator.interpolate(&mut inputs).map_err(interpolation_failed)?;
+        let user_query = match &sub_workflow.user_query {
+            Some(user_query) => match interpolator.interpolate_str(user_query).map_err(interpolation_failed)? {
+                Some(Value::String(text)) => text,
+                Some(other) => other.to_string(),
+                None => user_query.clone(),
+            },
+            None => scope.user_query.clone(),
+        };
+
+        let invalid = |message: String| PlanExecutorError::InvalidSubWorkflow(format!("activity '{}': {}", activity_id, message));
+        let workflow = self
+            .sub_workflows
+            .registry
+            .resolve(&sub_workflow.workflow)
+            .map_err(|e| invalid(e.to_string()))?;
+        let stack = &self.sub_workflows.stack;
+        if stack.contains(&workflow.source) {
+            return Err(invalid(format!(
+                "cycle between workflows: {} -> {}",
+                stack.join(" -> "),
+                workflow.source
+            )));
+        }
+        if stack.len() >= self.sub_workflows.max_depth {
+            return Err(invalid(format!(
+                "maximum sub-workflow depth of {} exceeded by '{}'",
+                self.sub_workflows.max_depth, workflow.source
+            )));
+        }
+
+        let source = workflow.source.clone();
+        let mut invokers = self.clone();
+        invokers.sub_workflows.stack.push(source.clone());
+        let mut child = PlanExecutor::with_invokers(workflow.graph, invokers, user_query)
+            .with_execution_policies(workflow.execution_policies)
+            .with_binding("input", inputs);
+        info!("Activity '{}' runs sub-workflow '{}' as run '{}'", activity_id, source, child.run_id());
+
+        // Boxed, since a child plan may itself run sub-workflows.
+        let child_run: PlanRun = Box::pin(async move { child.execute_plan().await });
+        let (final_outcome, _) = child_run.await.map_err(|e| {
+            PlanExecutorError::ExecutionFailed(format!("sub-workflow '{}' of activity '{}': {}", source, activity_id, e))
+        })?;
+        Ok(final_outcome)
+    }
+
+    /// Runs the tasks one after the other, each one able to reference the outputs of the previous ones.
+    async fn run_tasks_in_sequence(
+        &self,
+        activity_id: &str,
+        tasks: &[(String, Value)],
+        scope: &ActivityScope,
+    ) -> Result<Vec<Value>, PlanExecutorError> {
+        let mut outcomes = scope.outcomes.clone();
+        let mut outputs = Vec::with_capacity(tasks.len());
+        for (task_id, params) in tasks {
+            let interpolator = Interpolator::new(&outcomes, &scope.user_query).with_bindings(&scope.bindings);
+            let params = interpolate_task_parameters(activity_id, task_id, params, &interpolator)?;
+            let output = self
+                .invoke_task(activity_id, task_id.clone(), &params)
+                .await
+                .map_err(|e| PlanExecutorError::ExecutionFailed(format!("task '{}': {}", task_id, e)))?;
+            outcomes.insert(task_id.clone(), output.to_string());
+            outputs.push(output);
+        }
+        Ok(outputs)
+    }
+
+    /// Runs every task at once. The first failure aborts the remaining ones.
+    async fn run_tasks_in_parallel(
+        &self,
+        activity_id: &str,
+        tasks: &[(String, Value)],
+        scope: &ActivityScope,
+    ) -> Result<Vec<Value>, PlanExecutorError> {
+        let mut running = JoinSet::new();
+        for (index, (task_id, params)) in tasks.iter().enumerate() {
+            let params = interpolate_task_parameters(activity_id, task_id, params, &scope.interpolator())?;
+            let invokers = self.clone();
+            let activity_id = activity_id.to_string();
+            let task_id = task_id.clone();
+            running.spawn(async move {
+                let output = invokers
+                    .invoke_task(&activity_id, task_id.clone(), &params)
+                    .await
+                    .map_err(|e| PlanExecutorError::ExecutionFailed(format!("task '{}': {}", task_id, e)));
+                (index, output)
+            });
+        }
+
+        let mut outputs = vec![Value::Null; tasks.len()];
+        while let Some(joined) = running.join_next().await {
+            let (index, output) = joined.map_err(|e| PlanExecutorError::ExecutionFailed(e.to_string()))?;
+            outputs[index] = output?;
+        }
+        Ok(outputs)
+    }
+
+    /// Runs the compensation of the completed activity `node_id`.
+    async fn run_compensation(&self, node_id: &str, action: CompensationAction) -> Result<String, PlanExecutorError> {
+        let result = match action {
+            CompensationAction::Tool {
+                tool_to_use,
+                tool_parameters,
+            } => self.invoke_tool(node_id, tool_to_use, &tool_parameters).await,
+            CompensationAction::Task {
+                task_to_use,
+                task_parameters,
+            } => self.invoke_task(node_id, task_to_use, &task_parameters).await,
+            CompensationAction::Agent {
+                agent_id,
+                message,
+                skill_to_use,
+            } => {
+                self.interact(node_id, agent_id, message, skill_to_use.unwrap_or_default())
+                    .await
+            }
+        };
+        result
+            .map(|value| value.to_string())
+            .map_err(|e| PlanExecutorError::ExecutionFailed(e.to_string()))
+    }
+
+    async fn invoke_tool(&self, node_id: &str, tool_id: String, params: &Value) -> anyhow::Result<Value> {
+        let result = self.tool_invoker.invoke(tool_id.clone(), params).await;
+        self.recorder
+            .record(node_id, InvokerKind::Tool, &tool_id, params.clone(), &result);
+        result
+    }
+
+    async fn invoke_task(&self, node_id: &str, task_id: String, params: &Value) -> anyhow::Result<Value> {
+        let result = self.task_invoker.invoke(task_id.clone(), params).await;
+        self.recorder
+            .record(node_id, InvokerKind::Task, &task_id, params.clone(), &result);
+        result
+    }
+
+    async fn interact(&self, node_id: &str, agent_id: String, message: String, skill: String) -> anyhow::Result<Value> {
+        let request = agent_request(&message, &skill);
+        let answer = self.agent_invoker.delegate(agent_id.clone(), message, skill.clone()).await;
+        if let Ok(answer) = &answer {
+            emit_to(&self.events.senders, ExecutionEvent::AgentChosen {
+                run_id: self.events.run_id.clone(),
+                node_id: node_id.to_string(),
+                agent_id: answer.agent_id.clone(),
+                skill,
+            });
+        }
+        let result = answer.map(|answer| answer.response);
+        self.recorder
+            .record(node_id, InvokerKind::Agent, &agent_id, request, &result);
+        result
+    }
+}
+
+/// Interpolates the tool parameters and agent context of an activity.
+/// Task parameters are interpolated when each task starts, see `ActivityScope`.
+fn interpolate_activity(activity: &Activity, interpolator: &Interpolator) -> Result<Activity, InterpolationError> {
+    let mut hydrated_activity = activity.clone();
+    if let Some(tool_params) = &mut hydrated_activity.tool_parameters {
+        interpolator.interpolate(tool_params)?;
+    }
+    if let Some(agent_context) = &mut hydrated_activity.agent_context {
+        interpolator.interpolate(agent_context)?;
+    }
+    Ok(hydrated_activity)
+}
+
+fn interpolate_task_parameters(
+    activity_id: &str,
+    task_id: &str,
+    params: &Value,
+    interpolator: &Interpolator,
+) -> Result<Value, PlanExecutorError> {
+    let mut params = params.clone();
+    interpolator
+        .interpolate(&mut params)
+        .map_err(|e| {
+            PlanExecutorError::InterpolationFailed(format!(
+                "activity '{}', task '{}': {}",
+                activity_id, task_id, e
+            ))
+        })?;
+    Ok(params)
+}
+
+fn emit_to(senders: &[mpsc::UnboundedSender<ExecutionEvent>], event: ExecutionEvent) {
+    for sender in senders {
+        // A dropped receiver only means nobody listens anymore.
+        let _ = sender.send(event.clone());
+    }
+}
+
+/// Parameters shown to the human approving an activity: its tool parameters, its agent
+/// context, or the array of its task parameters. Task parameters referencing an earlier
+/// task of the activity cannot be interpolated yet and are shown as declared.
+fn approval_parameters(activity: &Activity, scope: &ActivityScope) -> Value {
+    match activity.activity_type {
+        ActivityType::DirectToolUse => activity.tool_parameters.clone().unwrap_or(Value::Null),
+        ActivityType::DelegationAgent => activity.agent_context.clone().unwrap_or(Value::Null),
+        ActivityType::DirectTaskExecution => Value::Array(
+            activity
+                .tasks
+                .iter()
+                .flatten()
+                .map(|task| {
+                    let mut parameters = task.task_parameters.clone();
+                    match scope.interpolator().interpolate(&mut parameters) {
+                        Ok(()) => parameters,
+                        Err(_) => task.task_parameters.clone(),
+                    }
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Waits for the decision on an activity suspended for approval, records it and applies it.
+async fn await_approval(
+    node_id: &str,
+    mut activity: Activity,
+    decision: oneshot::Receiver<ApprovalDecision>,
+    recorder: &InvocationRecorder,
+) -> Result<Activity, PlanExecutorError> {
+    let rejected = |reason: &str| PlanExecutorError::ApprovalRejected(node_id.to_string(), reason.to_string());
+    let decision = decision.await;
+    if let Ok(decision) = &decision {
+        recorder.record_approval(node_id, decision);
+    }
+    let parameters = match decision {
+        Ok(ApprovalDecision::Approve) => return Ok(activity),
+        Ok(ApprovalDecision::Reject { reason }) => {
+            return Err(rejected(reason.as_deref().unwrap_or("no reason given")));
+        }
+        Ok(ApprovalDecision::Edit { parameters }) => parameters,
+        Err(_) => return Err(rejected("approval request dropped")),
+    };
+
+    match activity.activity_type {
+        ActivityType::DirectToolUse => activity.tool_parameters = Some(parameters),
+        ActivityType::DelegationAgent => activity.agent_context = Some(parameters),
+        ActivityType::DirectTaskExecution => {
+            let tasks = activity.tasks.get_or_insert_with(Vec::new);
+            match parameters {
+                Value::Array(edited) if edited.len() == tasks.len() => {
+                    for (task, parameters) in tasks.iter_mut().zip(edited) {
+                        task.task_parameters = parameters;
+                    }
+                }
+                _ => {
+                    return Err(PlanExecutorError::InvalidApprovalEdit(
+                        node_id.to_string(),
+                        format!("expected an array with the parameters of its {} tasks", tasks.len()),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(activity)
+}
+
+/// An outcome as JSON, or as a JSON string when it is plain text.
+fn parse_outcome(outcome: &str) -> Value {
+    serde_json::from_str(outcome).unwrap_or_else(|_| Value::String(outcome.to_string()))
+}
+
+/// Outcomes as JSON, as seen by edge and loop conditions once `node_id` produced `result`.
+fn outcomes_as_json(outcomes: &HashMap<String, String>, node_id: &str, result: &str) -> HashMap<String, Value> {
+    let mut outcomes: HashMap<String, Value> = outcomes
+        .iter()
+        .map(|(id, outcome)| (id.clone(), parse_outcome(outcome)))
+        .collect();
+    outcomes.insert(node_id.to_string(), parse_outcome(result));
+    outcomes
+}
+
+/// Keys of the task outputs in a combined outcome: the task id, suffixed with the
+/// task position when the same task appears more than once.
+fn task_output_keys(tasks: &[(String, Value)]) -> Vec<String> {
+    tasks
+        .iter()
+        .enumerate()
+        .map(|(index, (task_id, _))| {
+            if tasks.iter().filter(|(other, _)| other == task_id).count() > 1 {
+                format!("{}#{}", task_id, index)
+            } else {
+                task_id.clone()
+            }
+        })
+        .collect()
+}
+
+pub struct PlanExecutor {
+    context: PlanContext,
+    invokers: ActivityInvokers,
     execution_queue: VecDeque<String>,
     dependency_tracker: HashMap<String, usize>,
+    activated_nodes: HashSet<String>,
+    skipped_nodes: HashSet<String>,
+    blocked_nodes: HashSet<String>,
+    completion_order: Vec<String>,
+    node_failures: HashMap<String, NodeFailure>,
+    node_started_at: HashMap<String, Instant>,
+    event_senders: Vec<mpsc::UnboundedSender<ExecutionEvent>>,
+    max_concurrency: usize,
+    in_flight: JoinSet<ActivityRun>,
+    run_id: String,
+    checkpoint_store: Option<Arc<dyn CheckpointStore>>,
+    execution_policies: ExecutionPolicies,
+    attempt_history: HashMap<String, Vec<ActivityAttempt>>,
+    cancellation: PlanCancellationHandle,
+    /// Values bound for the whole plan, such as the `input` of a sub-workflow.
+    bindings: HashMap<String, Value>,
 }
 
 impl PlanExecutor {
@@ -56,6 +862,24 @@ impl PlanExecutor {
         tool_invoker: Arc<dyn ToolInvoker>,
         user_query: String,
     ) -> Self {
+        let invokers = ActivityInvokers {
+            task_invoker,
+            agent_invoker,
+            tool_invoker,
+            sub_workflows: SubWorkflowScope {
+                registry: Arc::new(WorkflowRegistry::default()),
+                max_depth: DEFAULT_MAX_SUB_WORKFLOW_DEPTH,
+                stack: Vec::new(),
+                approval_listeners: Vec::new(),
+            },
+            approvals: PlanApprovalHandle::default(),
+            recorder: InvocationRecorder::default(),
+            events: RunEvents::default(),
+        };
+        Self::with_invokers(graph, invokers, user_query)
+    }
+
+    fn with_invokers(graph: Graph, invokers: ActivityInvokers, user_query: String) -> Self {
         Self {
             context: PlanContext {
                 plan_state: PlanState::Idle,
@@ -65,29 +889,359 @@ impl PlanExecutor {
                 final_outcome: String::new(),
                 user_query,
             },
+            invokers,
+            execution_queue: VecDeque::new(),
+            dependency_tracker: HashMap::new(),
+            activated_nodes: HashSet::new(),
+            skipped_nodes: HashSet::new(),
+            blocked_nodes: HashSet::new(),
+            completion_order: Vec::new(),
+            node_failures: HashMap::new(),
+            node_started_at: HashMap::new(),
+            event_senders: Vec::new(),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            in_flight: JoinSet::new(),
+            run_id: uuid::Uuid::new_v4().to_string(),
+            checkpoint_store: None,
+            execution_policies: ExecutionPolicies::default(),
+            attempt_history: HashMap::new(),
+            cancellation: PlanCancellationHandle {
+                sender: Arc::new(watch::channel(false).0),
+            },
+            bindings: HashMap::new(),
+        }
+    }
+
+    /// Rebuilds an executor for an interrupted run from its last checkpoint.
+    /// Nodes whose outcomes are already recorded are not executed again.
+    pub async fn resume(
+        run_id: &str,
+        checkpoint_store: Arc<dyn CheckpointStore>,
+        task_invoker: Arc<dyn TaskInvoker>,
+        agent_invoker: Arc<dyn AgentInvoker>,
+        tool_invoker: Arc<dyn ToolInvoker>,
+    ) -> Result<Self, PlanExecutorError> {
+        let checkpoint = checkpoint_store
+            .load(run_id)
+            .await
+            .map_err(|e| PlanExecutorError::CheckpointFailed(e.to_string()))?
+            .ok_or_else(|| PlanExecutorError::CheckpointNotFound(run_id.to_string()))?;
+        let graph = checkpoint
+            .graph()
+            .map_err(|e| PlanExecutorError::CheckpointFailed(e.to_string()))?;
+
+        Ok(Self::new(
+            graph,
             task_invoker,
             agent_invoker,
             tool_invoker,
-            execution_queue: VecDeque::new(),
-            dependency_tracker: HashMap::new(),
+            checkpoint.user_query,
+        )
+        .with_execution_policies(checkpoint.execution_policies)
+        .with_checkpoint_store(checkpoint_store, run_id))
+    }
+
+    /// Builds an executor re-running a recorded plan against its recorded responses.
+    /// See `RunRecording::branch` to replay the run with one response edited.
+    pub fn replay(recording: &RunRecording, replay_invokers: Arc<ReplayInvokers>) -> Result<Self, PlanExecutorError> {
+        let graph = recording
+            .graph()
+            .map_err(|e| PlanExecutorError::InvalidRecording(e.to_string()))?;
+        let mut executor = Self::new(
+            graph,
+            replay_invokers.clone(),
+            replay_invokers.clone(),
+            replay_invokers,
+            recording.user_query.clone(),
+        )
+        .with_execution_policies(recording.execution_policies.clone());
+        executor.invokers.approvals = PlanApprovalHandle::replaying(&recording.approvals);
+        Ok(executor)
+    }
+
+    /// Every invoker call made so far by this plan and its sub-workflows, with the plan itself.
+    pub fn recording(&self) -> Result<RunRecording, PlanExecutorError> {
+        let graph = serde_json::to_value(&self.context.graph)
+            .map_err(|e| PlanExecutorError::InvalidRecording(e.to_string()))?;
+        Ok(RunRecording {
+            run_id: self.run_id.clone(),
+            graph,
+            user_query: self.context.user_query.clone(),
+            execution_policies: self.execution_policies.clone(),
+            calls: self.invokers.recorder.calls(),
+            approvals: self.invokers.recorder.approvals(),
+        })
+    }
+
+    /// Sets the per-activity execution settings (retry policy, ...) declared in the workflow.
+    pub fn with_execution_policies(mut self, execution_policies: ExecutionPolicies) -> Self {
+        self.execution_policies = execution_policies;
+        self
+    }
+
+    /// Sets where `sub_workflow` activities find the workflows they reference.
+    /// Without a registry, only workflow paths relative to the working directory resolve.
+    pub fn with_workflow_registry(mut self, registry: Arc<WorkflowRegistry>) -> Self {
+        self.invokers.sub_workflows.registry = registry;
+        self
+    }
+
+    /// Sets how many levels of sub-workflows may be nested below this plan.
+    pub fn with_max_sub_workflow_depth(mut self, max_depth: usize) -> Self {
+        self.invokers.sub_workflows.max_depth = max_depth;
+        self
+    }
+
+    /// Binds `value` to the `{{name}}` placeholder root for every activity of the plan.
+    fn with_binding(mut self, name: &str, value: Value) -> Self {
+        self.bindings.insert(name.to_string(), value);
+        self
+    }
+
+    /// Returns a handle answering the approvals requested by activities declaring an `approval`,
+    /// in this plan or in its sub-workflows.
+    pub fn approval_handle(&self) -> PlanApprovalHandle {
+        self.invokers.approvals.clone()
+    }
+
+    /// Returns a handle that can cancel this plan while `execute_plan` is running.
+    pub fn cancellation_handle(&self) -> PlanCancellationHandle {
+        self.cancellation.clone()
+    }
+
+    /// Every attempt made for each executed node, including failed ones.
+    pub fn attempt_history(&self) -> &HashMap<String, Vec<ActivityAttempt>> {
+        &self.attempt_history
+    }
+
+    /// Nodes that failed during the run, with the `on_error` strategy that handled each of them.
+    pub fn node_failures(&self) -> &HashMap<String, NodeFailure> {
+        &self.node_failures
+    }
+
+    /// Outcomes of the nodes completed so far, by node id.
+    pub fn activities_outcome(&self) -> &HashMap<String, String> {
+        &self.context.activities_outcome
+    }
+
+    /// Persists the plan context after every completed node under `run_id`.
+    /// If a checkpoint already exists for this run, its recorded outcomes are reused.
+    pub fn with_checkpoint_store(
+        mut self,
+        checkpoint_store: Arc<dyn CheckpointStore>,
+        run_id: impl Into<String>,
+    ) -> Self {
+        self.checkpoint_store = Some(checkpoint_store);
+        self.run_id = run_id.into();
+        self
+    }
+
+    /// Sets the id of this run, used in its events and to cancel it. A random id is used otherwise.
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = run_id.into();
+        self
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Returns a stream of the events emitted from now on. The stream ends with
+    /// `ExecutionEvent::PlanFinished` once `execute_plan` returns.
+    pub fn subscribe_events(&mut self) -> mpsc::UnboundedReceiver<ExecutionEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.invokers.sub_workflows.approval_listeners.push(sender.clone());
+        self.event_senders.push(sender);
+        receiver
+    }
+
+    fn emit(&self, event: ExecutionEvent) {
+        emit_to(&self.event_senders, event);
+    }
+
+    /// Invokers reporting to the current subscribers of the run.
+    fn invokers_reporting_events(&self) -> ActivityInvokers {
+        let mut invokers = self.invokers.clone();
+        invokers.events = RunEvents {
+            run_id: self.run_id.clone(),
+            senders: self.event_senders.clone(),
+        };
+        invokers
+    }
+
+    /// Where approval events go: a child plan waits for answers from the subscribers of its parents.
+    fn approval_event_senders(&self) -> Vec<mpsc::UnboundedSender<ExecutionEvent>> {
+        let mut senders = self.event_senders.clone();
+        if !self.invokers.sub_workflows.stack.is_empty() {
+            senders.extend(self.invokers.sub_workflows.approval_listeners.iter().cloned());
         }
+        senders
     }
 
+    /// Sets how many ready nodes may run concurrently.
+    /// Nodes with no path between them are dispatched together, up to this limit.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Runs the plan to completion, failure, cancellation or deadline, whichever comes first.
     pub async fn execute_plan(&mut self) -> Result<(String, HashMap<String, String>), PlanExecutorError> {
+        let started = Instant::now();
+        self.emit(ExecutionEvent::PlanStarted {
+            run_id: self.run_id.clone(),
+            node_count: self.context.graph.nodes.len(),
+        });
+
+        let outcome = self.run_until_interrupted().await;
+
+        self.emit(ExecutionEvent::PlanFinished {
+            run_id: self.run_id.clone(),
+            success: outcome.is_ok(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            error: outcome.as_ref().err().map(ToString::to_string),
+        });
+        outcome
+    }
+
+    async fn run_until_interrupted(&mut self) -> Result<(String, HashMap<String, String>), PlanExecutorError> {
         self.context.plan_state = PlanState::Idle;
+        self.restore_checkpoint().await?;
+
+        let mut cancelled = self.cancellation.sender.subscribe();
+        let plan_timeout_ms = self.execution_policies.plan_timeout_ms;
+        let deadline = async move {
+            match plan_timeout_ms {
+                Some(timeout_ms) => tokio::time::sleep(Duration::from_millis(timeout_ms)).await,
+                None => std::future::pending::<()>().await,
+            }
+        };
+
+        let interruption = tokio::select! {
+            outcome = self.run_state_machine() => return outcome,
+            _ = async {
+                if cancelled.wait_for(|cancelled| *cancelled).await.is_err() {
+                    std::future::pending::<()>().await;
+                }
+            } => PlanInterruption::Cancelled,
+            _ = deadline => PlanInterruption::DeadlineExceeded(plan_timeout_ms.unwrap_or_default()),
+        };
+
+        self.in_flight.abort_all();
+        let activities_outcome = self.context.activities_outcome.clone();
+        let interruption = match interruption {
+            PlanInterruption::Cancelled => PlanExecutorError::Cancelled { activities_outcome },
+            PlanInterruption::DeadlineExceeded(timeout_ms) => PlanExecutorError::TimedOut {
+                timeout_ms,
+                activities_outcome,
+            },
+        };
+
+        warn!("Run '{}' interrupted: {}", self.run_id, interruption);
+        self.context.plan_state = PlanState::Failed(interruption.to_string());
+        self.save_checkpoint(CheckpointStatus::Failed(interruption.to_string())).await?;
+        Err(interruption)
+    }
+
+    async fn run_state_machine(&mut self) -> Result<(String, HashMap<String, String>), PlanExecutorError> {
         loop {
-            match self.context.plan_state.clone() {
-                PlanState::Idle => self.handle_idle_state()?,
-                PlanState::Initializing => self.handle_initializing_state()?,
-                PlanState::DecidingNextStep => self.handle_deciding_next_step_state()?,
-                PlanState::ExecutingStep => self.handle_executing_step_state().await?,
-                PlanState::Completed => return self.handle_completion_state(),
-                PlanState::Failed(ref reason) => return self.handle_failure_state(reason.clone()),
-                _ => return Err(PlanExecutorError::InvalidState),
+            let step = match self.context.plan_state.clone() {
+                PlanState::Idle => self.handle_idle_state(),
+                PlanState::Initializing => self.handle_initializing_state(),
+                PlanState::DecidingNextStep => self.handle_deciding_next_step_state(),
+                PlanState::ExecutingStep => self.handle_executing_step_state().await,
+                PlanState::Completed => {
+                    let outcome = self.handle_completion_state();
+                    self.save_checkpoint(CheckpointStatus::Completed).await?;
+                    return outcome;
+                }
+                PlanState::Failed(ref reason) => {
+                    self.save_checkpoint(CheckpointStatus::Failed(reason.clone())).await?;
+                    return self.handle_failure_state(reason.clone());
+                }
+                _ => Err(PlanExecutorError::InvalidState),
+            };
+
+            // Errors ending the plan (e.g. `on_error: fail`) are checkpointed as failed, but
+            // returned as is so callers can still tell what went wrong.
+            if let Err(error) = step {
+                self.context.plan_state = PlanState::Failed(error.to_string());
+                if let Err(e) = self.save_checkpoint(CheckpointStatus::Failed(error.to_string())).await {
+                    warn!("Unable to checkpoint failure of run '{}': {}", self.run_id, e);
+                }
+                return Err(error);
             }
         }
     }
 
+    async fn restore_checkpoint(&mut self) -> Result<(), PlanExecutorError> {
+        let Some(store) = &self.checkpoint_store else {
+            return Ok(());
+        };
+        let checkpoint = store
+            .load(&self.run_id)
+            .await
+            .map_err(|e| PlanExecutorError::CheckpointFailed(e.to_string()))?;
+
+        let Some(checkpoint) = checkpoint else {
+            return Ok(());
+        };
+
+        // Outcomes only make sense for the plan that produced them.
+        let graph = serde_json::to_value(&self.context.graph)
+            .map_err(|e| PlanExecutorError::CheckpointFailed(e.to_string()))?;
+        if checkpoint.graph != graph {
+            return Err(PlanExecutorError::CheckpointMismatch(
+                self.run_id.clone(),
+                "the workflow graph differs".to_string(),
+            ));
+        }
+        if checkpoint.user_query != self.context.user_query {
+            return Err(PlanExecutorError::CheckpointMismatch(
+                self.run_id.clone(),
+                "the user query differs".to_string(),
+            ));
+        }
+
+        info!(
+            "Resuming run '{}' with {} recorded outcome(s)",
+            self.run_id,
+            checkpoint.activities_outcome.len()
+        );
+        // Failures of nodes without an outcome (`on_error: fail`) are forgotten: those nodes run again.
+        self.node_failures = checkpoint
+            .node_failures
+            .into_iter()
+            .filter(|(node_id, _)| checkpoint.activities_outcome.contains_key(node_id))
+            .collect();
+        self.context.activities_outcome = checkpoint.activities_outcome;
+        self.context.current_step_id = checkpoint.current_step_id;
+        Ok(())
+    }
+
+    async fn save_checkpoint(&self, status: CheckpointStatus) -> Result<(), PlanExecutorError> {
+        let Some(store) = &self.checkpoint_store else {
+            return Ok(());
+        };
+        let graph = serde_json::to_value(&self.context.graph)
+            .map_err(|e| PlanExecutorError::CheckpointFailed(e.to_string()))?;
+        let checkpoint = PlanCheckpoint {
+            run_id: self.run_id.clone(),
+            graph,
+            user_query: self.context.user_query.clone(),
+            status,
+            execution_policies: self.execution_policies.clone(),
+            current_step_id: self.context.current_step_id.clone(),
+            activities_outcome: self.context.activities_outcome.clone(),
+            node_failures: self.node_failures.clone(),
+            updated_at: chrono::Utc::now(),
+        };
+        store
+            .save(&checkpoint)
+            .await
+            .map_err(|e| PlanExecutorError::CheckpointFailed(e.to_string()))
+    }
+
     fn handle_idle_state(&mut self) -> Result<(), PlanExecutorError> {
         self.context.plan_state = PlanState::Initializing;
         Ok(())
@@ -105,6 +1259,26 @@ impl PlanExecutor {
             self.dependency_tracker.insert(node_id.clone(), dep_count);
         }
 
+        // Fallback nodes wait on the node they stand in for, even without a declared edge.
+        let node_ids: Vec<String> = self.context.graph.nodes.keys().cloned().collect();
+        for node_id in node_ids {
+            let Some(fallback) = self.fallback_target(&node_id) else {
+                continue;
+            };
+            let declared = self
+                .context
+                .graph
+                .edges
+                .iter()
+                .any(|e| e.source == node_id && e.target == fallback);
+            if !declared {
+                *self
+                    .dependency_tracker
+                    .get_mut(&fallback)
+                    .ok_or_else(|| PlanExecutorError::MissingNode(fallback.clone()))? += 1;
+            }
+        }
+
         for (node_id, count) in &self.dependency_tracker {
             if *count == 0 {
                 self.execution_queue.push_back(node_id.clone());
@@ -115,32 +1289,105 @@ impl PlanExecutor {
             return Err(PlanExecutorError::CyclicDependency);
         }
 
+        self.skip_restored_nodes()?;
+
         self.context.plan_state = PlanState::DecidingNextStep;
         Ok(())
     }
 
+    /// Walks the ready queue and completes, without executing them, the nodes whose
+    /// outcome was restored from a checkpoint, releasing their dependents in turn.
+    fn skip_restored_nodes(&mut self) -> Result<(), PlanExecutorError> {
+        let mut pending = std::mem::take(&mut self.execution_queue);
+        let mut ready = VecDeque::new();
+
+        while let Some(node_id) = pending.pop_front() {
+            match self.context.activities_outcome.get(&node_id).cloned() {
+                Some(result) => {
+                    debug!("Skipping node '{}': outcome restored from checkpoint", node_id);
+                    self.completion_order.push(node_id.clone());
+                    if self.node_failures.contains_key(&node_id) {
+                        self.release_dependents_of_failed_node(&node_id);
+                    } else {
+                        self.update_downstream_dependencies(&node_id, &result)?;
+                    }
+                    pending.extend(self.execution_queue.drain(..));
+                }
+                None => ready.push_back(node_id),
+            }
+        }
+
+        self.execution_queue = ready;
+        Ok(())
+    }
+
     fn handle_deciding_next_step_state(&mut self) -> Result<(), PlanExecutorError> {
-        if let Some(node_id) = self.execution_queue.pop_front() {
-            self.context.current_step_id = Some(node_id);
+        if !self.execution_queue.is_empty() || !self.in_flight.is_empty() {
             self.context.plan_state = PlanState::ExecutingStep;
+        } else if self.context.activities_outcome.len() + self.skipped_nodes.len()
+            == self.context.graph.nodes.len()
+        {
+            self.context.plan_state = PlanState::Completed;
         } else {
-            if self.context.activities_outcome.len() == self.context.graph.nodes.len() {
-                self.context.plan_state = PlanState::Completed;
-            } else {
-                self.context.plan_state =
-                    PlanState::Failed("No executable tasks left and plan not completed.".to_string());
+            self.context.plan_state =
+                PlanState::Failed("No executable tasks left and plan not completed.".to_string());
+        }
+        Ok(())
+    }
+
+    /// Dispatches every ready node (up to `max_concurrency` in flight), then waits
+    /// for the first running activity to finish and records its outcome, or applies
+    /// the node's `on_error` strategy if it failed.
+    async fn handle_executing_step_state(&mut self) -> Result<(), PlanExecutorError> {
+        while self.in_flight.len() < self.max_concurrency {
+            let Some(node_id) = self.execution_queue.pop_front() else {
+                break;
+            };
+            self.dispatch_node(node_id)?;
+        }
+
+        if let Some(joined) = self.in_flight.join_next().await {
+            let run = joined.map_err(|e| PlanExecutorError::ExecutionFailed(e.to_string()))?;
+            let node_id = run.node_id;
+            let attempts = run.attempts.len();
+            self.attempt_history.insert(node_id.clone(), run.attempts);
+            let duration_ms = self
+                .node_started_at
+                .remove(&node_id)
+                .map(|started| started.elapsed().as_millis() as u64)
+                .unwrap_or_default();
+
+            match run.result {
+                Ok(result) => {
+                    self.emit(ExecutionEvent::NodeSucceeded {
+                        run_id: self.run_id.clone(),
+                        node_id: node_id.clone(),
+                        result: result.clone(),
+                        duration_ms,
+                        attempts,
+                    });
+                    self.record_outcome(&node_id, result)?
+                }
+                Err(error) => {
+                    self.emit(ExecutionEvent::NodeFailed {
+                        run_id: self.run_id.clone(),
+                        node_id: node_id.clone(),
+                        error: error.to_string(),
+                        duration_ms,
+                        attempts,
+                        strategy: self.on_error_strategy(&node_id),
+                    });
+                    self.handle_node_failure(&node_id, error).await?
+                }
             }
+            self.save_checkpoint(CheckpointStatus::Running).await?;
         }
+
+        self.context.plan_state = PlanState::DecidingNextStep;
         Ok(())
     }
 
-    async fn handle_executing_step_state(&mut self) -> Result<(), PlanExecutorError> {
-        let node_id = self
-            .context
-            .current_step_id
-            .as_ref()
-            .cloned()
-            .ok_or(PlanExecutorError::InvalidState)?;
+    fn dispatch_node(&mut self, node_id: String) -> Result<(), PlanExecutorError> {
         let node = self
             .context
             .graph
@@ -151,79 +1398,112 @@ impl PlanExecutor {
 
         let NodeType::Activity(original_activity) = &node.node_type;
 
-        let activity = self.interpolate_parameters(original_activity)?;
-        let result = match activity.activity_type {
-            ActivityType::DelegationAgent => {
-                let agent_id = activity
-                    .assigned_agent_id_preference
-                    .as_ref()
-                    .ok_or_else(|| {
-                        PlanExecutorError::AgentRunnerNotFound(
-                            "No agent preference specified".to_string(),
-                        )
-                    })?
-                    .clone();
-
-                let mut message = String::new();
-                message.push_str(&format!("Here is the user_query :"));
-                message.push_str(&activity.description.clone());
-                if let Some(context) = &activity.agent_context {
-                    message.push_str(&format!(
-                        "\nHere are contextual information to take into account when processing user_query: {}\n",
-                        context.to_string()
-                    ));
-                }
+        let policy = self
+            .execution_policies
+            .for_activity(&node_id)
+            .cloned()
+            .unwrap_or_default();
+        let timeout = policy.timeout_ms.map(Duration::from_millis);
 
-                debug!(
-                    "Executing activity '{}', message: '{}' \n",
-                    &activity.id, message
-                );
+        let repetition = match (&policy.for_each, &policy.repeat) {
+            (Some(for_each), _) => Repetition::ForEach {
+                items: self.for_each_items(&node_id, &for_each.items)?,
+                max_concurrency: for_each.max_concurrency,
+            },
+            (None, Some(repeat)) => Repetition::Loop(repeat.clone()),
+            (None, None) => Repetition::Once,
+        };
 
-                let skill = activity.skill_to_use.clone().unwrap_or_default();
+        // Repeated activities are interpolated for each element or iteration, once bound.
+        let activity = match repetition {
+            Repetition::Once => self.interpolate_parameters(original_activity)?,
+            _ => original_activity.clone(),
+        };
+        self.context.current_step_id = Some(node_id.clone());
 
-                self.agent_invoker
-                    .interact(agent_id, message, skill)
-                    .await
-                    .map_err(|e| PlanExecutorError::ExecutionFailed(e.to_string()))?
-                    .to_string()
+        let needs_scope = policy.sub_workflow.is_some()
+            || matches!(activity.activity_type, ActivityType::DirectTaskExecution)
+            || !matches!(repetition, Repetition::Once);
+        let scope = if needs_scope {
+            ActivityScope {
+                mode: policy.task_execution.unwrap_or_default(),
+                outcomes: self.context.activities_outcome.clone(),
+                user_query: self.context.user_query.clone(),
+                bindings: self.bindings.clone(),
+                sub_workflow: policy.sub_workflow.clone(),
             }
-            ActivityType::DirectToolUse => {
-                let tool_id = activity
-                    .tool_to_use
-                    .as_ref()
-                    .ok_or_else(|| PlanExecutorError::MissingTool(activity.id.clone()))?
-                    .clone();
-                let params = activity.tool_parameters.unwrap_or_else(|| Value::Null);
+        } else {
+            ActivityScope::default()
+        };
 
-                self.tool_invoker
-                    .invoke(tool_id, &params)
-                    .await
-                    .map_err(|e| PlanExecutorError::ExecutionFailed(e.to_string()))?
-                    .to_string()
+        // Registered before the node starts: the approvals handle is shared with sub-workflows.
+        let approval = match &policy.approval {
+            Some(approval) => {
+                let pending = PendingApproval {
+                    run_id: self.run_id.clone(),
+                    node_id: node_id.clone(),
+                    prompt: approval.prompt.clone(),
+                    parameters: approval_parameters(&activity, &scope),
+                };
+                let decision = self.invokers.approvals.request(pending.clone()).map_err(|e| {
+                    PlanExecutorError::ExecutionFailed(format!("activity '{}': {}", node_id, e))
+                })?;
+                Some((pending, decision))
             }
-            ActivityType::DirectTaskExecution => {
-                let tasks = activity
-                    .tasks
-                    .as_ref()
-                    .ok_or_else(|| PlanExecutorError::MissingTask(activity.id.clone()))?;
-                let task_config = tasks
-                    .get(0)
-                    .ok_or_else(|| PlanExecutorError::MissingTask(activity.id.clone()))?;
+            None => None,
+        };
 
-                let task_id = task_config.task_to_use.as_ref().cloned().unwrap_or_default();
-                let params = task_config.task_parameters.clone();
+        self.node_started_at.insert(node_id.clone(), Instant::now());
+        self.emit(ExecutionEvent::NodeStarted {
+            run_id: self.run_id.clone(),
+            node_id: node_id.clone(),
+        });
 
-                self.task_invoker
-                    .invoke(task_id, &params)
-                    .await
-                    .map_err(|e| PlanExecutorError::ExecutionFailed(e.to_string()))?
-                    .to_string()
-            }
-        };
+        let event_senders = self.approval_event_senders();
+        let approval = approval.map(|(pending, decision)| {
+            info!("Activity '{}' waits for approval", node_id);
+            emit_to(&event_senders, ExecutionEvent::ApprovalRequested {
+                run_id: self.run_id.clone(),
+                node_id: node_id.clone(),
+                prompt: pending.prompt,
+                parameters: pending.parameters,
+            });
+            decision
+        });
+
+        let invokers = self.invokers_reporting_events();
+        let run_id = self.run_id.clone();
+        self.in_flight.spawn(async move {
+            let activity = match approval {
+                Some(decision) => {
+                    let approved = await_approval(&node_id, activity, decision, &invokers.recorder).await;
+                    emit_to(
+                        &event_senders,
+                        ExecutionEvent::ApprovalResolved {
+                            run_id,
+                            node_id: node_id.clone(),
+                            approved: approved.is_ok(),
+                        },
+                    );
+                    match approved {
+                        Ok(activity) => activity,
+                        Err(e) => return ActivityRun { node_id, result: Err(e), attempts: Vec::new() },
+                    }
+                }
+                None => activity,
+            };
+            invokers
+                .run_node(node_id, activity, scope, repetition, policy.retry_policy, timeout)
+                .await
+        });
+        Ok(())
+    }
 
+    fn record_outcome(&mut self, node_id: &str, result: String) -> Result<(), PlanExecutorError> {
         self.context
             .activities_outcome
-            .insert(node_id.clone(), result.clone());
+            .insert(node_id.to_string(), result.clone());
+        self.completion_order.push(node_id.to_string());
 
         let printable_result = match serde_json::from_str::<serde_json::Value>(&result) {
             Ok(json_value) => {
@@ -236,10 +1516,7 @@ impl PlanExecutor {
             "Executed node '{}', result: '{}' \n",
             node_id, printable_result
         );
-        self.update_downstream_dependencies(&node_id, &result)?;
-        self.context.plan_state = PlanState::DecidingNextStep;
-
-        Ok(())
+        self.update_downstream_dependencies(node_id, &result)
     }
 
     fn update_downstream_dependencies(
@@ -247,30 +1524,259 @@ impl PlanExecutor {
         completed_node_id: &str,
         result: &str,
     ) -> Result<(), PlanExecutorError> {
-        for edge in &self.context.graph.edges {
-            if edge.source == *completed_node_id {
-                let mut condition_met = true;
-                if let Some(condition) = &edge.condition {
-                    let mut dependencies = HashMap::new();
-                    let result_value = serde_json::from_str(result)
-                        .unwrap_or_else(|_| Value::String(result.to_string()));
-                    dependencies.insert(completed_node_id.to_string(), result_value);
-                    condition_met = evaluate_condition(condition, &dependencies);
-                }
+        let fallback = self.fallback_target(completed_node_id);
 
-                if condition_met {
-                    if let Some(count) = self.dependency_tracker.get_mut(&edge.target) {
-                        *count -= 1;
-                        if *count == 0 {
-                            self.execution_queue.push_back(edge.target.clone());
-                        }
-                    }
+        let mut outcomes: Option<HashMap<String, Value>> = None;
+        for (target, condition) in self.downstream_edges(completed_node_id) {
+            if fallback.as_deref() == Some(target.as_str()) {
+                // Error edges are only followed when the source node fails.
+                self.resolve_inbound_edge(&target, false);
+                continue;
+            }
+            let condition_met = match &condition {
+                Some(condition) => {
+                    let outcomes = outcomes.get_or_insert_with(|| {
+                        outcomes_as_json(&self.context.activities_outcome, completed_node_id, result)
+                    });
+                    evaluate_condition(condition, completed_node_id, outcomes).map_err(|e| {
+                        PlanExecutorError::InvalidCondition(format!(
+                            "edge '{}' -> '{}': {}",
+                            completed_node_id, target, e
+                        ))
+                    })?
                 }
+                None => true,
+            };
+
+            if !condition_met {
+                debug!(
+                    "Condition on edge '{}' -> '{}' not met",
+                    completed_node_id, target
+                );
+                self.emit(ExecutionEvent::EdgeSkipped {
+                    run_id: self.run_id.clone(),
+                    source: completed_node_id.to_string(),
+                    target: target.clone(),
+                    condition: condition.unwrap_or_default(),
+                });
             }
+            self.resolve_inbound_edge(&target, condition_met);
         }
         Ok(())
     }
 
+    /// Marks one incoming edge of `target` as resolved.
+    /// Once every incoming edge is resolved, the node is queued if at least one of them
+    /// was satisfied, and skipped otherwise (which in turn resolves its own outgoing edges).
+    fn resolve_inbound_edge(&mut self, target: &str, satisfied: bool) {
+        if satisfied {
+            self.activated_nodes.insert(target.to_string());
+        }
+
+        let Some(count) = self.dependency_tracker.get_mut(target) else {
+            return;
+        };
+        *count = count.saturating_sub(1);
+        if *count > 0 {
+            return;
+        }
+
+        if self.activated_nodes.contains(target) && !self.blocked_nodes.contains(target) {
+            self.execution_queue.push_back(target.to_string());
+        } else {
+            if self.blocked_nodes.contains(target) {
+                info!("Skipping node '{}': a dependency failed", target);
+            } else {
+                info!("Skipping node '{}': no incoming condition was met", target);
+            }
+            self.skipped_nodes.insert(target.to_string());
+
+            for (next, _) in self.downstream_edges(target) {
+                self.resolve_inbound_edge(&next, false);
+            }
+        }
+    }
+
+    /// Edges leaving `node_id`, including the implied error edge to its fallback node.
+    fn downstream_edges(&self, node_id: &str) -> Vec<(String, Option<String>)> {
+        let mut edges: Vec<(String, Option<String>)> = self
+            .context
+            .graph
+            .edges
+            .iter()
+            .filter(|edge| edge.source == node_id)
+            .map(|edge| (edge.target.clone(), edge.condition.clone()))
+            .collect();
+        if let Some(fallback) = self.fallback_target(node_id) {
+            if !edges.iter().any(|(target, _)| *target == fallback) {
+                edges.push((fallback, None));
+            }
+        }
+        edges
+    }
+
+    fn on_error_strategy(&self, node_id: &str) -> OnErrorStrategy {
+        self.execution_policies
+            .for_activity(node_id)
+            .and_then(|policy| policy.on_error.clone())
+            .unwrap_or_default()
+    }
+
+    fn fallback_target(&self, node_id: &str) -> Option<String> {
+        match self.execution_policies.for_activity(node_id)?.on_error.as_ref()? {
+            OnErrorStrategy::Fallback { node } => Some(node.clone()),
+            _ => None,
+        }
+    }
+
+    /// Applies the `on_error` strategy of a node whose activity failed.
+    /// Returns the error when the strategy ends the plan.
+    async fn handle_node_failure(
+        &mut self,
+        node_id: &str,
+        error: PlanExecutorError,
+    ) -> Result<(), PlanExecutorError> {
+        let strategy = self.on_error_strategy(node_id);
+        let mut failure = NodeFailure {
+            error: error.to_string(),
+            strategy: strategy.clone(),
+            compensated: Vec::new(),
+            compensation_errors: HashMap::new(),
+            aborted: Vec::new(),
+        };
+
+        match strategy {
+            OnErrorStrategy::Fail => {
+                failure.aborted = self.abort_in_flight_nodes().await;
+                if !failure.aborted.is_empty() {
+                    warn!(
+                        "Activity '{}' failed, aborted activities {:?} while they were running",
+                        node_id, failure.aborted
+                    );
+                }
+                self.node_failures.insert(node_id.to_string(), failure);
+                Err(error)
+            }
+            OnErrorStrategy::Continue | OnErrorStrategy::Fallback { .. } => {
+                warn!(
+                    "Activity '{}' failed, applying {:?}: {}",
+                    node_id, failure.strategy, error
+                );
+                let outcome = serde_json::json!({ "error": failure.error }).to_string();
+                self.context
+                    .activities_outcome
+                    .insert(node_id.to_string(), outcome);
+                self.completion_order.push(node_id.to_string());
+                self.node_failures.insert(node_id.to_string(), failure);
+                self.release_dependents_of_failed_node(node_id);
+                Ok(())
+            }
+            OnErrorStrategy::Compensate => {
+                warn!(
+                    "Activity '{}' failed, compensating completed activities: {}",
+                    node_id, error
+                );
+                failure.aborted = self.abort_in_flight_nodes().await;
+                if !failure.aborted.is_empty() {
+                    warn!(
+                        "Aborted activities {:?} while they were running, they are not compensated",
+                        failure.aborted
+                    );
+                }
+                self.compensate_completed_nodes(&mut failure).await;
+                self.node_failures.insert(node_id.to_string(), failure);
+                Err(error)
+            }
+        }
+    }
+
+    /// Resolves the outgoing edges of a failed node. Only its fallback node and the
+    /// dependents that tolerate failed dependencies may run; the others are skipped.
+    fn release_dependents_of_failed_node(&mut self, node_id: &str) {
+        let fallback = self.fallback_target(node_id);
+        for (target, _) in self.downstream_edges(node_id) {
+            let tolerated = fallback.as_deref() == Some(target.as_str())
+                || self
+                    .execution_policies
+                    .for_activity(&target)
+                    .is_some_and(|policy| policy.tolerates_failed_dependencies);
+            if !tolerated {
+                self.blocked_nodes.insert(target.clone());
+            }
+            self.resolve_inbound_edge(&target, tolerated);
+        }
+    }
+
+    /// Aborts the activities still running and returns their ids. Activities that finished
+    /// in the meantime are recorded as completed instead, so they get compensated.
+    async fn abort_in_flight_nodes(&mut self) -> Vec<String> {
+        self.in_flight.abort_all();
+        while let Some(joined) = self.in_flight.join_next().await {
+            let Ok(run) = joined else {
+                continue;
+            };
+            self.node_started_at.remove(&run.node_id);
+            self.attempt_history.insert(run.node_id.clone(), run.attempts);
+            if let Ok(result) = run.result {
+                self.context.activities_outcome.insert(run.node_id.clone(), result);
+                self.completion_order.push(run.node_id);
+            }
+        }
+        let mut aborted: Vec<String> = self.node_started_at.drain().map(|(node_id, _)| node_id).collect();
+        aborted.sort();
+        aborted
+    }
+
+    /// Runs the compensation of every successfully completed node, latest first.
+    /// Compensation failures are recorded and do not stop the remaining compensations.
+    async fn compensate_completed_nodes(&mut self, failure: &mut NodeFailure) {
+        let completed: Vec<String> = self.completion_order.iter().rev().cloned().collect();
+        for node_id in completed {
+            if self.node_failures.contains_key(&node_id) {
+                continue;
+            }
+            let Some(action) = self
+                .execution_policies
+                .for_activity(&node_id)
+                .and_then(|policy| policy.compensation.clone())
+            else {
+                continue;
+            };
+
+            let compensation = match self.interpolate_compensation(&node_id, action) {
+                Ok(action) => self.invokers_reporting_events().run_compensation(&node_id, action).await,
+                Err(e) => Err(e),
+            };
+            match compensation {
+                Ok(_) => {
+                    info!("Compensated node '{}'", node_id);
+                    failure.compensated.push(node_id);
+                }
+                Err(e) => {
+                    warn!("Compensation of node '{}' failed: {}", node_id, e);
+                    failure.compensation_errors.insert(node_id, e.to_string());
+                }
+            }
+        }
+    }
+
+    fn interpolate_compensation(
+        &self,
+        node_id: &str,
+        mut action: CompensationAction,
+    ) -> Result<CompensationAction, PlanExecutorError> {
+        match &mut action {
+            CompensationAction::Tool { tool_parameters, .. } => {
+                self.interpolate_json(node_id, tool_parameters)?
+            }
+            CompensationAction::Task { task_parameters, .. } => {
+                self.interpolate_json(node_id, task_parameters)?
+            }
+            CompensationAction::Agent { .. } => {}
+        }
+        Ok(action)
+    }
+
     fn handle_completion_state(&mut self) -> Result<(String, HashMap<String, String>), PlanExecutorError> {
         let all_node_ids: HashSet<String> = self.context.graph.nodes.keys().cloned().collect();
         let source_node_ids: HashSet<String> =
@@ -309,70 +1815,684 @@ impl PlanExecutor {
         &self,
         activity: &Activity,
     ) -> Result<Activity, PlanExecutorError> {
-        let mut hydrated_activity = activity.clone();
-        let re = Regex::new(r"\{\{([^}]+)\}\}").unwrap();
-
-        let get_interpolated_value = |path: &str| -> Result<String, PlanExecutorError> {
-            let source_id = path.split('.').next().unwrap_or("");
-
-            if source_id.is_empty() {
-                return Err(PlanExecutorError::InterpolationFailed(
-                    "Invalid interpolation path: empty source ID".to_string(),
-                ));
-            }
-
-            self.context
-                .activities_outcome
-                .get(source_id)
-                .cloned()
-                .ok_or_else(|| {
-                    PlanExecutorError::InterpolationFailed(format!(
-                        "Dependency result for '{}' not found for activity '{}'",
-                        source_id, activity.id
-                    ))
-                })
+        let interpolator = Interpolator::new(&self.context.activities_outcome, &self.context.user_query)
+            .with_bindings(&self.bindings);
+        let hydrated_activity = interpolate_activity(activity, &interpolator)
+            .map_err(|e| PlanExecutorError::InterpolationFailed(format!("activity '{}': {}", activity.id, e)))?;
+
+        debug!("Hydrated Activity: {:?}", hydrated_activity);
+
+        Ok(hydrated_activity)
+    }
+
+    /// Elements a `for_each` activity maps over: its `items`, interpolated, which must be
+    /// an array or a string holding a JSON array.
+    fn for_each_items(&self, activity_id: &str, items: &Value) -> Result<Vec<Value>, PlanExecutorError> {
+        let mut items = items.clone();
+        self.interpolate_json(activity_id, &mut items)?;
+        if let Value::String(text) = &items {
+            items = parse_outcome(text);
+        }
+        match items {
+            Value::Array(items) => Ok(items),
+            other => Err(PlanExecutorError::InterpolationFailed(format!(
+                "activity '{}': for_each items must be an array, got {}",
+                activity_id, other
+            ))),
+        }
+    }
+
+    fn interpolate_json(&self, activity_id: &str, json_value: &mut Value) -> Result<(), PlanExecutorError> {
+        Interpolator::new(&self.context.activities_outcome, &self.context.user_query)
+            .with_bindings(&self.bindings)
+            .interpolate(json_value)
+            .map_err(|e| PlanExecutorError::InterpolationFailed(format!("activity '{}': {}", activity_id, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::recording::RecordedApproval;
+    use crate::testing::{EchoTasks, EchoTools, NoAgents};
+    use agent_models::graph::graph_definition::WorkflowPlanInput;
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    /// Answers like `EchoTools` after `delay`, keeping track of how many calls run at once.
+    #[derive(Default)]
+    struct DelayedTools {
+        delay: Duration,
+        running: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ToolInvoker for DelayedTools {
+        async fn invoke(&self, tool_id: String, params: &Value) -> anyhow::Result<Value> {
+            use std::sync::atomic::Ordering;
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            EchoTools.invoke(tool_id, params).await
+        }
+    }
+
+    fn tool_activity(id: &str, tool: &str, dependencies: Value) -> Value {
+        json!({
+            "id": id,
+            "description": id,
+            "type": "test",
+            "activity_type": "direct_tool_use",
+            "agent": {},
+            "tools": [{ "tool_to_use": tool, "tool_parameters": {} }],
+            "tasks": [],
+            "dependencies": dependencies,
+            "expected_outcome": ""
+        })
+    }
+
+    fn task_activity(id: &str, tasks: Value) -> Value {
+        json!({
+            "id": id,
+            "description": id,
+            "type": "test",
+            "activity_type": "direct_task_execution",
+            "agent": {},
+            "tools": [],
+            "tasks": tasks,
+            "dependencies": [],
+            "expected_outcome": ""
+        })
+    }
+
+    fn executor(workflow: Value) -> PlanExecutor {
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let workflow: WorkflowPlanInput = serde_json::from_value(workflow).unwrap();
+        PlanExecutor::new(
+            workflow.into(),
+            Arc::new(EchoTasks),
+            Arc::new(NoAgents),
+            Arc::new(EchoTools),
+            "test".to_string(),
+        )
+        .with_execution_policies(policies)
+    }
+
+    fn collect(receiver: &mut mpsc::UnboundedReceiver<ExecutionEvent>) -> Vec<ExecutionEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_events_follow_plan_progress() {
+        let mut executor = executor(json!({
+            "plan_name": "events",
+            "activities": [
+                tool_activity("check", "fail", json!([])),
+                tool_activity("proceed", "proceed", json!([{ "source": "check", "condition": "result.ok == true" }]))
+            ]
+        }));
+        let mut receiver = executor.subscribe_events();
+        executor.execute_plan().await.unwrap();
+
+        let events = collect(&mut receiver);
+        let kinds: Vec<&str> = events
+            .iter()
+            .map(|event| match event {
+                ExecutionEvent::PlanStarted { .. } => "plan_started",
+                ExecutionEvent::NodeStarted { .. } => "node_started",
+                ExecutionEvent::NodeSucceeded { .. } => "node_succeeded",
+                ExecutionEvent::NodeFailed { .. } => "node_failed",
+                ExecutionEvent::ApprovalRequested { .. } => "approval_requested",
+                ExecutionEvent::ApprovalResolved { .. } => "approval_resolved",
+                ExecutionEvent::AgentChosen { .. } => "agent_chosen",
+                ExecutionEvent::EdgeSkipped { .. } => "edge_skipped",
+                ExecutionEvent::PlanFinished { .. } => "plan_finished",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            ["plan_started", "node_started", "node_succeeded", "edge_skipped", "plan_finished"]
+        );
+        assert!(matches!(
+            &events[3],
+            ExecutionEvent::EdgeSkipped { source, target, .. } if source == "check" && target == "proceed"
+        ));
+        assert!(events.iter().all(|event| event.run_id() == executor.run_id()));
+    }
+
+    #[tokio::test]
+    async fn test_failed_node_continues_with_tolerant_dependents() {
+        let mut failing = tool_activity("charge", "broken", json!([]));
+        failing["on_error"] = json!({ "strategy": "continue" });
+        let mut tolerant = tool_activity("notify", "notify", json!([{ "source": "charge" }]));
+        tolerant["tolerates_failed_dependencies"] = json!(true);
+
+        let mut executor = executor(json!({
+            "plan_name": "continue",
+            "activities": [
+                failing,
+                tolerant,
+                tool_activity("ship", "ship", json!([{ "source": "charge" }]))
+            ]
+        }));
+        let mut receiver = executor.subscribe_events();
+        let (_, outcomes) = executor.execute_plan().await.unwrap();
+
+        assert!(outcomes.contains_key("notify"));
+        assert!(!outcomes.contains_key("ship"));
+        assert_eq!(executor.node_failures()["charge"].strategy, OnErrorStrategy::Continue);
+        assert!(collect(&mut receiver).iter().any(|event| matches!(
+            event,
+            ExecutionEvent::NodeFailed { node_id, strategy: OnErrorStrategy::Continue, .. } if node_id == "charge"
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_every_task_runs_and_pipes_outputs() {
+        let mut executor = executor(json!({
+            "plan_name": "tasks",
+            "activities": [task_activity("greet", json!([
+                { "task_to_use": "lookup", "task_parameters": { "name": "{{user_query}}" } },
+                { "task_to_use": "greeting", "task_parameters": { "name": "{{lookup.params.name}}" } }
+            ]))]
+        }));
+        let (_, outcomes) = executor.execute_plan().await.unwrap();
+
+        let outcome: Value = serde_json::from_str(&outcomes["greet"]).unwrap();
+        assert_eq!(outcome["lookup"]["task"], json!("lookup"));
+        assert_eq!(outcome["greeting"]["params"]["name"], json!("test"));
+    }
+
+    #[tokio::test]
+    async fn test_for_each_maps_items_and_loop_stops_on_condition() {
+        let list = task_activity("list", json!([{ "task_to_use": "list", "task_parameters": { "ids": ["a", "b", "c"] } }]));
+        let mut enrich = task_activity("enrich", json!([
+            { "task_to_use": "enrich", "task_parameters": { "id": "{{item}}", "index": "{{item_index}}" } }
+        ]));
+        enrich["dependencies"] = json!([{ "source": "list" }]);
+        enrich["for_each"] = json!({ "items": "{{list.params.ids}}", "max_concurrency": 2 });
+        let mut poll = task_activity("poll", json!([
+            { "task_to_use": "poll", "task_parameters": { "n": "{{iteration}}", "previous": "{{previous.params.n | 0}}" } }
+        ]));
+        poll["loop"] = json!({ "until": "result.params.n >= 3", "max_iterations": 5 });
+
+        let mut executor = executor(json!({ "plan_name": "repeat", "activities": [list, enrich, poll] }));
+        let (_, outcomes) = executor.execute_plan().await.unwrap();
+
+        let enriched: Value = serde_json::from_str(&outcomes["enrich"]).unwrap();
+        let ids: Vec<&Value> = enriched.as_array().unwrap().iter().map(|output| &output["params"]["id"]).collect();
+        assert_eq!(ids, [&json!("a"), &json!("b"), &json!("c")]);
+        assert_eq!(enriched[2]["params"]["index"], json!(2));
+
+        let polled: Value = serde_json::from_str(&outcomes["poll"]).unwrap();
+        assert_eq!(polled["params"], json!({ "n": 3, "previous": 2 }));
+    }
+
+    #[tokio::test]
+    async fn test_sub_workflow_runs_child_plan_and_detects_cycles() {
+        let mut registry = WorkflowRegistry::new();
+        let register = |registry: &mut WorkflowRegistry, name: &str, workflow: Value| {
+            let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+            let workflow: WorkflowPlanInput = serde_json::from_value(workflow).unwrap();
+            registry.register(name, workflow.into(), policies);
         };
+        register(&mut registry, "greeting", json!({
+            "plan_name": "greeting",
+            "activities": [task_activity("greet", json!([{ "task_to_use": "greet", "task_parameters": { "name": "{{input.name}}" } }]))]
+        }));
+        let mut recursive = task_activity("again", json!([{ "task_to_use": "noop", "task_parameters": {} }]));
+        recursive["sub_workflow"] = json!({ "name": "recursive" });
+        register(&mut registry, "recursive", json!({ "plan_name": "recursive", "activities": [recursive.clone()] }));
+        let registry = Arc::new(registry);
 
-        let interpolator = |json_value: &mut Value| {
-            if let Value::Object(map) = json_value {
-                for (_, value) in map.iter_mut() {
-                    if let Value::String(s) = value {
-                        if s.contains("{{") {
-                            if let Some(caps) = re.captures(s) {
-                                if let Some(path) = caps.get(1) {
-                                    match get_interpolated_value(path.as_str()) {
-                                        Ok(interpolated_val) => {
-                                            *value = serde_json::from_str(&interpolated_val)
-                                                .unwrap_or(Value::String(interpolated_val));
-                                        }
-                                        Err(e) => {
-                                            *value = Value::String(format!("ERROR: {}", e));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+        let mut welcome = task_activity("welcome", json!([{ "task_to_use": "noop", "task_parameters": {} }]));
+        welcome["sub_workflow"] = json!({ "name": "greeting", "inputs": { "name": "{{user_query}}" } });
+        let (_, outcomes) = executor(json!({ "plan_name": "parent", "activities": [welcome] }))
+            .with_workflow_registry(registry.clone())
+            .execute_plan()
+            .await
+            .unwrap();
+        let welcome: Value = serde_json::from_str(&outcomes["welcome"]).unwrap();
+        assert_eq!(welcome, json!({ "task": "greet", "params": { "name": "test" } }));
+
+        let error = executor(json!({ "plan_name": "parent", "activities": [recursive] }))
+            .with_workflow_registry(registry)
+            .execute_plan()
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("cycle between workflows: recursive -> recursive"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_sub_workflows_wait_for_their_own_approvals() {
+        let mut update = task_activity("update", json!([{ "task_to_use": "update", "task_parameters": { "name": "{{input.name}}" } }]));
+        update["approval"] = json!({ "prompt": "Update the record?" });
+        let workflow = json!({ "plan_name": "update", "activities": [update] });
+        let mut registry = WorkflowRegistry::new();
+        registry.register(
+            "update",
+            serde_json::from_value::<WorkflowPlanInput>(workflow.clone()).unwrap().into(),
+            ExecutionPolicies::from_workflow_json(&workflow).unwrap(),
+        );
+
+        let child = |id: &str| {
+            let mut activity = task_activity(id, json!([{ "task_to_use": "noop", "task_parameters": {} }]));
+            activity["sub_workflow"] = json!({ "name": "update", "inputs": { "name": id } });
+            activity
+        };
+        let mut executor = executor(json!({ "plan_name": "parent", "activities": [child("first"), child("second")] }))
+            .with_workflow_registry(Arc::new(registry))
+            .with_max_concurrency(2);
+        let approvals = executor.approval_handle();
+        let mut receiver = executor.subscribe_events();
+        let run = tokio::spawn(async move { executor.execute_plan().await });
+
+        // Both children wait for an approval of their own "update" node, announced to the parent's subscribers
+        let mut waiting = Vec::new();
+        while waiting.len() < 2 {
+            if let Some(ExecutionEvent::ApprovalRequested { run_id, node_id, parameters, .. }) = receiver.recv().await {
+                assert_eq!(node_id, "update");
+                waiting.push((run_id, parameters[0]["name"].clone()));
+            }
+        }
+        assert_ne!(waiting[0].0, waiting[1].0);
+        assert_eq!(approvals.pending().len(), 2);
+
+        for (run_id, name) in &waiting {
+            let edited = json!([{ "name": format!("{}-approved", name.as_str().unwrap()) }]);
+            approvals
+                .decide(run_id, "update", ApprovalDecision::Edit { parameters: edited })
+                .unwrap();
+        }
+        let (_, outcomes) = run.await.unwrap().unwrap();
+        for id in ["first", "second"] {
+            let outcome: Value = serde_json::from_str(&outcomes[id]).unwrap();
+            assert_eq!(outcome["params"]["name"], json!(format!("{}-approved", id)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approval_suspends_node_until_decided() {
+        let mut update = task_activity("update", json!([{ "task_to_use": "update", "task_parameters": { "name": "{{user_query}}" } }]));
+        update["approval"] = json!({ "prompt": "Update the record?" });
+        let mut executor = executor(json!({ "plan_name": "approval", "activities": [update] }));
+        let approvals = executor.approval_handle();
+        let run_id = executor.run_id().to_string();
+        let mut receiver = executor.subscribe_events();
+        let run = tokio::spawn(async move { executor.execute_plan().await });
+
+        while let Some(event) = receiver.recv().await {
+            if let ExecutionEvent::ApprovalRequested { node_id, parameters, .. } = event {
+                assert_eq!(node_id, "update");
+                assert_eq!(parameters, json!([{ "name": "test" }]));
+                break;
+            }
+        }
+        assert_eq!(approvals.pending()[0].prompt.as_deref(), Some("Update the record?"));
+        approvals
+            .decide(&run_id, "update", ApprovalDecision::Edit { parameters: json!([{ "name": "edited" }]) })
+            .unwrap();
+
+        let (_, outcomes) = run.await.unwrap().unwrap();
+        let outcome: Value = serde_json::from_str(&outcomes["update"]).unwrap();
+        assert_eq!(outcome["params"], json!({ "name": "edited" }));
+        assert!(approvals.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replay_and_branch_recorded_run() {
+        let workflow = json!({
+            "plan_name": "replay",
+            "activities": [
+                tool_activity("check", "fail", json!([])),
+                tool_activity("proceed", "proceed", json!([{ "source": "check", "condition": "result.ok == true" }]))
+            ]
+        });
+        let mut recorded = executor(workflow);
+        let (_, recorded_outcomes) = recorded.execute_plan().await.unwrap();
+        let recording = recorded.recording().unwrap();
+        assert_eq!(recording.calls.len(), 1);
+        assert_eq!(recording.calls[0].output, Some(json!({ "tool": "fail", "ok": false })));
+
+        let replay = Arc::new(ReplayInvokers::new(&recording));
+        let (_, replayed_outcomes) = PlanExecutor::replay(&recording, replay).unwrap().execute_plan().await.unwrap();
+        assert_eq!(replayed_outcomes, recorded_outcomes);
+
+        // "proceed" was never called in the recording, so it reaches the live fallback.
+        let branch = recording.branch("check", 0, json!({ "tool": "fail", "ok": true })).unwrap();
+        let replay = Arc::new(ReplayInvokers::new(&branch).with_live_fallback(
+            Arc::new(EchoTasks),
+            Arc::new(NoAgents),
+            Arc::new(EchoTools),
+        ));
+        let (_, branched_outcomes) = PlanExecutor::replay(&branch, replay).unwrap().execute_plan().await.unwrap();
+        assert_eq!(branched_outcomes["proceed"], json!({ "tool": "proceed", "ok": true }).to_string());
+    }
+
+    #[tokio::test]
+    async fn test_replay_answers_approvals_with_recorded_decisions() {
+        let mut update = task_activity("update", json!([{ "task_to_use": "update", "task_parameters": { "name": "{{user_query}}" } }]));
+        update["approval"] = json!({ "prompt": "Update the record?" });
+        let mut recorded = executor(json!({ "plan_name": "approval", "activities": [update] }));
+        let approvals = recorded.approval_handle();
+        let run_id = recorded.run_id().to_string();
+        let run = tokio::spawn(async move {
+            let outcomes = recorded.execute_plan().await.map(|(_, outcomes)| outcomes);
+            (outcomes, recorded.recording())
+        });
+        while approvals.pending().is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let edit = ApprovalDecision::Edit { parameters: json!([{ "name": "edited" }]) };
+        approvals.decide(&run_id, "update", edit.clone()).unwrap();
+        let (recorded_outcomes, recording) = run.await.unwrap();
+        let (recorded_outcomes, recording) = (recorded_outcomes.unwrap(), recording.unwrap());
+        assert_eq!(recording.approvals, [RecordedApproval { node_id: "update".to_string(), decision: edit }]);
+
+        // The edited parameters are applied again, so the recorded task call answers the replay
+        let replay = Arc::new(ReplayInvokers::new(&recording));
+        let (_, replayed_outcomes) = PlanExecutor::replay(&recording, replay).unwrap().execute_plan().await.unwrap();
+        assert_eq!(replayed_outcomes, recorded_outcomes);
+
+        // Without a recorded decision, the approval is rejected instead of waited for
+        let mut undecided = recording.clone();
+        undecided.approvals.clear();
+        let replay = Arc::new(ReplayInvokers::new(&undecided));
+        let error = PlanExecutor::replay(&undecided, replay).unwrap().execute_plan().await.unwrap_err();
+        assert!(error.to_string().contains("no decision recorded"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_independent_nodes_run_concurrently_within_limit() {
+        let workflow: WorkflowPlanInput = serde_json::from_value(json!({
+            "plan_name": "fan_out",
+            "activities": [
+                tool_activity("a", "a", json!([])),
+                tool_activity("b", "b", json!([])),
+                tool_activity("c", "c", json!([])),
+                tool_activity("d", "d", json!([])),
+                tool_activity("join", "join", json!([{ "source": "a" }, { "source": "b" }, { "source": "c" }, { "source": "d" }]))
+            ]
+        }))
+        .unwrap();
+        let tools = Arc::new(DelayedTools {
+            delay: Duration::from_millis(100),
+            ..Default::default()
+        });
+        let mut executor = PlanExecutor::new(
+            workflow.into(),
+            Arc::new(EchoTasks),
+            Arc::new(NoAgents),
+            tools.clone(),
+            "test".to_string(),
+        )
+        .with_max_concurrency(2);
+
+        let (final_outcome, outcomes) = executor.execute_plan().await.unwrap();
+
+        assert_eq!(outcomes.len(), 5);
+        assert_eq!(final_outcome, json!({ "tool": "join", "ok": true }).to_string());
+        assert_eq!(tools.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_run_resumes_from_checkpoint() {
+        use crate::graph::checkpoint::InMemoryCheckpointStore;
+
+        /// Echoes every tool, including `broken`, and remembers which ones were called.
+        #[derive(Default)]
+        struct RepairedTools(std::sync::Mutex<Vec<String>>);
+
+        #[async_trait]
+        impl ToolInvoker for RepairedTools {
+            async fn invoke(&self, tool_id: String, _params: &Value) -> anyhow::Result<Value> {
+                self.0.lock().unwrap().push(tool_id.clone());
+                Ok(json!({ "tool": tool_id, "ok": true }))
+            }
+        }
+
+        let workflow = json!({
+            "plan_name": "resume",
+            "activities": [
+                tool_activity("reserve", "reserve", json!([])),
+                tool_activity("charge", "broken", json!([{ "source": "reserve" }]))
+            ]
+        });
+        let store = Arc::new(InMemoryCheckpointStore::new());
+        let error = executor(workflow.clone())
+            .with_checkpoint_store(store.clone(), "run-1")
+            .execute_plan()
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("broken"), "{}", error);
+        let checkpoint = store.load("run-1").await.unwrap().unwrap();
+        assert!(matches!(checkpoint.status, CheckpointStatus::Failed(_)));
+        assert!(checkpoint.activities_outcome.contains_key("reserve"));
+
+        let tools = Arc::new(RepairedTools::default());
+        let mut resumed = PlanExecutor::resume("run-1", store.clone(), Arc::new(EchoTasks), Arc::new(NoAgents), tools.clone())
+            .await
+            .unwrap();
+        let (_, outcomes) = resumed.execute_plan().await.unwrap();
+        assert_eq!(*tools.0.lock().unwrap(), ["broken"]);
+        assert_eq!(outcomes["reserve"], json!({ "tool": "reserve", "ok": true }).to_string());
+        assert_eq!(store.load("run-1").await.unwrap().unwrap().status, CheckpointStatus::Completed);
+
+        // The same run id cannot be reused by another plan
+        let other = json!({ "plan_name": "other", "activities": [tool_activity("refund", "refund", json!([]))] });
+        let error = executor(other)
+            .with_checkpoint_store(store, "run-1")
+            .execute_plan()
+            .await
+            .unwrap_err();
+        assert!(matches!(error, PlanExecutorError::CheckpointMismatch(_, _)), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_failing_activity_is_retried_with_backoff() {
+        /// Fails with a server error until it was called `failures + 1` times.
+        struct FlakyTools {
+            failures: usize,
+            calls: std::sync::atomic::AtomicUsize,
+        }
+
+        #[async_trait]
+        impl ToolInvoker for FlakyTools {
+            async fn invoke(&self, tool_id: String, params: &Value) -> anyhow::Result<Value> {
+                let call = self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                if call < self.failures {
+                    anyhow::bail!("503 service unavailable");
                 }
+                EchoTools.invoke(tool_id, params).await
             }
+        }
+
+        let mut charge = tool_activity("charge", "charge", json!([]));
+        charge["retry_policy"] = json!({ "max_attempts": 3, "initial_backoff_ms": 50, "backoff_multiplier": 2.0 });
+        let workflow = json!({ "plan_name": "retry", "activities": [charge] });
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let workflow: WorkflowPlanInput = serde_json::from_value(workflow).unwrap();
+        let tools = Arc::new(FlakyTools {
+            failures: 2,
+            calls: Default::default(),
+        });
+        let mut executor = PlanExecutor::new(
+            workflow.into(),
+            Arc::new(EchoTasks),
+            Arc::new(NoAgents),
+            tools.clone(),
+            "test".to_string(),
+        )
+        .with_execution_policies(policies);
+
+        let started = Instant::now();
+        let (_, outcomes) = executor.execute_plan().await.unwrap();
+
+        assert_eq!(outcomes["charge"], json!({ "tool": "charge", "ok": true }).to_string());
+        // Waited 50 ms, then 100 ms, between the three attempts
+        assert!(started.elapsed() >= Duration::from_millis(150), "{:?}", started.elapsed());
+        let attempts = &executor.attempt_history()["charge"];
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(attempts[0].error_kind, Some(ErrorKind::Server));
+        assert!(attempts[2].error.is_none());
+    }
+
+    fn delayed_executor(workflow: Value, delay: Duration) -> PlanExecutor {
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let workflow: WorkflowPlanInput = serde_json::from_value(workflow).unwrap();
+        let tools = DelayedTools {
+            delay,
+            ..Default::default()
         };
+        PlanExecutor::new(
+            workflow.into(),
+            Arc::new(EchoTasks),
+            Arc::new(NoAgents),
+            Arc::new(tools),
+            "test".to_string(),
+        )
+        .with_execution_policies(policies)
+    }
 
-        if let Some(tool_params) = &mut hydrated_activity.tool_parameters {
-            interpolator(tool_params);
-        }
+    #[tokio::test]
+    async fn test_activity_times_out() {
+        let mut slow = tool_activity("slow", "slow", json!([]));
+        slow["timeout_ms"] = json!(50);
+        let mut executor = delayed_executor(json!({ "plan_name": "timeout", "activities": [slow] }), Duration::from_secs(5));
+
+        let started = Instant::now();
+        let error = executor.execute_plan().await.unwrap_err();
+        assert_eq!(error, PlanExecutorError::ActivityTimedOut("slow".to_string(), 50));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(executor.attempt_history()["slow"][0].error_kind, Some(ErrorKind::Timeout));
+    }
+
+    #[tokio::test]
+    async fn test_plan_deadline_keeps_completed_outcomes() {
+        let mut executor = delayed_executor(
+            json!({
+                "plan_name": "deadline",
+                "plan_timeout_ms": 150,
+                "activities": [
+                    tool_activity("first", "first", json!([])),
+                    tool_activity("second", "second", json!([{ "source": "first" }]))
+                ]
+            }),
+            Duration::from_millis(100),
+        );
 
-        if let Some(tasks) = &mut hydrated_activity.tasks {
-            for task_config in tasks.iter_mut() {
-                interpolator(&mut task_config.task_parameters);
+        match executor.execute_plan().await.unwrap_err() {
+            PlanExecutorError::TimedOut { timeout_ms, activities_outcome } => {
+                assert_eq!(timeout_ms, 150);
+                assert_eq!(activities_outcome.keys().collect::<Vec<_>>(), ["first"]);
             }
+            other => panic!("unexpected error: {}", other),
         }
+    }
 
-        if let Some(agent_context) = &mut hydrated_activity.agent_context {
-            interpolator(agent_context);
+    #[tokio::test]
+    async fn test_cancelled_plan_stops_running_activities() {
+        let mut executor = delayed_executor(
+            json!({ "plan_name": "cancel", "activities": [tool_activity("slow", "slow", json!([]))] }),
+            Duration::from_secs(5),
+        );
+        let cancellation = executor.cancellation_handle();
+        let run = tokio::spawn(async move { executor.execute_plan().await });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancellation.cancel();
+        let error = tokio::time::timeout(Duration::from_secs(1), run).await.unwrap().unwrap().unwrap_err();
+        assert!(cancellation.is_cancelled());
+        assert!(
+            matches!(&error, PlanExecutorError::Cancelled { activities_outcome } if activities_outcome.is_empty()),
+            "{}",
+            error
+        );
+    }
+
+    /// Echoes tools after a delay depending on the tool: `slow` takes seconds, `broken` fails,
+    /// and remembers which ones were called.
+    #[derive(Default)]
+    struct ScriptedTools(std::sync::Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl ToolInvoker for ScriptedTools {
+        async fn invoke(&self, tool_id: String, params: &Value) -> anyhow::Result<Value> {
+            self.0.lock().unwrap().push(tool_id.clone());
+            match tool_id.as_str() {
+                "slow" => tokio::time::sleep(Duration::from_secs(5)).await,
+                "broken" => tokio::time::sleep(Duration::from_millis(50)).await,
+                _ => {}
+            }
+            EchoTools.invoke(tool_id, params).await
         }
+    }
 
-        debug!("Hydrated Activity: {:?}", hydrated_activity);
+    #[tokio::test]
+    async fn test_failure_aborts_running_siblings() {
+        let workflow: WorkflowPlanInput = serde_json::from_value(json!({
+            "plan_name": "fail",
+            "activities": [
+                tool_activity("charge", "broken", json!([])),
+                tool_activity("notify", "slow", json!([]))
+            ]
+        }))
+        .unwrap();
+        let mut executor = PlanExecutor::new(
+            workflow.into(),
+            Arc::new(EchoTasks),
+            Arc::new(NoAgents),
+            Arc::new(ScriptedTools::default()),
+            "test".to_string(),
+        )
+        .with_max_concurrency(2);
 
-        Ok(hydrated_activity)
+        let started = Instant::now();
+        executor.execute_plan().await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let failure = &executor.node_failures()["charge"];
+        assert_eq!(failure.strategy, OnErrorStrategy::Fail);
+        assert_eq!(failure.aborted, ["notify"]);
+        assert!(executor.attempt_history().contains_key("charge"));
+    }
+
+    #[tokio::test]
+    async fn test_compensation_reports_aborted_siblings() {
+        let mut reserve = tool_activity("reserve", "reserve", json!([]));
+        reserve["compensation"] = json!({ "type": "tool", "tool_to_use": "release" });
+        let mut charge = tool_activity("charge", "broken", json!([]));
+        charge["on_error"] = json!({ "strategy": "compensate" });
+        let workflow = json!({
+            "plan_name": "compensate",
+            "activities": [reserve, charge, tool_activity("notify", "slow", json!([]))]
+        });
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let workflow: WorkflowPlanInput = serde_json::from_value(workflow).unwrap();
+        let tools = Arc::new(ScriptedTools::default());
+        let mut executor = PlanExecutor::new(
+            workflow.into(),
+            Arc::new(EchoTasks),
+            Arc::new(NoAgents),
+            tools.clone(),
+            "test".to_string(),
+        )
+        .with_execution_policies(policies)
+        .with_max_concurrency(3);
+
+        let started = Instant::now();
+        executor.execute_plan().await.unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(1));
+
+        let failure = &executor.node_failures()["charge"];
+        assert_eq!(failure.compensated, ["reserve"]);
+        assert_eq!(failure.aborted, ["notify"]);
+        assert!(tools.0.lock().unwrap().contains(&"release".to_string()));
     }
-}
\ No newline at end of file
+}
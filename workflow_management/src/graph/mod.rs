@@ -1,3 +1,12 @@
 pub mod a_star;
+pub mod approval;
+pub mod checkpoint;
 pub mod config;
+pub mod execution_event;
+pub mod execution_policy;
 pub mod graph_orchestrator;
+pub mod recording;
+pub mod validation;
+pub mod workflow_registry;
+
+pub use validation::{validate, validate_plan, Capabilities, ValidationError, ValidationIssue};
@@ -0,0 +1,273 @@
+use agent_models::graph::graph_definition::Graph;
+
+use crate::agent_communication::agent_invoker::{AgentAnswer, AgentInvoker};
+use crate::graph::approval::ApprovalDecision;
+use crate::graph::execution_policy::ExecutionPolicies;
+use crate::tasks::task_invoker::TaskInvoker;
+use crate::tools::tool_invoker::ToolInvoker;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RecordingError {
+    #[error("Node '{node_id}' made no call number {call_index} in the recording")]
+    CallNotFound { node_id: String, call_index: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvokerKind {
+    Agent,
+    Tool,
+    Task,
+}
+
+/// One call made by the executor to an invoker, with its response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub node_id: String,
+    pub invoker: InvokerKind,
+    /// Agent, tool or task id.
+    pub target: String,
+    /// Parameters of a tool or task, or `{"message", "skill"}` for an agent.
+    pub request: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Decision taken by a human on an activity waiting for approval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedApproval {
+    pub node_id: String,
+    pub decision: ApprovalDecision,
+}
+
+/// Every invoker call of a plan run, with what is needed to run the same plan again offline.
+/// Obtain it with `PlanExecutor::recording` and replay it with `PlanExecutor::replay`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecording {
+    pub run_id: String,
+    pub graph: Value,
+    pub user_query: String,
+    #[serde(default)]
+    pub execution_policies: ExecutionPolicies,
+    /// Calls in the order they completed.
+    pub calls: Vec<RecordedCall>,
+    /// Approval decisions in the order they were taken, given again to the same nodes on replay.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approvals: Vec<RecordedApproval>,
+}
+
+impl RunRecording {
+    pub fn graph(&self) -> anyhow::Result<Graph> {
+        Ok(serde_json::from_value(self.graph.clone())?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Copy of the recording where call number `call_index` (starting at 0) of `node_id` answered `output`.
+    ///
+    /// Replaying the copy re-runs the nodes before `node_id` exactly and hands the edited response
+    /// to `node_id`. Nodes whose inputs change as a consequence make calls that were never recorded,
+    /// answered by the live fallback of the `ReplayInvokers`, if any.
+    pub fn branch(&self, node_id: &str, call_index: usize, output: Value) -> Result<Self, RecordingError> {
+        let mut branch = self.clone();
+        let call = branch
+            .calls
+            .iter_mut()
+            .filter(|call| call.node_id == node_id)
+            .nth(call_index)
+            .ok_or_else(|| RecordingError::CallNotFound {
+                node_id: node_id.to_string(),
+                call_index,
+            })?;
+        call.output = Some(output);
+        call.error = None;
+        Ok(branch)
+    }
+}
+
+/// Collects the calls made by the invokers of a plan and of its sub-workflows.
+#[derive(Clone, Default)]
+pub(crate) struct InvocationRecorder {
+    calls: Arc<Mutex<Vec<RecordedCall>>>,
+    approvals: Arc<Mutex<Vec<RecordedApproval>>>,
+}
+
+impl InvocationRecorder {
+    pub(crate) fn record(
+        &self,
+        node_id: &str,
+        invoker: InvokerKind,
+        target: &str,
+        request: Value,
+        result: &anyhow::Result<Value>,
+    ) {
+        let (output, error) = match result {
+            Ok(output) => (Some(output.clone()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        self.calls.lock().unwrap().push(RecordedCall {
+            node_id: node_id.to_string(),
+            invoker,
+            target: target.to_string(),
+            request,
+            output,
+            error,
+        });
+    }
+
+    pub(crate) fn calls(&self) -> Vec<RecordedCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    pub(crate) fn record_approval(&self, node_id: &str, decision: &ApprovalDecision) {
+        self.approvals.lock().unwrap().push(RecordedApproval {
+            node_id: node_id.to_string(),
+            decision: decision.clone(),
+        });
+    }
+
+    pub(crate) fn approvals(&self) -> Vec<RecordedApproval> {
+        self.approvals.lock().unwrap().clone()
+    }
+}
+
+/// Request recorded for an agent call.
+pub(crate) fn agent_request(message: &str, skill: &str) -> Value {
+    json!({ "message": message, "skill": skill })
+}
+
+type ReplayKey = (InvokerKind, String, String);
+
+/// Agent, tool and task invoker answering from a `RunRecording` instead of live services.
+///
+/// A call is answered by the first unused recorded call with the same invoker, target and
+/// request. Calls missing from the recording go to the live fallback when one is set,
+/// and fail otherwise.
+pub struct ReplayInvokers {
+    responses: Mutex<HashMap<ReplayKey, VecDeque<Result<Value, String>>>>,
+    fallback: Option<LiveInvokers>,
+}
+
+struct LiveInvokers {
+    task_invoker: Arc<dyn TaskInvoker>,
+    agent_invoker: Arc<dyn AgentInvoker>,
+    tool_invoker: Arc<dyn ToolInvoker>,
+}
+
+impl ReplayInvokers {
+    pub fn new(recording: &RunRecording) -> Self {
+        let mut responses: HashMap<ReplayKey, VecDeque<Result<Value, String>>> = HashMap::new();
+        for call in &recording.calls {
+            let response = match (&call.output, &call.error) {
+                (_, Some(error)) => Err(error.clone()),
+                (output, None) => Ok(output.clone().unwrap_or(Value::Null)),
+            };
+            responses
+                .entry((call.invoker, call.target.clone(), call.request.to_string()))
+                .or_default()
+                .push_back(response);
+        }
+        Self {
+            responses: Mutex::new(responses),
+            fallback: None,
+        }
+    }
+
+    /// Sends the calls missing from the recording to live invokers, e.g. after a `RunRecording::branch`.
+    pub fn with_live_fallback(
+        mut self,
+        task_invoker: Arc<dyn TaskInvoker>,
+        agent_invoker: Arc<dyn AgentInvoker>,
+        tool_invoker: Arc<dyn ToolInvoker>,
+    ) -> Self {
+        self.fallback = Some(LiveInvokers {
+            task_invoker,
+            agent_invoker,
+            tool_invoker,
+        });
+        self
+    }
+
+    /// Recorded response of a call, or `None` when the recording has no unused match.
+    fn replay(&self, invoker: InvokerKind, target: &str, request: &Value) -> Option<anyhow::Result<Value>> {
+        let key = (invoker, target.to_string(), request.to_string());
+        let response = self.responses.lock().unwrap().get_mut(&key)?.pop_front()?;
+        Some(response.map_err(|e| anyhow::anyhow!(e)))
+    }
+
+    fn not_recorded(invoker: InvokerKind, target: &str, request: &Value) -> anyhow::Error {
+        anyhow::anyhow!(
+            "No recorded response for {:?} '{}' with request {}",
+            invoker,
+            target,
+            request
+        )
+    }
+}
+
+#[async_trait]
+impl ToolInvoker for ReplayInvokers {
+    async fn invoke(&self, tool_id: String, params: &Value) -> anyhow::Result<Value> {
+        if let Some(response) = self.replay(InvokerKind::Tool, &tool_id, params) {
+            return response;
+        }
+        match &self.fallback {
+            Some(live) => live.tool_invoker.invoke(tool_id, params).await,
+            None => Err(Self::not_recorded(InvokerKind::Tool, &tool_id, params)),
+        }
+    }
+}
+
+#[async_trait]
+impl TaskInvoker for ReplayInvokers {
+    async fn invoke(&self, task_id: String, params: &Value) -> anyhow::Result<Value> {
+        if let Some(response) = self.replay(InvokerKind::Task, &task_id, params) {
+            return response;
+        }
+        match &self.fallback {
+            Some(live) => live.task_invoker.invoke(task_id, params).await,
+            None => Err(Self::not_recorded(InvokerKind::Task, &task_id, params)),
+        }
+    }
+}
+
+#[async_trait]
+impl AgentInvoker for ReplayInvokers {
+    async fn interact(&self, agent_id: String, message: String, skill: String) -> anyhow::Result<Value> {
+        self.delegate(agent_id, message, skill).await.map(|answer| answer.response)
+    }
+
+    async fn delegate(&self, agent_id: String, message: String, skill: String) -> anyhow::Result<AgentAnswer> {
+        let request = agent_request(&message, &skill);
+        if let Some(response) = self.replay(InvokerKind::Agent, &agent_id, &request) {
+            return response.map(|response| AgentAnswer { agent_id, response });
+        }
+        match &self.fallback {
+            Some(live) => live.agent_invoker.delegate(agent_id, message, skill).await,
+            None => Err(Self::not_recorded(InvokerKind::Agent, &agent_id, &request)),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
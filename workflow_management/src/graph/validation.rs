@@ -0,0 +1,635 @@
+use agent_models::graph::graph_definition::{Activity, ActivityType, Graph, NodeType};
+
+use crate::graph::execution_policy::{CompensationAction, ExecutionPolicies, OnErrorStrategy, WorkflowReference};
+
+use crate::tasks::condition_evaluator::parse_condition;
+use crate::tasks::interpolation::{placeholder_expressions, Placeholder, RESERVED_BINDINGS, USER_QUERY_REFERENCE};
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::fmt;
+use thiserror::Error;
+
+/// Resources a plan may refer to. A `None` set is not checked.
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    pub agents: Option<HashSet<String>>,
+    pub tools: Option<HashSet<String>>,
+    pub tasks: Option<HashSet<String>>,
+    /// Names of the saved workflows `sub_workflow` activities may run.
+    pub workflows: Option<HashSet<String>>,
+}
+
+impl Capabilities {
+    pub fn with_agents(mut self, agents: impl IntoIterator<Item = String>) -> Self {
+        self.agents = Some(agents.into_iter().collect());
+        self
+    }
+
+    pub fn with_tools(mut self, tools: impl IntoIterator<Item = String>) -> Self {
+        self.tools = Some(tools.into_iter().collect());
+        self
+    }
+
+    pub fn with_tasks(mut self, tasks: impl IntoIterator<Item = String>) -> Self {
+        self.tasks = Some(tasks.into_iter().collect());
+        self
+    }
+
+    pub fn with_workflows(mut self, workflows: impl IntoIterator<Item = String>) -> Self {
+        self.workflows = Some(workflows.into_iter().collect());
+        self
+    }
+}
+
+/// A structural problem found in a workflow graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum ValidationIssue {
+    DanglingEdge { source: String, target: String, missing: String },
+    Cycle { nodes: Vec<String> },
+    UnreachableNode { node: String },
+    MissingField { node: String, field: String },
+    InvalidPlaceholder { node: String, message: String },
+    UnknownReference { node: String, reference: String },
+    NonAncestorReference { node: String, reference: String },
+    InvalidCondition { source: String, target: String, message: String },
+    UnknownAgent { node: String, agent_id: String },
+    UnknownTool { node: String, tool_id: String },
+    UnknownTask { node: String, task_id: String },
+    UnknownFallback { node: String, fallback: String },
+    UnknownWorkflow { node: String, workflow: String },
+    UnknownPolicyTarget { node: String },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::DanglingEdge { source, target, missing } => write!(
+                f,
+                "edge '{}' -> '{}' refers to unknown activity '{}'",
+                source, target, missing
+            ),
+            ValidationIssue::Cycle { nodes } => {
+                write!(f, "dependency cycle between activities {}", nodes.join(", "))
+            }
+            ValidationIssue::UnreachableNode { node } => {
+                write!(f, "activity '{}' can never start: it only depends on a cycle", node)
+            }
+            ValidationIssue::MissingField { node, field } => {
+                write!(f, "activity '{}' is missing '{}'", node, field)
+            }
+            ValidationIssue::InvalidPlaceholder { node, message } => {
+                write!(f, "activity '{}': {}", node, message)
+            }
+            ValidationIssue::UnknownReference { node, reference } => write!(
+                f,
+                "activity '{}' references '{{{{{}}}}}', which is not an activity",
+                node, reference
+            ),
+            ValidationIssue::NonAncestorReference { node, reference } => write!(
+                f,
+                "activity '{}' references '{{{{{}}}}}' but does not depend on it",
+                node, reference
+            ),
+            ValidationIssue::InvalidCondition { source, target, message } => write!(
+                f,
+                "condition on edge '{}' -> '{}' is invalid: {}",
+                source, target, message
+            ),
+            ValidationIssue::UnknownAgent { node, agent_id } => {
+                write!(f, "activity '{}' uses unknown agent '{}'", node, agent_id)
+            }
+            ValidationIssue::UnknownTool { node, tool_id } => {
+                write!(f, "activity '{}' uses unknown tool '{}'", node, tool_id)
+            }
+            ValidationIssue::UnknownTask { node, task_id } => {
+                write!(f, "activity '{}' uses unknown task '{}'", node, task_id)
+            }
+            ValidationIssue::UnknownFallback { node, fallback } => write!(
+                f,
+                "activity '{}' falls back to '{}', which is not an activity",
+                node, fallback
+            ),
+            ValidationIssue::UnknownWorkflow { node, workflow } => {
+                write!(f, "activity '{}' runs unknown workflow '{}'", node, workflow)
+            }
+            ValidationIssue::UnknownPolicyTarget { node } => {
+                write!(f, "execution policies are declared for '{}', which is not an activity", node)
+            }
+        }
+    }
+}
+
+#[derive(Error, Debug, PartialEq)]
+#[error("invalid workflow graph:\n{}", format_issues(.issues))]
+pub struct ValidationError {
+    pub issues: Vec<ValidationIssue>,
+}
+
+fn format_issues(issues: &[ValidationIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| format!("- {}", issue))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Checks a graph before execution and reports every problem found, not only the first.
+pub fn validate(graph: &Graph, capabilities: &Capabilities) -> Result<(), ValidationError> {
+    let mut issues = Vec::new();
+
+    let node_ids: BTreeSet<&str> = graph.nodes.keys().map(String::as_str).collect();
+
+    // Adjacency over the edges whose both ends exist.
+    let mut successors: BTreeMap<&str, Vec<&str>> = node_ids.iter().map(|id| (*id, Vec::new())).collect();
+    let mut predecessors: BTreeMap<&str, Vec<&str>> = successors.clone();
+    for edge in &graph.edges {
+        let mut dangling = false;
+        for end in [&edge.source, &edge.target] {
+            if !node_ids.contains(end.as_str()) {
+                dangling = true;
+                issues.push(ValidationIssue::DanglingEdge {
+                    source: edge.source.clone(),
+                    target: edge.target.clone(),
+                    missing: end.clone(),
+                });
+            }
+        }
+        if dangling {
+            continue;
+        }
+        successors.get_mut(edge.source.as_str()).unwrap().push(edge.target.as_str());
+        predecessors.get_mut(edge.target.as_str()).unwrap().push(edge.source.as_str());
+
+        if let Some(condition) = &edge.condition {
+            if let Err(e) = parse_condition(condition) {
+                issues.push(ValidationIssue::InvalidCondition {
+                    source: edge.source.clone(),
+                    target: edge.target.clone(),
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
+
+    let cycles = find_cycles(&successors);
+    let in_cycle: HashSet<&str> = cycles.iter().flatten().copied().collect();
+    for cycle in &cycles {
+        issues.push(ValidationIssue::Cycle {
+            nodes: cycle.iter().map(|id| id.to_string()).collect(),
+        });
+    }
+
+    let roots = node_ids.iter().copied().filter(|id| predecessors[id].is_empty());
+    let reachable = reachable_from(roots, &successors);
+    for id in &node_ids {
+        if !reachable.contains(id) && !in_cycle.contains(id) {
+            issues.push(ValidationIssue::UnreachableNode { node: id.to_string() });
+        }
+    }
+
+    for id in &node_ids {
+        let NodeType::Activity(activity) = &graph.nodes[*id].node_type;
+        check_activity(id, activity, capabilities, &mut issues);
+
+        let ancestors = reachable_from(predecessors[id].iter().copied(), &predecessors);
+        let (references, invalid_placeholders) = referenced_activities(activity);
+        for message in invalid_placeholders {
+            issues.push(ValidationIssue::InvalidPlaceholder {
+                node: id.to_string(),
+                message,
+            });
+        }
+        // Tasks of the same activity may reference each other's outputs.
+        let task_ids: HashSet<&str> = activity
+            .tasks
+            .iter()
+            .flatten()
+            .filter_map(|task| task.task_to_use.as_deref())
+            .collect();
+        for reference in references {
+            if !node_ids.contains(reference.as_str()) {
+                if reference == USER_QUERY_REFERENCE
+                    || RESERVED_BINDINGS.contains(&reference.as_str())
+                    || task_ids.contains(reference.as_str())
+                {
+                    continue;
+                }
+                issues.push(ValidationIssue::UnknownReference {
+                    node: id.to_string(),
+                    reference,
+                });
+            } else if !ancestors.contains(reference.as_str()) {
+                issues.push(ValidationIssue::NonAncestorReference {
+                    node: id.to_string(),
+                    reference,
+                });
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError { issues })
+    }
+}
+
+/// Checks a graph along with its execution policies, which may refer to activities,
+/// tools, tasks, agents and saved workflows too.
+pub fn validate_plan(
+    graph: &Graph,
+    policies: &ExecutionPolicies,
+    capabilities: &Capabilities,
+) -> Result<(), ValidationError> {
+    let mut issues = validate(graph, capabilities).err().map(|e| e.issues).unwrap_or_default();
+
+    let mut node_ids: Vec<&String> = policies.activities.keys().collect();
+    node_ids.sort();
+    for id in node_ids {
+        let policy = &policies.activities[id];
+        let node = || id.to_string();
+        if !graph.nodes.contains_key(id) {
+            issues.push(ValidationIssue::UnknownPolicyTarget { node: node() });
+            continue;
+        }
+
+        if let Some(OnErrorStrategy::Fallback { node: fallback }) = &policy.on_error {
+            if !graph.nodes.contains_key(fallback) {
+                issues.push(ValidationIssue::UnknownFallback {
+                    node: node(),
+                    fallback: fallback.clone(),
+                });
+            }
+        }
+
+        if let Some(sub_workflow) = &policy.sub_workflow {
+            if let WorkflowReference::Name(name) = &sub_workflow.workflow {
+                if capabilities.workflows.as_ref().is_some_and(|workflows| !workflows.contains(name)) {
+                    issues.push(ValidationIssue::UnknownWorkflow {
+                        node: node(),
+                        workflow: name.clone(),
+                    });
+                }
+            }
+        }
+
+        let compensation_parameters = match &policy.compensation {
+            Some(CompensationAction::Tool { tool_to_use, tool_parameters }) => {
+                if capabilities.tools.as_ref().is_some_and(|tools| !tools.contains(tool_to_use)) {
+                    issues.push(ValidationIssue::UnknownTool {
+                        node: node(),
+                        tool_id: tool_to_use.clone(),
+                    });
+                }
+                Some(tool_parameters)
+            }
+            Some(CompensationAction::Task { task_to_use, task_parameters }) => {
+                if capabilities.tasks.as_ref().is_some_and(|tasks| !tasks.contains(task_to_use)) {
+                    issues.push(ValidationIssue::UnknownTask {
+                        node: node(),
+                        task_id: task_to_use.clone(),
+                    });
+                }
+                Some(task_parameters)
+            }
+            Some(CompensationAction::Agent { agent_id, skill_to_use, .. }) => {
+                if skill_to_use.is_none()
+                    && capabilities.agents.as_ref().is_some_and(|agents| !agents.contains(agent_id))
+                {
+                    issues.push(ValidationIssue::UnknownAgent {
+                        node: node(),
+                        agent_id: agent_id.clone(),
+                    });
+                }
+                None
+            }
+            None => None,
+        };
+        // Compensations run once the plan has failed, so they may reference any activity.
+        let (references, invalid_placeholders) = placeholder_roots(compensation_parameters.into_iter().collect());
+        for message in invalid_placeholders {
+            issues.push(ValidationIssue::InvalidPlaceholder { node: node(), message });
+        }
+        for reference in references {
+            if !graph.nodes.contains_key(&reference)
+                && reference != USER_QUERY_REFERENCE
+                && !RESERVED_BINDINGS.contains(&reference.as_str())
+            {
+                issues.push(ValidationIssue::UnknownReference { node: node(), reference });
+            }
+        }
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError { issues })
+    }
+}
+
+fn check_activity(id: &str, activity: &Activity, capabilities: &Capabilities, issues: &mut Vec<ValidationIssue>) {
+    let missing = |field: &str| ValidationIssue::MissingField {
+        node: id.to_string(),
+        field: field.to_string(),
+    };
+
+    match activity.activity_type {
+        // With a skill, the agent invoker replaces a missing or unknown agent by one advertising it.
+        ActivityType::DelegationAgent => match &activity.assigned_agent_id_preference {
+            Some(agent_id) => {
+                if activity.skill_to_use.is_none()
+                    && capabilities.agents.as_ref().is_some_and(|agents| !agents.contains(agent_id))
+                {
+                    issues.push(ValidationIssue::UnknownAgent {
+                        node: id.to_string(),
+                        agent_id: agent_id.clone(),
+                    });
+                }
+            }
+            None if activity.skill_to_use.is_some() => {}
+            None => issues.push(missing("assigned_agent_id_preference")),
+        },
+        ActivityType::DirectToolUse => match &activity.tool_to_use {
+            Some(tool_id) => {
+                if capabilities.tools.as_ref().is_some_and(|tools| !tools.contains(tool_id)) {
+                    issues.push(ValidationIssue::UnknownTool {
+                        node: id.to_string(),
+                        tool_id: tool_id.clone(),
+                    });
+                }
+            }
+            None => issues.push(missing("tool_to_use")),
+        },
+        ActivityType::DirectTaskExecution => {
+            let tasks = activity.tasks.as_deref().unwrap_or_default();
+            if tasks.is_empty() {
+                issues.push(missing("tasks"));
+            }
+            for task in tasks {
+                match &task.task_to_use {
+                    Some(task_id) => {
+                        if capabilities.tasks.as_ref().is_some_and(|known| !known.contains(task_id)) {
+                            issues.push(ValidationIssue::UnknownTask {
+                                node: id.to_string(),
+                                task_id: task_id.clone(),
+                            });
+                        }
+                    }
+                    None => issues.push(missing("task_to_use")),
+                }
+            }
+        }
+    }
+}
+
+/// Roots referenced by the `{{activity_id...}}` placeholders in the parameters of an activity,
+/// along with the placeholders that could not be parsed.
+fn referenced_activities(activity: &Activity) -> (BTreeSet<String>, Vec<String>) {
+    let mut values: Vec<&Value> = Vec::new();
+    values.extend(activity.tool_parameters.as_ref());
+    values.extend(activity.agent_context.as_ref());
+    if let Some(tasks) = &activity.tasks {
+        values.extend(tasks.iter().map(|task| &task.task_parameters));
+    }
+
+    placeholder_roots(values)
+}
+
+/// Roots of the placeholders found in `values`, along with the placeholders that could not be parsed.
+fn placeholder_roots(values: Vec<&Value>) -> (BTreeSet<String>, Vec<String>) {
+    let mut references = BTreeSet::new();
+    let mut invalid = Vec::new();
+    let mut pending = values;
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::String(s) => {
+                for expression in placeholder_expressions(s) {
+                    match Placeholder::parse(expression) {
+                        Ok(placeholder) => {
+                            references.insert(placeholder.root);
+                        }
+                        Err(e) => invalid.push(e.to_string()),
+                    }
+                }
+            }
+            Value::Array(items) => pending.extend(items),
+            Value::Object(map) => pending.extend(map.values()),
+            _ => {}
+        }
+    }
+    (references, invalid)
+}
+
+fn reachable_from<'a>(
+    starts: impl IntoIterator<Item = &'a str>,
+    adjacency: &BTreeMap<&'a str, Vec<&'a str>>,
+) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<&str> = starts.into_iter().collect();
+    while let Some(id) = queue.pop_front() {
+        if seen.insert(id) {
+            queue.extend(adjacency[id].iter().copied());
+        }
+    }
+    seen
+}
+
+/// Strongly connected components with more than one node, or with a self-loop (Tarjan).
+fn find_cycles<'a>(successors: &BTreeMap<&'a str, Vec<&'a str>>) -> Vec<Vec<&'a str>> {
+    struct Tarjan<'a, 'g> {
+        successors: &'g BTreeMap<&'a str, Vec<&'a str>>,
+        index: usize,
+        indices: BTreeMap<&'a str, usize>,
+        low_links: BTreeMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: HashSet<&'a str>,
+        cycles: Vec<Vec<&'a str>>,
+    }
+
+    impl<'a> Tarjan<'a, '_> {
+        fn visit(&mut self, id: &'a str) {
+            self.indices.insert(id, self.index);
+            self.low_links.insert(id, self.index);
+            self.index += 1;
+            self.stack.push(id);
+            self.on_stack.insert(id);
+
+            let successors = self.successors;
+            for &next in &successors[id] {
+                if !self.indices.contains_key(next) {
+                    self.visit(next);
+                    let low = self.low_links[id].min(self.low_links[next]);
+                    self.low_links.insert(id, low);
+                } else if self.on_stack.contains(next) {
+                    let low = self.low_links[id].min(self.indices[next]);
+                    self.low_links.insert(id, low);
+                }
+            }
+
+            if self.low_links[id] == self.indices[id] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member);
+                    if member == id {
+                        break;
+                    }
+                }
+                if component.len() > 1 || successors[id].contains(&id) {
+                    component.sort_unstable();
+                    self.cycles.push(component);
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        successors,
+        index: 0,
+        indices: BTreeMap::new(),
+        low_links: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        cycles: Vec::new(),
+    };
+    for &id in successors.keys() {
+        if !tarjan.indices.contains_key(id) {
+            tarjan.visit(id);
+        }
+    }
+    tarjan.cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_models::graph::graph_definition::WorkflowPlanInput;
+    use serde_json::json;
+
+    fn graph(activities: Value) -> Graph {
+        let workflow: WorkflowPlanInput =
+            serde_json::from_value(json!({ "plan_name": "validation", "activities": activities })).unwrap();
+        workflow.into()
+    }
+
+    fn tool_activity(id: &str, tool: &str, params: Value, dependencies: Value) -> Value {
+        json!({
+            "id": id,
+            "description": id,
+            "type": "test",
+            "activity_type": "direct_tool_use",
+            "agent": {},
+            "tools": [{ "tool_to_use": tool, "tool_parameters": params }],
+            "tasks": [],
+            "dependencies": dependencies,
+            "expected_outcome": ""
+        })
+    }
+
+    #[test]
+    fn test_valid_graph_passes() {
+        let graph = graph(json!([
+            tool_activity("a", "lookup", json!({}), json!([])),
+            tool_activity("b", "weather", json!({ "location": "{{a.city}}" }), json!([{ "source": "a" }]))
+        ]));
+        let capabilities = Capabilities::default().with_tools(["lookup".to_string(), "weather".to_string()]);
+        assert_eq!(validate(&graph, &capabilities), Ok(()));
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let graph = graph(json!([
+            tool_activity("a", "lookup", json!({ "id": "{{c}}" }), json!([])),
+            tool_activity("b", "teleport", json!({}), json!([{ "source": "c" }])),
+            tool_activity("c", "lookup", json!({}), json!([{ "source": "b" }]))
+        ]));
+        let capabilities = Capabilities::default().with_tools(["lookup".to_string()]);
+        let issues = validate(&graph, &capabilities).unwrap_err().issues;
+
+        assert!(issues.contains(&ValidationIssue::Cycle {
+            nodes: vec!["b".to_string(), "c".to_string()]
+        }));
+        assert!(issues.contains(&ValidationIssue::UnknownTool {
+            node: "b".to_string(),
+            tool_id: "teleport".to_string()
+        }));
+        assert!(issues.contains(&ValidationIssue::NonAncestorReference {
+            node: "a".to_string(),
+            reference: "c".to_string()
+        }));
+    }
+
+    #[test]
+    fn test_reports_unknown_policy_references() {
+        let mut reserve = tool_activity("reserve", "reserve", json!({}), json!([]));
+        reserve["on_error"] = json!({ "strategy": "fallback", "node": "notify" });
+        reserve["compensation"] = json!({ "type": "tool", "tool_to_use": "refund", "tool_parameters": { "id": "{{charge.id}}" } });
+        let mut enrich = tool_activity("enrich", "lookup", json!({}), json!([{ "source": "reserve" }]));
+        enrich["sub_workflow"] = json!({ "name": "enrich_customer" });
+        let workflow = json!({ "plan_name": "policies", "activities": [reserve, enrich] });
+
+        let policies = ExecutionPolicies::from_workflow_json(&workflow).unwrap();
+        let graph: Graph = serde_json::from_value::<WorkflowPlanInput>(workflow).unwrap().into();
+        let capabilities = Capabilities::default()
+            .with_tools(["reserve".to_string(), "lookup".to_string()])
+            .with_workflows(["enrich_order".to_string()]);
+        let issues = validate_plan(&graph, &policies, &capabilities).unwrap_err().issues;
+
+        assert_eq!(
+            issues,
+            [
+                ValidationIssue::UnknownWorkflow {
+                    node: "enrich".to_string(),
+                    workflow: "enrich_customer".to_string()
+                },
+                ValidationIssue::UnknownFallback {
+                    node: "reserve".to_string(),
+                    fallback: "notify".to_string()
+                },
+                ValidationIssue::UnknownTool {
+                    node: "reserve".to_string(),
+                    tool_id: "refund".to_string()
+                },
+                ValidationIssue::UnknownReference {
+                    node: "reserve".to_string(),
+                    reference: "charge".to_string()
+                },
+            ]
+        );
+
+        let capabilities = capabilities
+            .with_tools(["reserve".to_string(), "lookup".to_string(), "refund".to_string()])
+            .with_workflows(["enrich_customer".to_string()]);
+        let mut policies = policies;
+        policies.activities.get_mut("reserve").unwrap().on_error = Some(OnErrorStrategy::Compensate);
+        policies.activities.get_mut("reserve").unwrap().compensation = Some(CompensationAction::Tool {
+            tool_to_use: "refund".to_string(),
+            tool_parameters: json!({ "id": "{{reserve.id}}" }),
+        });
+        assert_eq!(validate_plan(&graph, &policies, &capabilities), Ok(()));
+    }
+
+    #[test]
+    fn test_example_workflows_are_valid() {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("example_workflow");
+        let mut checked = 0;
+        for entry in std::fs::read_dir(&directory).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+                continue;
+            }
+            let document = std::fs::read_to_string(&path).unwrap();
+            let workflow: WorkflowPlanInput = serde_json::from_str(&document)
+                .unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            let graph: Graph = workflow.into();
+            if let Err(e) = validate(&graph, &Capabilities::default()) {
+                panic!("{}: {}", path.display(), e);
+            }
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+}
@@ -0,0 +1,160 @@
+use agent_models::graph::graph_definition::Graph;
+
+use crate::graph::config::{load_workflow_from_file, ConfigurationError};
+use crate::graph::execution_policy::{ExecutionPolicies, WorkflowReference};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum WorkflowRegistryError {
+    #[error("No workflow registered under the name '{0}'")]
+    UnknownWorkflow(String),
+    #[error("Failed to load workflow file '{path}': {source}")]
+    LoadFailed {
+        path: String,
+        #[source]
+        source: ConfigurationError,
+    },
+}
+
+/// A workflow ready to be run as a child plan.
+#[derive(Debug, Clone)]
+pub struct ResolvedWorkflow {
+    /// Identity of the workflow, used to detect cycles between workflows:
+    /// the canonical path of its file, or its registered name for in-memory workflows.
+    pub source: String,
+    pub graph: Graph,
+    pub execution_policies: ExecutionPolicies,
+}
+
+/// Workflows that `sub_workflow` activities may run, by registered name or by file path.
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowRegistry {
+    base_dir: Option<PathBuf>,
+    workflows: HashMap<String, ResolvedWorkflow>,
+}
+
+impl WorkflowRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Directory relative workflow paths are resolved against, instead of the working directory.
+    pub fn with_base_dir(mut self, base_dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = Some(base_dir.into());
+        self
+    }
+
+    /// Registers every `.json` workflow file of `dir` under its file name without extension,
+    /// e.g. `enrich_customer.json` as `enrich_customer`. Relative paths are resolved against `dir`.
+    pub fn from_dir(dir: impl Into<PathBuf>) -> Result<Self, WorkflowRegistryError> {
+        let dir = dir.into();
+        let read_failed = |e: std::io::Error| WorkflowRegistryError::LoadFailed {
+            path: dir.display().to_string(),
+            source: ConfigurationError::FileReadError(e),
+        };
+
+        let mut registry = Self::new().with_base_dir(dir.clone());
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(&dir).map_err(read_failed)? {
+            let path = entry.map_err(read_failed)?.path();
+            if path.is_file() && path.extension().is_some_and(|extension| extension == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            registry.register_file(name, &path.to_string_lossy())?;
+        }
+        Ok(registry)
+    }
+
+    /// Names `sub_workflow` activities may refer to.
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.workflows.keys()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.workflows.contains_key(name)
+    }
+
+    /// Registers an in-memory workflow under `name`.
+    pub fn register(&mut self, name: impl Into<String>, graph: Graph, execution_policies: ExecutionPolicies) {
+        let name = name.into();
+        self.workflows.insert(
+            name.clone(),
+            ResolvedWorkflow {
+                source: name,
+                graph,
+                execution_policies,
+            },
+        );
+    }
+
+    /// Loads a workflow file and registers it under `name`.
+    pub fn register_file(&mut self, name: impl Into<String>, path: &str) -> Result<(), WorkflowRegistryError> {
+        let workflow = self.load(path)?;
+        self.workflows.insert(name.into(), workflow);
+        Ok(())
+    }
+
+    pub fn resolve(&self, reference: &WorkflowReference) -> Result<ResolvedWorkflow, WorkflowRegistryError> {
+        match reference {
+            WorkflowReference::Name(name) => self
+                .workflows
+                .get(name)
+                .cloned()
+                .ok_or_else(|| WorkflowRegistryError::UnknownWorkflow(name.clone())),
+            WorkflowReference::Path(path) => self.load(path),
+        }
+    }
+
+    fn load(&self, path: &str) -> Result<ResolvedWorkflow, WorkflowRegistryError> {
+        let full_path = match &self.base_dir {
+            Some(base_dir) if Path::new(path).is_relative() => base_dir.join(path),
+            _ => PathBuf::from(path),
+        };
+        let load_failed = |source: ConfigurationError| WorkflowRegistryError::LoadFailed {
+            path: full_path.display().to_string(),
+            source,
+        };
+
+        let source = full_path
+            .canonicalize()
+            .map_err(|e| load_failed(ConfigurationError::FileReadError(e)))?;
+        let (graph, execution_policies) =
+            load_workflow_from_file(&source.to_string_lossy()).map_err(load_failed)?;
+
+        Ok(ResolvedWorkflow {
+            source: source.display().to_string(),
+            graph,
+            execution_policies,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_loads_every_workflow_of_a_directory() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("example_workflow");
+        let registry = WorkflowRegistry::from_dir(&directory).unwrap();
+
+        let mut names: Vec<&String> = registry.names().collect();
+        names.sort();
+        assert_eq!(names, ["auto_generated", "multi_agent_workflow"]);
+        let resolved = registry
+            .resolve(&WorkflowReference::Name("auto_generated".to_string()))
+            .unwrap();
+        assert!(!resolved.graph.nodes.is_empty());
+        assert!(registry.contains("multi_agent_workflow"));
+        assert!(!registry.contains("archive"));
+    }
+}
@@ -2,3 +2,6 @@ pub mod graph;
 pub mod tasks;
 pub mod agent_communication;
 pub mod tools;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
@@ -1,30 +1,517 @@
-use std::collections::HashMap;
+use regex::Regex;
 use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConditionError {
+    #[error("Invalid condition syntax at position {position}: {message}")]
+    Parse { position: usize, message: String },
+    #[error("Unknown reference '{0}' in condition: node has no recorded outcome")]
+    UnknownReference(String),
+    #[error("Type error in condition: {0}")]
+    Type(String),
+    #[error("Invalid regular expression '{0}': {1}")]
+    InvalidRegex(String, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Matches,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Path(Vec<PathSegment>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+}
+
+/// Evaluates the expression language used by `Edge.condition`.
+///
+/// Supported syntax:
+/// - literals: `42`, `-1.5`, `'text'`, `"text"`, `true`, `false`, `null`
+/// - paths: `result`, `result.status`, `result.items[0].id`, `other_node.field`
+///   (`result` is the outcome of the edge's source node, any other root is the id of a completed node)
+/// - comparisons: `==`, `!=`, `<`, `<=`, `>`, `>=`, `contains`, `matches` (regex)
+/// - logic: `&&`, `||`, `!` and parentheses
+///
+/// Missing object keys and out-of-range indices resolve to `null`; an unknown root
+/// reference, a type mismatch or a syntax error is reported as a `ConditionError`.
+///
+/// `source_node_id` is the node the edge starts from, bound to `result`.
+pub fn evaluate_condition(
+    condition: &str,
+    source_node_id: &str,
+    outcomes: &HashMap<String, Value>,
+) -> Result<bool, ConditionError> {
+    let expr = parse_condition(condition)?;
+    match expr.evaluate(source_node_id, outcomes)? {
+        Value::Bool(b) => Ok(b),
+        other => Err(ConditionError::Type(format!(
+            "condition must evaluate to a boolean, got {}",
+            other
+        ))),
+    }
+}
+
+pub fn parse_condition(condition: &str) -> Result<Expr, ConditionError> {
+    let tokens = tokenize(condition)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    if let Some((position, token)) = parser.tokens.get(parser.pos) {
+        return Err(ConditionError::Parse {
+            position: *position,
+            message: format!("unexpected token {:?}", token),
+        });
+    }
+    Ok(expr)
+}
+
+impl Expr {
+    pub fn evaluate(
+        &self,
+        source_node_id: &str,
+        outcomes: &HashMap<String, Value>,
+    ) -> Result<Value, ConditionError> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Path(segments) => resolve_path(segments, source_node_id, outcomes),
+            Expr::Not(inner) => Ok(Value::Bool(!as_bool(&inner.evaluate(source_node_id, outcomes)?, "!")?)),
+            Expr::And(left, right) => {
+                if !as_bool(&left.evaluate(source_node_id, outcomes)?, "&&")? {
+                    return Ok(Value::Bool(false));
+                }
+                Ok(Value::Bool(as_bool(&right.evaluate(source_node_id, outcomes)?, "&&")?))
+            }
+            Expr::Or(left, right) => {
+                if as_bool(&left.evaluate(source_node_id, outcomes)?, "||")? {
+                    return Ok(Value::Bool(true));
+                }
+                Ok(Value::Bool(as_bool(&right.evaluate(source_node_id, outcomes)?, "||")?))
+            }
+            Expr::Compare(left, op, right) => {
+                let left = left.evaluate(source_node_id, outcomes)?;
+                let right = right.evaluate(source_node_id, outcomes)?;
+                compare(&left, *op, &right).map(Value::Bool)
+            }
+        }
+    }
+}
+
+fn as_bool(value: &Value, operator: &str) -> Result<bool, ConditionError> {
+    value.as_bool().ok_or_else(|| {
+        ConditionError::Type(format!("operand of '{}' must be a boolean, got {}", operator, value))
+    })
+}
+
+fn resolve_path(
+    segments: &[PathSegment],
+    source_node_id: &str,
+    outcomes: &HashMap<String, Value>,
+) -> Result<Value, ConditionError> {
+    let (root, rest) = match segments.split_first() {
+        Some((PathSegment::Key(root), rest)) => (root, rest),
+        _ => return Err(ConditionError::Type("path must start with a name".to_string())),
+    };
+
+    let node_id = if root == "result" { source_node_id } else { root.as_str() };
+    let mut current = outcomes
+        .get(node_id)
+        .cloned()
+        .ok_or_else(|| ConditionError::UnknownReference(node_id.to_string()))?;
+
+    for segment in rest {
+        // Agent outcomes are often JSON documents carried as strings
+        if let Value::String(s) = &current {
+            if let Ok(parsed) = serde_json::from_str::<Value>(s) {
+                current = parsed;
+            }
+        }
+        current = match segment {
+            PathSegment::Key(key) => current.get(key).cloned().unwrap_or(Value::Null),
+            PathSegment::Index(index) => current.get(index).cloned().unwrap_or(Value::Null),
+        };
+    }
+    Ok(current)
+}
+
+fn compare(left: &Value, op: CompareOp, right: &Value) -> Result<bool, ConditionError> {
+    match op {
+        CompareOp::Eq => Ok(values_equal(left, right)),
+        CompareOp::Ne => Ok(!values_equal(left, right)),
+        CompareOp::Lt | CompareOp::Le | CompareOp::Gt | CompareOp::Ge => {
+            let ordering = match (left, right) {
+                (Value::Number(a), Value::Number(b)) => a
+                    .as_f64()
+                    .zip(b.as_f64())
+                    .and_then(|(a, b)| a.partial_cmp(&b)),
+                (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                ConditionError::Type(format!("cannot order {} and {}", left, right))
+            })?;
+            Ok(match op {
+                CompareOp::Lt => ordering == Ordering::Less,
+                CompareOp::Le => ordering != Ordering::Greater,
+                CompareOp::Gt => ordering == Ordering::Greater,
+                _ => ordering != Ordering::Less,
+            })
+        }
+        CompareOp::Contains => match (left, right) {
+            (Value::String(haystack), Value::String(needle)) => Ok(haystack.contains(needle.as_str())),
+            (Value::Array(items), needle) => Ok(items.iter().any(|item| values_equal(item, needle))),
+            (Value::Object(map), Value::String(key)) => Ok(map.contains_key(key)),
+            _ => Err(ConditionError::Type(format!("{} cannot contain {}", left, right))),
+        },
+        CompareOp::Matches => match (left, right) {
+            (Value::String(text), Value::String(pattern)) => Regex::new(pattern)
+                .map(|re| re.is_match(text))
+                .map_err(|e| ConditionError::InvalidRegex(pattern.clone(), e.to_string())),
+            _ => Err(ConditionError::Type(format!(
+                "'matches' expects a string and a pattern, got {} and {}",
+                left, right
+            ))),
+        },
+    }
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => left == right,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Str(String),
+    Dot,
+    LBracket,
+    RBracket,
+    LParen,
+    RParen,
+    Not,
+    And,
+    Or,
+    Op(CompareOp),
+}
 
-pub fn evaluate_condition(condition: &str, dependencies: &HashMap<String, Value>) -> bool {
-    // This is a very simple evaluator for demonstration purposes.
-    // It can be replaced with a more robust expression language engine if needed.
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ConditionError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
 
-    // First, replace placeholders like `result` with actual values
-    let mut replaced_condition = condition.to_string();
-    if let Some(result_value) = dependencies.values().next() {
-        // Convert the serde_json::Value to a string for simple evaluation
-        let result_str = result_value.to_string();
-        replaced_condition = replaced_condition.replace("result", &format!("'{}'", result_str.trim_matches('"'))); // Trim quotes if it was a JSON string
+    let parse_error = |position: usize, message: &str| ConditionError::Parse {
+        position,
+        message: message.to_string(),
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        let next = chars.get(i + 1).copied();
+
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '.' => Token::Dot,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '&' if next == Some('&') => {
+                i += 1;
+                Token::And
+            }
+            '|' if next == Some('|') => {
+                i += 1;
+                Token::Or
+            }
+            '=' if next == Some('=') => {
+                i += 1;
+                Token::Op(CompareOp::Eq)
+            }
+            '!' if next == Some('=') => {
+                i += 1;
+                Token::Op(CompareOp::Ne)
+            }
+            '!' => Token::Not,
+            '<' if next == Some('=') => {
+                i += 1;
+                Token::Op(CompareOp::Le)
+            }
+            '<' => Token::Op(CompareOp::Lt),
+            '>' if next == Some('=') => {
+                i += 1;
+                Token::Op(CompareOp::Ge)
+            }
+            '>' => Token::Op(CompareOp::Gt),
+            '\'' | '"' => {
+                let quote = c;
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(parse_error(start, "unterminated string literal")),
+                        Some('\\') if chars.get(i + 1).is_some() => {
+                            text.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        Some(&ch) if ch == quote => break,
+                        Some(&ch) => {
+                            text.push(ch);
+                            i += 1;
+                        }
+                    }
+                }
+                Token::Str(text)
+            }
+            c if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) => {
+                let mut end = i + 1;
+                while end < chars.len() && (chars[end].is_ascii_digit() || chars[end] == '.') {
+                    end += 1;
+                }
+                let literal: String = chars[i..end].iter().collect();
+                let number = literal
+                    .parse::<f64>()
+                    .map_err(|_| parse_error(start, &format!("invalid number '{}'", literal)))?;
+                i = end - 1;
+                Token::Number(number)
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut end = i + 1;
+                while end < chars.len()
+                    && (chars[end].is_alphanumeric() || chars[end] == '_' || chars[end] == '-')
+                {
+                    end += 1;
+                }
+                let word: String = chars[i..end].iter().collect();
+                i = end - 1;
+                match word.as_str() {
+                    "contains" => Token::Op(CompareOp::Contains),
+                    "matches" => Token::Op(CompareOp::Matches),
+                    _ => Token::Ident(word),
+                }
+            }
+            other => return Err(parse_error(start, &format!("unexpected character '{}'", other))),
+        };
+        tokens.push((start, token));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
     }
 
-    if replaced_condition.contains("==") {
-        let parts: Vec<&str> = replaced_condition.split("==").map(|s| s.trim()).collect();
-        if parts.len() == 2 {
-            return parts[0].trim_matches('\'') == parts[1].trim_matches('\'');
+    fn error(&self, message: &str) -> ConditionError {
+        let position = self
+            .tokens
+            .get(self.pos)
+            .map(|(position, _)| *position)
+            .unwrap_or_else(|| self.tokens.last().map(|(p, _)| p + 1).unwrap_or(0));
+        ConditionError::Parse {
+            position,
+            message: message.to_string(),
         }
-    } else if replaced_condition.contains("!=") {
-        let parts: Vec<&str> = replaced_condition.split("!=").map(|s| s.trim()).collect();
-        if parts.len() == 2 {
-            return parts[0].trim_matches('\'') != parts[1].trim_matches('\'');
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ConditionError> {
+        if self.peek() == Some(&expected) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {:?}", expected)))
         }
     }
 
-    // Default to true if the condition is malformed or not an equality check
-    true
+    fn parse_or(&mut self) -> Result<Expr, ConditionError> {
+        let mut left = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ConditionError> {
+        let mut left = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            left = Expr::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ConditionError> {
+        if self.peek() == Some(&Token::Not) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ConditionError> {
+        let left = self.parse_primary()?;
+        if let Some(Token::Op(op)) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.parse_primary()?;
+            return Ok(Expr::Compare(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ConditionError> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| self.error("unexpected end of condition"))?;
+        self.pos += 1;
+
+        match token {
+            Token::LParen => {
+                let inner = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Token::Str(text) => Ok(Expr::Literal(Value::String(text))),
+            Token::Number(number) => Ok(Expr::Literal(
+                serde_json::Number::from_f64(number)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null),
+            )),
+            Token::Ident(word) => match word.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                _ => self.parse_path(word),
+            },
+            _ => {
+                self.pos -= 1;
+                Err(self.error(&format!("unexpected token {:?}", token)))
+            }
+        }
+    }
+
+    fn parse_path(&mut self, root: String) -> Result<Expr, ConditionError> {
+        let mut segments = vec![PathSegment::Key(root)];
+        loop {
+            match self.peek() {
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    match self.peek().cloned() {
+                        Some(Token::Ident(key)) => {
+                            self.pos += 1;
+                            segments.push(PathSegment::Key(key));
+                        }
+                        _ => return Err(self.error("expected a field name after '.'")),
+                    }
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    match self.peek().cloned() {
+                        Some(Token::Number(n)) if n >= 0.0 && n.fract() == 0.0 => {
+                            self.pos += 1;
+                            segments.push(PathSegment::Index(n as usize));
+                        }
+                        Some(Token::Str(key)) => {
+                            self.pos += 1;
+                            segments.push(PathSegment::Key(key));
+                        }
+                        _ => return Err(self.error("expected an index or a quoted key inside '[]'")),
+                    }
+                    self.expect(Token::RBracket)?;
+                }
+                _ => return Ok(Expr::Path(segments)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn outcomes() -> HashMap<String, Value> {
+        let mut outcomes = HashMap::new();
+        outcomes.insert(
+            "fetch".to_string(),
+            json!({"status": "ok", "count": 3, "items": [{"id": "a1"}], "message": "Hello, Jupiter!"}),
+        );
+        outcomes.insert("greet".to_string(), json!("Hello, Jupiter!"));
+        outcomes
+    }
+
+    #[test]
+    fn test_paths_and_comparisons() {
+        let outcomes = outcomes();
+        assert_eq!(evaluate_condition("result.status == 'ok'", "fetch", &outcomes), Ok(true));
+        assert_eq!(evaluate_condition("result.count >= 3 && result.count < 4", "fetch", &outcomes), Ok(true));
+        assert_eq!(evaluate_condition("result.items[0].id != \"a1\"", "fetch", &outcomes), Ok(false));
+        assert_eq!(evaluate_condition("result == 'Hello, Jupiter!'", "greet", &outcomes), Ok(true));
+        assert_eq!(evaluate_condition("result.missing == null", "fetch", &outcomes), Ok(true));
+    }
+
+    #[test]
+    fn test_logic_contains_matches_and_references() {
+        let outcomes = outcomes();
+        assert_eq!(
+            evaluate_condition("!(result contains 'Mars') || fetch.count > 10", "greet", &outcomes),
+            Ok(true)
+        );
+        assert_eq!(evaluate_condition("fetch.message matches '^Hello, [A-Z]'", "greet", &outcomes), Ok(true));
+        assert_eq!(evaluate_condition("result contains 'status'", "fetch", &outcomes), Ok(true));
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let outcomes = outcomes();
+        assert!(matches!(
+            evaluate_condition("result.status = 'ok'", "fetch", &outcomes),
+            Err(ConditionError::Parse { .. })
+        ));
+        assert_eq!(
+            evaluate_condition("unknown_node.status == 'ok'", "fetch", &outcomes),
+            Err(ConditionError::UnknownReference("unknown_node".to_string()))
+        );
+        assert!(matches!(
+            evaluate_condition("result.status", "fetch", &outcomes),
+            Err(ConditionError::Type(_))
+        ));
+        assert!(matches!(
+            evaluate_condition("result.status matches '('", "fetch", &outcomes),
+            Err(ConditionError::InvalidRegex(_, _))
+        ));
+    }
 }
@@ -0,0 +1,360 @@
+use crate::tasks::condition_evaluator::PathSegment;
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// Root of a placeholder bound to the original user query, unless an activity has this id.
+pub const USER_QUERY_REFERENCE: &str = "user_query";
+
+/// Roots bound by the executor rather than by an activity: by `for_each` (`item`, `item_index`)
+/// and `loop` (`iteration`, `previous`) activities, and in sub-workflows (`input`).
+pub const RESERVED_BINDINGS: [&str; 5] = ["item", "item_index", "iteration", "previous", "input"];
+
+/// Segment accepted right after the root as an alias of the whole outcome,
+/// as in `{{activity_1.activity_output}}`, when the outcome has no such field.
+const WHOLE_OUTPUT_ALIAS: &str = "activity_output";
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum InterpolationError {
+    #[error("Invalid placeholder '{{{{{placeholder}}}}}': {message}")]
+    InvalidPlaceholder { placeholder: String, message: String },
+    #[error("No value for placeholder '{{{{{0}}}}}' and no default")]
+    MissingValue(String),
+}
+
+/// A parsed `{{root.path[0].to.value | default}}` placeholder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    pub root: String,
+    pub path: Vec<PathSegment>,
+    /// Used when the value is missing or `null`. Parsed as JSON, or taken as plain text.
+    pub default: Option<Value>,
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{(.*?)\}\}").unwrap())
+}
+
+/// Raw expressions of the placeholders found in `text`, without their braces.
+pub fn placeholder_expressions(text: &str) -> impl Iterator<Item = &str> {
+    placeholder_regex()
+        .captures_iter(text)
+        .filter_map(|caps| caps.get(1))
+        .map(|expression| expression.as_str())
+}
+
+impl Placeholder {
+    pub fn parse(expression: &str) -> Result<Self, InterpolationError> {
+        let invalid = |message: &str| InterpolationError::InvalidPlaceholder {
+            placeholder: expression.to_string(),
+            message: message.to_string(),
+        };
+
+        let separator = default_separator(expression).map(|index| expression.split_at(index));
+        let (path, default) = match separator {
+            Some((path, default)) => {
+                let default = default[1..].trim();
+                if default.is_empty() {
+                    return Err(invalid("empty default value"));
+                }
+                let default = serde_json::from_str(default).unwrap_or_else(|_| Value::String(default.to_string()));
+                (path.trim(), Some(default))
+            }
+            None => (expression.trim(), None),
+        };
+
+        let chars: Vec<char> = path.chars().collect();
+        let mut i = 0;
+        let read_name = |i: &mut usize| {
+            let start = *i;
+            while *i < chars.len() && chars[*i] != '.' && chars[*i] != '[' {
+                *i += 1;
+            }
+            chars[start..*i].iter().collect::<String>()
+        };
+
+        let root = read_name(&mut i);
+        if root.is_empty() {
+            return Err(invalid("missing activity id"));
+        }
+
+        let mut segments = Vec::new();
+        while i < chars.len() {
+            match chars[i] {
+                '.' => {
+                    i += 1;
+                    let key = read_name(&mut i);
+                    if key.is_empty() {
+                        return Err(invalid("empty field name"));
+                    }
+                    segments.push(PathSegment::Key(key));
+                }
+                '[' => {
+                    let close = closing_bracket(&chars, i).ok_or_else(|| invalid("unclosed '['"))?;
+                    let inner: String = chars[i + 1..close].iter().collect();
+                    let inner = inner.trim();
+                    let segment = if let Ok(index) = inner.parse::<usize>() {
+                        PathSegment::Index(index)
+                    } else if inner.len() >= 2
+                        && ((inner.starts_with('"') && inner.ends_with('"'))
+                            || (inner.starts_with('\'') && inner.ends_with('\'')))
+                    {
+                        PathSegment::Key(inner[1..inner.len() - 1].to_string())
+                    } else {
+                        return Err(invalid("brackets must hold an index or a quoted key"));
+                    };
+                    segments.push(segment);
+                    i = close + 1;
+                }
+                _ => return Err(invalid("unexpected character")),
+            }
+        }
+
+        Ok(Self {
+            root,
+            path: segments,
+            default,
+        })
+    }
+}
+
+/// Byte index of the `|` introducing the default value, ignoring those within brackets or quotes,
+/// as in `{{a["x|y"]}}`.
+fn default_separator(expression: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut depth = 0usize;
+    for (index, c) in expression.char_indices() {
+        match (quote, c) {
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') if depth > 0 => quote = Some(c),
+            (None, '[') => depth += 1,
+            (None, ']') => depth = depth.saturating_sub(1),
+            (None, '|') if depth == 0 => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Index of the `]` closing the `[` at `open`, skipping quoted keys.
+fn closing_bracket(chars: &[char], open: usize) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in chars.iter().enumerate().skip(open + 1) {
+        match (quote, *c) {
+            (Some(open_quote), c) if c == open_quote => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(*c),
+            (None, ']') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replaces the `{{...}}` placeholders found in activity parameters.
+///
+/// - `{{activity_1}}` is the whole outcome of `activity_1`, parsed as JSON when possible
+/// - `{{activity_1.items[0].id}}` or `{{activity_1["some key"]}}` navigates into that outcome
+/// - `{{user_query}}` is the original user query
+/// - `{{activity_1.id | "unknown"}}` falls back to a default when the value is missing or `null`
+/// - bindings set with `with_bindings`, e.g. `{{item.id}}`, take precedence over outcomes
+///
+/// A string made of a single placeholder is replaced by the value itself, keeping its JSON type.
+/// Placeholders embedded in a longer string are rendered as text.
+/// Objects and arrays are processed recursively.
+pub struct Interpolator<'a> {
+    outcomes: &'a HashMap<String, String>,
+    user_query: &'a str,
+    bindings: Option<&'a HashMap<String, Value>>,
+}
+
+impl<'a> Interpolator<'a> {
+    pub fn new(outcomes: &'a HashMap<String, String>, user_query: &'a str) -> Self {
+        Self {
+            outcomes,
+            user_query,
+            bindings: None,
+        }
+    }
+
+    pub fn with_bindings(mut self, bindings: &'a HashMap<String, Value>) -> Self {
+        self.bindings = Some(bindings);
+        self
+    }
+
+    pub fn interpolate(&self, value: &mut Value) -> Result<(), InterpolationError> {
+        match value {
+            Value::String(text) => {
+                if let Some(interpolated) = self.interpolate_str(text)? {
+                    *value = interpolated;
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.interpolate(item)?;
+                }
+            }
+            Value::Object(map) => {
+                for item in map.values_mut() {
+                    self.interpolate(item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Interpolated value of `text`, or `None` when it holds no placeholder.
+    pub fn interpolate_str(&self, text: &str) -> Result<Option<Value>, InterpolationError> {
+        let regex = placeholder_regex();
+        let Some(first) = regex.find(text) else {
+            return Ok(None);
+        };
+
+        if first.start() == 0 && first.end() == text.len() {
+            let expression = &text[2..text.len() - 2];
+            return self.resolve(&Placeholder::parse(expression)?).map(Some);
+        }
+
+        let mut rendered = String::with_capacity(text.len());
+        let mut last_end = 0;
+        for caps in regex.captures_iter(text) {
+            let whole = caps.get(0).unwrap();
+            rendered.push_str(&text[last_end..whole.start()]);
+            match self.resolve(&Placeholder::parse(&caps[1])?)? {
+                Value::String(s) => rendered.push_str(&s),
+                other => rendered.push_str(&other.to_string()),
+            }
+            last_end = whole.end();
+        }
+        rendered.push_str(&text[last_end..]);
+        Ok(Some(Value::String(rendered)))
+    }
+
+    fn resolve(&self, placeholder: &Placeholder) -> Result<Value, InterpolationError> {
+        let binding = self.bindings.and_then(|bindings| bindings.get(&placeholder.root));
+        let root_value = match (binding, self.outcomes.get(&placeholder.root)) {
+            (Some(value), _) => Some(value.clone()),
+            (None, Some(outcome)) => Some(serde_json::from_str(outcome).unwrap_or_else(|_| Value::String(outcome.clone()))),
+            (None, None) if placeholder.root == USER_QUERY_REFERENCE => Some(Value::String(self.user_query.to_string())),
+            (None, None) => None,
+        };
+
+        match root_value.and_then(|value| navigate(value, &placeholder.path)) {
+            Some(value) if !value.is_null() => Ok(value),
+            _ => placeholder
+                .default
+                .clone()
+                .ok_or_else(|| InterpolationError::MissingValue(describe(placeholder))),
+        }
+    }
+}
+
+fn navigate(mut current: Value, path: &[PathSegment]) -> Option<Value> {
+    for (position, segment) in path.iter().enumerate() {
+        // Agent outcomes are often JSON documents carried as strings
+        if let Value::String(s) = &current {
+            if let Ok(parsed @ (Value::Object(_) | Value::Array(_))) = serde_json::from_str::<Value>(s) {
+                current = parsed;
+            }
+        }
+        current = match segment {
+            PathSegment::Key(key) if position == 0 && key == WHOLE_OUTPUT_ALIAS && current.get(key).is_none() => {
+                continue;
+            }
+            PathSegment::Key(key) => current.get(key)?.clone(),
+            PathSegment::Index(index) => current.get(index)?.clone(),
+        };
+    }
+    Some(current)
+}
+
+fn describe(placeholder: &Placeholder) -> String {
+    let mut text = placeholder.root.clone();
+    for segment in &placeholder.path {
+        match segment {
+            PathSegment::Key(key) => {
+                text.push('.');
+                text.push_str(key);
+            }
+            PathSegment::Index(index) => text.push_str(&format!("[{}]", index)),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn outcomes() -> HashMap<String, String> {
+        let mut outcomes = HashMap::new();
+        outcomes.insert(
+            "activity_1".to_string(),
+            json!({"items": [{"id": "a1"}, {"id": "a2"}], "city": "Boston", "count": 2}).to_string(),
+        );
+        outcomes.insert("activity_2".to_string(), "plain text".to_string());
+        outcomes
+    }
+
+    #[test]
+    fn test_paths_nested_values_and_embedded_placeholders() {
+        let outcomes = outcomes();
+        let interpolator = Interpolator::new(&outcomes, "weather in Boston?");
+        let mut params = json!({
+            "id": "{{activity_1.items[1].id}}",
+            "count": "{{ activity_1.count }}",
+            "nested": [{"text": "{{activity_2}} for {{activity_1.city}} ({{activity_1.count}})"}],
+            "query": "{{user_query}}",
+            "whole": "{{activity_1.activity_output}}"
+        });
+        interpolator.interpolate(&mut params).unwrap();
+
+        assert_eq!(params["id"], json!("a2"));
+        assert_eq!(params["count"], json!(2));
+        assert_eq!(params["nested"][0]["text"], json!("plain text for Boston (2)"));
+        assert_eq!(params["query"], json!("weather in Boston?"));
+        assert_eq!(params["whole"]["city"], json!("Boston"));
+    }
+
+    #[test]
+    fn test_defaults_and_failures() {
+        let outcomes = outcomes();
+        let interpolator = Interpolator::new(&outcomes, "");
+
+        let mut params = json!({"id": "{{activity_1.items[5].id | \"none\"}}", "n": "{{skipped.count | 0}}"});
+        interpolator.interpolate(&mut params).unwrap();
+        assert_eq!(params, json!({"id": "none", "n": 0}));
+
+        let mut params = json!({"id": "{{activity_1.items[5].id}}"});
+        assert_eq!(
+            interpolator.interpolate(&mut params),
+            Err(InterpolationError::MissingValue("activity_1.items[5].id".to_string()))
+        );
+        assert!(matches!(
+            Placeholder::parse("activity_1.items[x]"),
+            Err(InterpolationError::InvalidPlaceholder { .. })
+        ));
+    }
+
+    #[test]
+    fn test_separators_within_quoted_keys() {
+        let placeholder = Placeholder::parse(r#"a["x|y"]['z]'] | "none""#).unwrap();
+        assert_eq!(placeholder.root, "a");
+        assert_eq!(
+            placeholder.path,
+            vec![PathSegment::Key("x|y".to_string()), PathSegment::Key("z]".to_string())]
+        );
+        assert_eq!(placeholder.default, Some(json!("none")));
+
+        let placeholder = Placeholder::parse(r#"a["x|y"]"#).unwrap();
+        assert_eq!(placeholder.path, vec![PathSegment::Key("x|y".to_string())]);
+        assert_eq!(placeholder.default, None);
+    }
+}
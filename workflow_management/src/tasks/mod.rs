@@ -2,3 +2,4 @@
 //pub mod task_registry;
 pub mod task_invoker;
 pub mod condition_evaluator;
+pub mod interpolation;
@@ -0,0 +1,56 @@
+//! Invokers answering without any remote service, for tests of plan execution.
+//! Enabled by the `testing` feature, and re-exported by `test_support`.
+
+use crate::agent_communication::agent_invoker::AgentInvoker;
+use crate::tasks::task_invoker::TaskInvoker;
+use crate::tools::tool_invoker::ToolInvoker;
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::any::Any;
+
+/// Answers `{"tool": <tool_id>, "ok": <tool_id != "fail">}`, or fails for the `broken` tool.
+pub struct EchoTools;
+
+#[async_trait]
+impl ToolInvoker for EchoTools {
+    async fn invoke(&self, tool_id: String, _params: &Value) -> anyhow::Result<Value> {
+        if tool_id == "broken" {
+            anyhow::bail!("tool '{}' is broken", tool_id);
+        }
+        Ok(json!({ "tool": tool_id, "ok": tool_id != "fail" }))
+    }
+}
+
+/// Answers `{"task": <task_id>, "params": <params>}`.
+pub struct EchoTasks;
+
+#[async_trait]
+impl TaskInvoker for EchoTasks {
+    async fn invoke(&self, task_id: String, params: &Value) -> anyhow::Result<Value> {
+        Ok(json!({ "task": task_id, "params": params }))
+    }
+}
+
+/// Fails every tool call, for plans that must not call any tool.
+pub struct NoTools;
+
+#[async_trait]
+impl ToolInvoker for NoTools {
+    async fn invoke(&self, tool_id: String, _params: &Value) -> anyhow::Result<Value> {
+        anyhow::bail!("unexpected tool '{}'", tool_id)
+    }
+}
+
+/// Fails every delegation, for plans that must not call any agent.
+pub struct NoAgents;
+
+#[async_trait]
+impl AgentInvoker for NoAgents {
+    async fn interact(&self, agent_id: String, _message: String, _skill: String) -> anyhow::Result<Value> {
+        anyhow::bail!("unexpected agent '{}'", agent_id)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}